@@ -0,0 +1,212 @@
+#[cfg(test)]
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// The most fixed steps one frame can run, so that a long stall doesn't leave every following
+/// frame running more steps than fit in it
+const MAX_STEPS_PER_ADVANCE: u32 = 8;
+
+pub trait Clock {
+    fn reset(&mut self);
+    fn tick(&mut self) -> Duration;
+}
+
+pub struct SystemClock {
+    last_time: Instant,
+}
+
+impl SystemClock {
+    pub fn new() -> Self {
+        Self {
+            last_time: Instant::now(),
+        }
+    }
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for SystemClock {
+    fn reset(&mut self) {
+        self.last_time = Instant::now();
+    }
+
+    fn tick(&mut self) -> Duration {
+        let time = Instant::now();
+        let dt = time - self.last_time;
+        self.last_time = time;
+        dt
+    }
+}
+
+#[cfg(test)]
+pub struct ScriptedClock {
+    frame_times: VecDeque<Duration>,
+}
+
+#[cfg(test)]
+impl ScriptedClock {
+    pub fn new(frame_times: impl IntoIterator<Item = Duration>) -> Self {
+        Self {
+            frame_times: frame_times.into_iter().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+impl Clock for ScriptedClock {
+    fn reset(&mut self) {}
+
+    fn tick(&mut self) -> Duration {
+        self.frame_times.pop_front().unwrap_or(Duration::ZERO)
+    }
+}
+
+pub struct FixedTimestep {
+    step: Duration,
+    accumulated: Duration,
+}
+
+impl FixedTimestep {
+    pub fn new(step: Duration) -> Self {
+        Self {
+            step,
+            accumulated: Duration::ZERO,
+        }
+    }
+
+    pub fn step(&self) -> Duration {
+        self.step
+    }
+
+    pub fn advance(&mut self, dt: Duration) -> u32 {
+        self.accumulated = (self.accumulated + dt).min(self.step * MAX_STEPS_PER_ADVANCE);
+        let mut steps = 0;
+        while self.accumulated >= self.step {
+            self.accumulated -= self.step;
+            steps += 1;
+        }
+        steps
+    }
+
+    pub fn alpha(&self) -> f32 {
+        (self.accumulated.as_nanos() as f64 / self.step.as_nanos() as f64) as f32
+    }
+
+    pub fn reset(&mut self) {
+        self.accumulated = Duration::ZERO;
+    }
+}
+
+/// Fixed steps asked for one at a time while fixed updates are paused
+#[derive(Default)]
+pub struct SingleSteps {
+    pending: u32,
+}
+
+impl SingleSteps {
+    pub fn request(&mut self) {
+        self.pending += 1;
+    }
+
+    pub fn take(&mut self) -> bool {
+        if self.pending == 0 {
+            return false;
+        }
+        self.pending -= 1;
+        true
+    }
+
+    pub fn clear(&mut self) {
+        self.pending = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accumulator_stays_reset_while_disabled() {
+        let mut fixed_timestep = FixedTimestep::new(Duration::from_millis(10));
+        assert_eq!(fixed_timestep.advance(Duration::from_millis(15)), 1);
+
+        // main resets instead of advancing while fixed updates are disabled
+        for _ in 0..100 {
+            fixed_timestep.reset();
+            assert_eq!(fixed_timestep.alpha(), 0.0);
+        }
+
+        // re-enabling doesn't catch up on the disabled frames
+        assert_eq!(fixed_timestep.advance(Duration::from_millis(15)), 1);
+        assert!((fixed_timestep.alpha() - 0.5).abs() <= 1e-6);
+    }
+
+    #[test]
+    fn each_single_step_request_runs_one_step() {
+        let mut single_steps = SingleSteps::default();
+        let mut fixed_updates = 0;
+        // main drains every requested step once per frame
+        let mut run_frame = |single_steps: &mut SingleSteps| {
+            while single_steps.take() {
+                fixed_updates += 1;
+            }
+            fixed_updates
+        };
+
+        assert_eq!(run_frame(&mut single_steps), 0);
+        for expected in 1..=5 {
+            single_steps.request();
+            assert_eq!(run_frame(&mut single_steps), expected);
+            assert_eq!(run_frame(&mut single_steps), expected);
+        }
+
+        // several presses within one frame each get their own step
+        single_steps.request();
+        single_steps.request();
+        assert_eq!(run_frame(&mut single_steps), 7);
+
+        single_steps.request();
+        single_steps.clear();
+        assert_eq!(run_frame(&mut single_steps), 7);
+    }
+
+    #[test]
+    fn scripted_frames_run_the_expected_fixed_steps() {
+        let frame_times = [5, 5, 25, 4, 1000, 15].map(Duration::from_millis);
+        let mut clock = ScriptedClock::new(frame_times);
+        let mut fixed_timestep = FixedTimestep::new(Duration::from_millis(10));
+
+        let steps = [(); 6].map(|()| fixed_timestep.advance(clock.tick()));
+        // a one second stall only catches up by the capped number of steps
+        assert_eq!(steps, [0, 1, 2, 0, MAX_STEPS_PER_ADVANCE, 1]);
+        assert!((fixed_timestep.alpha() - 0.5).abs() <= 1e-6);
+
+        // the script has run out
+        assert_eq!(fixed_timestep.advance(clock.tick()), 0);
+    }
+
+    #[test]
+    fn high_refresh_frames_dont_drift() {
+        let step = Duration::from_secs(1) / 100;
+        let frame_time = Duration::from_secs(1) / 240;
+        let mut fixed_timestep = FixedTimestep::new(step);
+        let mut steps = 0;
+        // ten minutes of frames
+        let frames = 240 * 60 * 10;
+        for _ in 0..frames {
+            steps += fixed_timestep.advance(frame_time);
+            let alpha = fixed_timestep.alpha();
+            assert!((0.0..1.0).contains(&alpha), "{alpha}");
+        }
+        let elapsed = frame_time * frames;
+        assert_eq!(steps as u128, elapsed.as_nanos() / step.as_nanos());
+        assert_eq!(
+            fixed_timestep.accumulated.as_nanos(),
+            elapsed.as_nanos() % step.as_nanos()
+        );
+    }
+}
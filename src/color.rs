@@ -1,7 +1,7 @@
 use bytemuck::{Pod, Zeroable};
 use encase::impl_vector;
 
-#[derive(Debug, Clone, Copy, Zeroable, Pod)]
+#[derive(Debug, Clone, Copy, PartialEq, Zeroable, Pod)]
 #[repr(C)]
 pub struct Color {
     pub r: f32,
@@ -34,3 +34,134 @@ impl From<Color> for [f32; 3] {
 }
 
 impl_vector!(3, Color, f32; using AsRef AsMut From);
+
+/// An rgb color that isn't limited to `0..=1`, for emissive values
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColorHDR(pub [f32; 3]);
+
+impl AsRef<[f32; 3]> for ColorHDR {
+    fn as_ref(&self) -> &[f32; 3] {
+        &self.0
+    }
+}
+
+impl AsMut<[f32; 3]> for ColorHDR {
+    fn as_mut(&mut self) -> &mut [f32; 3] {
+        &mut self.0
+    }
+}
+
+impl From<[f32; 3]> for ColorHDR {
+    fn from(value: [f32; 3]) -> Self {
+        Self(value)
+    }
+}
+
+impl From<ColorHDR> for [f32; 3] {
+    fn from(value: ColorHDR) -> [f32; 3] {
+        value.0
+    }
+}
+
+impl From<Color> for ColorHDR {
+    fn from(Color { r, g, b }: Color) -> Self {
+        Self([r, g, b])
+    }
+}
+
+impl From<ColorHDR> for Color {
+    fn from(ColorHDR([r, g, b]): ColorHDR) -> Self {
+        Self {
+            r: r.clamp(0.0, 1.0),
+            g: g.clamp(0.0, 1.0),
+            b: b.clamp(0.0, 1.0),
+        }
+    }
+}
+
+impl_vector!(3, ColorHDR, f32; using AsRef AsMut From);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gradient {
+    stops: Vec<(f32, Color)>,
+}
+
+impl Gradient {
+    /// Builds a gradient from `(position, color)` stops, with positions clamped to `0..=1`
+    pub fn new(stops: impl IntoIterator<Item = (f32, Color)>) -> Self {
+        let mut stops: Vec<_> = stops
+            .into_iter()
+            .map(|(position, color)| (position.clamp(0.0, 1.0), color))
+            .collect();
+        stops.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+        Self { stops }
+    }
+
+    pub fn sample(&self, t: f32) -> Color {
+        let next = self.stops.partition_point(|&(position, _)| position < t);
+        match (
+            next.checked_sub(1).map(|i| self.stops[i]),
+            self.stops.get(next),
+        ) {
+            (Some((start, a)), Some(&(end, b))) => {
+                let t = (t - start) / (end - start).max(f32::EPSILON);
+                Color {
+                    r: a.r + (b.r - a.r) * t,
+                    g: a.g + (b.g - a.g) * t,
+                    b: a.b + (b.b - a.b) * t,
+                }
+            }
+            (Some((_, color)), None) | (None, Some(&(_, color))) => color,
+            (None, None) => Color {
+                r: 0.0,
+                g: 0.0,
+                b: 0.0,
+            },
+        }
+    }
+
+    /// Samples the gradient at `N` evenly spaced points from 0 to 1
+    pub fn bake<const N: usize>(&self) -> [Color; N] {
+        std::array::from_fn(|i| self.sample(i as f32 / (N.max(2) - 1) as f32))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hdr_colors_keep_values_above_one() {
+        let color = Color {
+            r: 0.25,
+            g: 0.5,
+            b: 1.0,
+        };
+        assert_eq!(Color::from(ColorHDR::from(color)), color);
+
+        let emissive = ColorHDR([4.0, 0.5, -1.0]);
+        assert_eq!(<[f32; 3]>::from(emissive), [4.0, 0.5, -1.0]);
+        assert_eq!(ColorHDR::from([4.0, 0.5, -1.0]), emissive);
+        // only converting back to a plain color clamps
+        assert_eq!(
+            Color::from(emissive),
+            Color {
+                r: 1.0,
+                g: 0.5,
+                b: 0.0,
+            }
+        );
+    }
+
+    #[test]
+    fn baking_hits_both_endpoints() {
+        let [start, end] = [[0.25, 0.0, 1.0], [1.0, 1.0, 0.0]].map(|[r, g, b]| Color { r, g, b });
+        let gradient = Gradient::new([(1.0, end), (0.0, start)]);
+
+        let baked = gradient.bake::<8>();
+        assert_eq!(baked[0], start);
+        assert_eq!(baked[7], end);
+        assert_eq!(gradient.bake::<1>(), [start]);
+        assert_eq!(gradient.bake::<0>(), []);
+    }
+}
@@ -0,0 +1,77 @@
+use anyhow::{bail, Context};
+use std::{path::PathBuf, str::SplitWhitespace};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Fov(f32),
+    Speed(f32),
+    Teleport([f32; 4]),
+    Save(PathBuf),
+}
+
+pub fn parse(line: &str) -> anyhow::Result<Command> {
+    let mut words = line.split_whitespace();
+    let Some(name) = words.next() else {
+        bail!("Expected a command");
+    };
+    let command = match name {
+        "fov" => Command::Fov(parse_number(&mut words)?),
+        "speed" => Command::Speed(parse_number(&mut words)?),
+        "tp" => Command::Teleport([
+            parse_number(&mut words)?,
+            parse_number(&mut words)?,
+            parse_number(&mut words)?,
+            parse_number(&mut words)?,
+        ]),
+        "save" => Command::Save(words.next().context("Expected a path")?.into()),
+        _ => bail!("Unknown command `{name}`"),
+    };
+    if let Some(word) = words.next() {
+        bail!("Unexpected argument `{word}` for `{name}`");
+    }
+    Ok(command)
+}
+
+fn parse_number(words: &mut SplitWhitespace) -> anyhow::Result<f32> {
+    let word = words.next().context("Expected a number")?;
+    word.parse()
+        .with_context(|| format!("`{word}` is not a number"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commands_parse_to_their_actions() {
+        assert_eq!(parse("fov 90").unwrap(), Command::Fov(90.0));
+        assert_eq!(parse("  speed   10.5 ").unwrap(), Command::Speed(10.5));
+        assert_eq!(
+            parse("tp 1 -2 3.5 0").unwrap(),
+            Command::Teleport([1.0, -2.0, 3.5, 0.0])
+        );
+        assert_eq!(
+            parse("save chunks/world.chunk").unwrap(),
+            Command::Save("chunks/world.chunk".into())
+        );
+    }
+
+    #[test]
+    fn invalid_commands_are_errors() {
+        for line in [
+            "",
+            "   ",
+            "jump",
+            "fov",
+            "fov wide",
+            "fov 90 100",
+            "speed fast",
+            "tp 1 2 3",
+            "tp 1 2 3 4 5",
+            "save",
+            "save a b",
+        ] {
+            assert!(parse(line).is_err(), "`{line}` should not parse");
+        }
+    }
+}
@@ -1,7 +1,22 @@
-use crate::{color::Color, math::transform::Transform};
+use crate::{
+    clock::SingleSteps,
+    color::{Color, Gradient},
+    console::{self, Command},
+    math::{point::Point, transform::Transform},
+};
 use anyhow::{bail, Context};
-use encase::{ShaderSize, ShaderType, StorageBuffer, UniformBuffer};
-use std::{sync::Arc, time::Duration};
+use encase::{impl_vector, ShaderSize, ShaderType, StorageBuffer, UniformBuffer};
+use std::{
+    collections::VecDeque,
+    f32::consts::FRAC_PI_2,
+    fmt::Write,
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::mpsc::{Receiver, TryRecvError},
+    sync::Arc,
+    thread::JoinHandle,
+    time::Duration,
+};
 use winit::{
     dpi::PhysicalSize,
     event::KeyEvent,
@@ -15,422 +30,7686 @@ struct Camera {
     v_fov: f32,
 }
 
-const CHUNK_SIZE: usize = 4;
+#[derive(ShaderType)]
+struct RenderSettings {
+    ambient_color: Color,
+    ambient_strength: f32,
+    directional_light: u32,
+    sample_index: u32,
+    render_mode: u32,
+    split_view: u32,
+    split_screen: u32,
+    wireframe: u32,
+    voxel_margin: f32,
+    viewport_x: u32,
+    viewport_y: u32,
+    viewport_width: u32,
+    viewport_height: u32,
+    letterbox_color: Color,
+    back_faces: u32,
+    outline_thickness: f32,
+    outline_darkness: f32,
+    world_border_radius: f32,
+    srgb_encode: u32,
+    sky_zenith_color: Color,
+    sky_horizon_color: Color,
+    sky_nadir_color: Color,
+    tonemap_white_point: f32,
+    tonemap_contrast: f32,
+    exposure: f32,
+    measure_luminance: u32,
+    view_plane_gizmo: u32,
+    shadows: u32,
+    shadow_bias: f32,
+    aperture: f32,
+    focal_distance: f32,
+    custom_heatmap_gradient: u32,
+    selection_min: BlockPosition,
+    selection_max: BlockPosition,
+}
+
+impl RenderSettings {
+    /// Turns off everything drawn over the world itself, for photo mode
+    fn hide_overlays(&mut self) {
+        self.render_mode = RenderMode::Normal as u32;
+        self.split_view = 0;
+        self.split_screen = 0;
+        self.view_plane_gizmo = 0;
+        self.wireframe = 0;
+        self.world_border_radius = 0.0;
+        self.selection_min = [0; 4].into();
+        self.selection_max = [0; 4].into();
+    }
+}
 
 #[derive(ShaderType)]
-struct Block {
+struct DebugPoint {
+    x: f32,
+    y: f32,
+    radius: f32,
     color: Color,
-    exists: u32,
 }
 
 #[derive(ShaderType)]
-struct Chunk {
-    data: [Block; CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE],
+struct DebugPoints {
+    count: u32,
+    points: [DebugPoint; MAX_DEBUG_POINTS],
 }
 
-pub struct Game {
-    window: Arc<Window>,
-    device: wgpu::Device,
-    queue: wgpu::Queue,
-    surface_configuration: wgpu::SurfaceConfiguration,
-    surface: wgpu::Surface<'static>,
-    main_texture: wgpu::Texture,
-    main_texture_bind_group_layout: wgpu::BindGroupLayout,
-    main_texture_bind_group: wgpu::BindGroup,
-    camera_uniform_buffer: wgpu::Buffer,
-    camera_bind_group: wgpu::BindGroup,
-    chunk_storage_buffer: wgpu::Buffer,
-    chunk_bind_group: wgpu::BindGroup,
-    compute_pipeline: wgpu::ComputePipeline,
+#[derive(ShaderType)]
+struct ViewPlaneLine {
+    start_x: f32,
+    start_y: f32,
+    end_x: f32,
+    end_y: f32,
+}
 
-    movement_state: MovementState,
-    camera_transform: Transform,
-    camera_vertical_look: Transform,
-    chunk: Chunk,
+#[derive(ShaderType)]
+struct ViewPlaneLines {
+    count: u32,
+    lines: [ViewPlaneLine; MAX_VIEW_PLANE_LINES],
 }
 
-impl Game {
-    pub async fn new(window: Arc<Window>) -> anyhow::Result<Self> {
-        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
-            ..Default::default()
-        });
-        let surface = instance.create_surface(window.clone())?;
+#[derive(ShaderType)]
+struct HeatmapGradient {
+    colors: [Color; HEATMAP_GRADIENT_SIZE],
+}
 
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::HighPerformance,
-                force_fallback_adapter: false,
-                compatible_surface: Some(&surface),
-            })
-            .await
-            .context("Could not find an adapter")?;
+impl HeatmapGradient {
+    /// Bakes `gradient` into the lookup buffer the heatmaps sample
+    fn encode(gradient: &Gradient) -> anyhow::Result<[u8; Self::SHADER_SIZE.get() as usize]> {
+        let mut buffer = StorageBuffer::new([0; Self::SHADER_SIZE.get() as _]);
+        buffer.write(&HeatmapGradient {
+            colors: gradient.bake(),
+        })?;
+        Ok(buffer.into_inner())
+    }
+}
 
-        let (device, queue) = adapter
-            .request_device(
-                &wgpu::DeviceDescriptor {
-                    label: Some("Device"),
-                    required_features: wgpu::Features::default(),
-                    required_limits: wgpu::Limits::default(),
-                },
-                None,
-            )
-            .await?;
+/// A block's integer coordinates, as the shader's `vec4<i32>`
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct BlockPosition([i32; 4]);
 
-        let surface_capabilities = surface.get_capabilities(&adapter);
-        let PhysicalSize { width, height } = window.inner_size();
-        let surface_configuration = wgpu::SurfaceConfiguration {
-            usage: wgpu::TextureUsages::COPY_DST,
-            format: wgpu::TextureFormat::Rgba8Unorm,
-            width,
-            height,
-            present_mode: surface_capabilities
-                .present_modes
-                .iter()
-                .copied()
-                .find(|present_mode| matches!(present_mode, wgpu::PresentMode::Mailbox))
-                .unwrap_or(wgpu::PresentMode::AutoNoVsync),
-            desired_maximum_frame_latency: 2,
-            alpha_mode: wgpu::CompositeAlphaMode::Auto,
-            view_formats: vec![],
-        };
-        surface.configure(&device, &surface_configuration);
+impl AsRef<[i32; 4]> for BlockPosition {
+    fn as_ref(&self) -> &[i32; 4] {
+        &self.0
+    }
+}
 
-        let main_texture = device.create_texture(&wgpu::TextureDescriptor {
-            label: Some("Main Texture"),
-            size: wgpu::Extent3d {
-                width,
-                height,
-                depth_or_array_layers: 1,
-            },
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
-            format: surface_configuration.format,
-            usage: wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::COPY_SRC,
-            view_formats: &[],
-        });
+impl AsMut<[i32; 4]> for BlockPosition {
+    fn as_mut(&mut self) -> &mut [i32; 4] {
+        &mut self.0
+    }
+}
 
-        let main_texture_bind_group_layout =
-            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                label: Some("Main Texture Bind Group Layout"),
-                entries: &[wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::COMPUTE,
-                    ty: wgpu::BindingType::StorageTexture {
-                        access: wgpu::StorageTextureAccess::WriteOnly,
-                        format: main_texture.format(),
-                        view_dimension: wgpu::TextureViewDimension::D2,
-                    },
-                    count: None,
-                }],
-            });
-        let main_texture_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("Main Texture Bind Group"),
-            layout: &main_texture_bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: wgpu::BindingResource::TextureView(
-                    &main_texture.create_view(&wgpu::TextureViewDescriptor::default()),
-                ),
-            }],
-        });
+impl From<[i32; 4]> for BlockPosition {
+    fn from(value: [i32; 4]) -> Self {
+        Self(value)
+    }
+}
 
-        let camera_uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Camera Uniform Buffer"),
-            size: Camera::SHADER_SIZE.get(),
-            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::UNIFORM,
-            mapped_at_creation: false,
-        });
-        let camera_bind_group_layout =
-            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                label: Some("Camera Bind Group Layout"),
-                entries: &[wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::COMPUTE,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: Some(Camera::SHADER_SIZE),
-                    },
-                    count: None,
-                }],
-            });
-        let camera_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("Camera Bind Group"),
-            layout: &camera_bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: camera_uniform_buffer.as_entire_binding(),
-            }],
-        });
+impl From<BlockPosition> for [i32; 4] {
+    fn from(value: BlockPosition) -> [i32; 4] {
+        value.0
+    }
+}
 
-        let chunk_storage_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Chunk Storage Buffer"),
-            size: Chunk::SHADER_SIZE.get(),
-            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::STORAGE,
-            mapped_at_creation: false,
-        });
-        let chunk_bind_group_layout =
-            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                label: Some("Chunk Bind Group Layout"),
-                entries: &[wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::COMPUTE,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Storage { read_only: true },
-                        has_dynamic_offset: false,
-                        min_binding_size: Some(Chunk::SHADER_SIZE),
-                    },
-                    count: None,
-                }],
-            });
-        let chunk_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("Chunk Bind Group"),
-            layout: &chunk_bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: chunk_storage_buffer.as_entire_binding(),
-            }],
-        });
+impl_vector!(4, BlockPosition, i32; using AsRef AsMut From);
 
-        let compute_shader = device.create_shader_module(wgpu::include_wgsl!("./shader.wgsl"));
-        let compute_pipeline_layout =
-            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: Some("Compute Pipeline Layout"),
-                bind_group_layouts: &[
-                    &main_texture_bind_group_layout,
-                    &camera_bind_group_layout,
-                    &chunk_bind_group_layout,
-                ],
-                push_constant_ranges: &[],
-            });
-        let compute_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
-            label: Some("Compute Pipeline"),
-            layout: Some(&compute_pipeline_layout),
-            module: &compute_shader,
-            entry_point: "main",
-        });
+const CHUNK_SIZE: usize = 4;
+pub const MAX_BLOCK_HEALTH: u32 = 4;
+const LOADED_REGION_MARGIN: f32 = CHUNK_SIZE as f32;
 
-        Ok(Game {
-            window,
-            device,
-            queue,
-            surface_configuration,
-            surface,
-            main_texture,
-            main_texture_bind_group_layout,
-            main_texture_bind_group,
-            camera_uniform_buffer,
-            camera_bind_group,
-            chunk_storage_buffer,
-            chunk_bind_group,
-            compute_pipeline,
+const MAX_SCROLL_PER_SECOND: f32 = 50.0;
+const MAX_LOOK_ROTATION_PER_EVENT: f32 = 0.2;
 
-            movement_state: MovementState::default(),
-            camera_transform: Transform::translation([-4.5, 0.5, -1.5, 0.5]),
-            camera_vertical_look: Transform::IDENTITY,
-            chunk: Chunk {
-                data: std::array::from_fn(|i| {
-                    if i % 3 == 0 {
-                        Block {
-                            color: Color {
-                                r: 0.0,
-                                g: 1.0,
-                                b: 0.0,
-                            },
-                            exists: 1,
-                        }
-                    } else {
-                        Block {
-                            color: Color {
-                                r: 1.0,
-                                g: 0.0,
-                                b: 0.0,
-                            },
-                            exists: 0,
-                        }
-                    }
-                }),
-            },
-        })
-    }
+const DEFAULT_MAX_FRAME_LATENCY: u32 = 2;
 
-    pub fn keyboard(&mut self, key_event: KeyEvent) -> anyhow::Result<()> {
-        let value = if key_event.state.is_pressed() {
-            1.0
-        } else {
-            0.0
-        };
-        match key_event.physical_key {
-            PhysicalKey::Code(key_code) => match key_code {
-                KeyCode::KeyS => self.movement_state.backward = value,
-                KeyCode::KeyW => self.movement_state.forward = value,
-                KeyCode::KeyA => self.movement_state.left = value,
-                KeyCode::KeyD => self.movement_state.right = value,
-                KeyCode::ShiftLeft => self.movement_state.down = value,
-                KeyCode::Space => self.movement_state.up = value,
-                KeyCode::KeyQ => self.movement_state.kata = value,
-                KeyCode::KeyE => self.movement_state.ana = value,
-                _ => {}
-            },
-            PhysicalKey::Unidentified(_) => {}
+const FRAME_TIME_HISTORY: usize = 240;
+const FRAME_BUDGET_WARNING_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Longest frame time applied to movement, so a startup hitch can't teleport the camera
+const MAX_UPDATE_DT: Duration = Duration::from_millis(100);
+
+const COLLISION_STEP: f32 = 0.25;
+const COLLISION_CONTACT_ITERATIONS: u32 = 16;
+
+/// A margin of half a cell shrinks blocks down to nothing
+const MAX_VOXEL_MARGIN: f32 = 0.5;
+
+const MEMORY_BUDGET_WARNING_FRACTION: f64 = 0.9;
+
+const LUMINANCE_SCALE: f32 = 16.0;
+
+const MAX_DEBUG_POINTS: usize = 64;
+const MIN_DEBUG_POINT_RADIUS: f32 = 2.0;
+/// How many unit cells the view plane grid reaches out from the camera along each axis
+const VIEW_PLANE_GRID_EXTENT: i32 = 2;
+const VIEW_PLANE_GRID_NEAR: f32 = 0.1;
+/// One line per axis through each cell corner of a grid face
+const MAX_VIEW_PLANE_LINES: usize = 75;
+
+const HEATMAP_GRADIENT_SIZE: usize = 32;
+
+const MAX_POOLED_FRAME_TARGETS: usize = 4;
+
+const MAX_UNDO_ENTRIES: usize = 64;
+
+const AUTOFOCUS_SPEED: f32 = 8.0;
+const AUTOFOCUS_MAX_DISTANCE: f32 = 64.0;
+
+const SHADER_SOURCE: &str = include_str!("./shader.wgsl");
+const BLIT_SHADER_SOURCE: &str = include_str!("./blit.wgsl");
+
+const STILL_FRAMES_BEFORE_REFINEMENT: u32 = 10;
+const MAX_REFINEMENT_SAMPLES: u32 = 64;
+
+const DEFAULT_GRAVITY: [f32; 4] = [0.0, -9.81, 0.0, 0.0];
+
+#[derive(Debug, Clone, Copy, PartialEq, ShaderType)]
+pub struct Block {
+    pub color: Color,
+    pub exists: u32,
+    pub health: u32,
+}
+
+impl Block {
+    /// A solid block at full health
+    pub fn new(color: Color) -> Self {
+        Self {
+            color,
+            exists: 1,
+            health: MAX_BLOCK_HEALTH,
         }
-        Ok(())
     }
+}
 
-    pub fn cursor(&mut self, x: f32, y: f32) -> anyhow::Result<()> {
-        self.camera_vertical_look = self.camera_vertical_look * Transform::rotation_xy(y * -0.001);
-        self.camera_transform = self.camera_transform * Transform::rotation_xz(x * 0.001);
-        Ok(())
+#[derive(Clone, PartialEq, ShaderType)]
+pub struct Chunk {
+    data: [Block; CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE],
+}
+
+impl Chunk {
+    fn to_bytes(&self) -> anyhow::Result<Vec<u8>> {
+        let mut buffer = StorageBuffer::new([0; Chunk::SHADER_SIZE.get() as _]);
+        buffer.write(self)?;
+        Ok(buffer.into_inner().to_vec())
     }
 
-    pub fn scroll(&mut self, _x: f32, y: f32) -> anyhow::Result<()> {
-        self.camera_transform = self.camera_transform * Transform::rotation_xw(y * 0.01);
-        Ok(())
+    fn from_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+        Ok(StorageBuffer::new(bytes).create()?)
     }
 
-    pub fn update(&mut self, dt: Duration) -> anyhow::Result<()> {
-        self.camera_transform =
-            self.camera_transform * self.movement_state.transform(dt.as_secs_f32());
-        Ok(())
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        std::fs::write(path, self.to_bytes()?)
+            .with_context(|| format!("Could not save chunk to {}", path.display()))
     }
 
-    pub fn fixed_update(&mut self, _ts: Duration) -> anyhow::Result<()> {
-        Ok(())
+    pub fn damage_block(&mut self, position: [usize; 4], amount: u32) -> anyhow::Result<bool> {
+        let index = Chunk::block_index(position)
+            .with_context(|| format!("Block position {position:?} is outside the chunk"))?;
+        let block = &mut self.data[index];
+        if block.exists == 0 {
+            return Ok(false);
+        }
+        block.health = block.health.saturating_sub(amount);
+        if block.health == 0 {
+            block.exists = 0;
+        }
+        Ok(true)
     }
 
-    pub fn resize(&mut self, width: u32, height: u32) -> anyhow::Result<()> {
-        if width > 0 && height > 0 {
-            self.surface_configuration.width = width;
-            self.surface_configuration.height = height;
-            self.surface
-                .configure(&self.device, &self.surface_configuration);
+    pub fn diff(&self, other: &Chunk) -> Vec<(usize, Block)> {
+        std::iter::zip(&self.data, &other.data)
+            .enumerate()
+            .filter(|(_, (block, other_block))| block != other_block)
+            .map(|(index, (_, &other_block))| (index, other_block))
+            .collect()
+    }
 
-            self.main_texture = self.device.create_texture(&wgpu::TextureDescriptor {
-                label: Some("Main Texture"),
-                size: wgpu::Extent3d {
-                    width,
-                    height,
-                    depth_or_array_layers: 1,
-                },
-                mip_level_count: 1,
-                sample_count: 1,
-                dimension: wgpu::TextureDimension::D2,
-                format: self.surface_configuration.format,
-                usage: wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::COPY_SRC,
-                view_formats: &[],
-            });
-            self.main_texture_bind_group =
-                self.device.create_bind_group(&wgpu::BindGroupDescriptor {
-                    label: Some("Main Texture Bind Group"),
-                    layout: &self.main_texture_bind_group_layout,
-                    entries: &[wgpu::BindGroupEntry {
-                        binding: 0,
-                        resource: wgpu::BindingResource::TextureView(
-                            &self
-                                .main_texture
-                                .create_view(&wgpu::TextureViewDescriptor::default()),
-                        ),
-                    }],
-                });
+    pub fn apply_diff(&mut self, diff: &[(usize, Block)]) -> anyhow::Result<()> {
+        if let Some(&(index, _)) = diff.iter().find(|&&(index, _)| index >= self.data.len()) {
+            bail!("Block index {index} is outside the chunk");
+        }
+        for &(index, block) in diff {
+            self.data[index] = block;
         }
         Ok(())
     }
 
-    pub fn draw(&mut self) -> anyhow::Result<()> {
-        let texture = loop {
-            match self.surface.get_current_texture() {
-                Ok(texture) => break texture,
-                Err(e @ wgpu::SurfaceError::Timeout) => {
-                    eprintln!("{e}");
-                    return Ok(());
-                }
-                Err(wgpu::SurfaceError::Outdated) | Err(wgpu::SurfaceError::Lost) => {
-                    let PhysicalSize { width, height } = self.window.inner_size();
-                    if width == 0 || height == 0 {
-                        return Ok(());
-                    }
-                    self.resize(width, height)?;
-                }
-                Err(e @ wgpu::SurfaceError::OutOfMemory) => bail!(e),
+    pub fn copy_region(&self, min: [usize; 4], max: [usize; 4]) -> RegionClip {
+        let size = std::array::from_fn(|axis| max[axis].min(CHUNK_SIZE).saturating_sub(min[axis]));
+        let blocks = region_offsets(size)
+            .map(|offset| {
+                let position = std::array::from_fn(|axis| min[axis] + offset[axis]);
+                self.data[Chunk::block_index(position).unwrap()]
+            })
+            .collect();
+        RegionClip { size, blocks }
+    }
+
+    /// Pastes `clip` with its first corner at `at`, dropping blocks that fall outside the chunk,
+    /// and returns the overwritten blocks so the paste can be undone with `apply_diff`
+    pub fn paste_region(&mut self, clip: &RegionClip, at: [usize; 4]) -> Vec<(usize, Block)> {
+        let mut previous = vec![];
+        for (offset, &block) in region_offsets(clip.size).zip(&clip.blocks) {
+            let position = std::array::from_fn(|axis| at[axis].saturating_add(offset[axis]));
+            if let Some(index) = Chunk::block_index(position) {
+                previous.push((index, self.data[index]));
+                self.data[index] = block;
             }
-        };
+        }
+        previous
+    }
 
-        {
-            let mut buffer = UniformBuffer::new([0; Camera::SHADER_SIZE.get() as _]);
-            buffer.write(&Camera {
-                transform: self.camera_transform * self.camera_vertical_look,
-                v_fov: 90.0f32.to_radians(),
-            })?;
-            self.queue
-                .write_buffer(&self.camera_uniform_buffer, 0, &buffer.into_inner());
+    /// Writes the 3D slice of the chunk at `w` as an OBJ mesh with a colored cube per solid block
+    pub fn export_slice_obj(&self, w: usize, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        std::fs::write(path, self.slice_obj(w))
+            .with_context(|| format!("Could not export chunk slice to {}", path.display()))
+    }
+
+    fn slice_obj(&self, w: usize) -> String {
+        // Corners are numbered by their x, y and z bits, and faces wind counter-clockwise from outside
+        const CUBE_FACES: [[usize; 4]; 6] = [
+            [0, 4, 6, 2],
+            [1, 3, 7, 5],
+            [0, 1, 5, 4],
+            [2, 6, 7, 3],
+            [0, 2, 3, 1],
+            [4, 5, 7, 6],
+        ];
+
+        let mut obj = String::new();
+        let mut vertex_count = 0;
+        for position in region_offsets([CHUNK_SIZE, CHUNK_SIZE, CHUNK_SIZE, 1]) {
+            let [x, y, z, _] = position;
+            let Some(block) = Chunk::block_index([x, y, z, w]).map(|index| self.data[index]) else {
+                continue;
+            };
+            if block.exists == 0 {
+                continue;
+            }
+            for corner in 0..8 {
+                let [cx, cy, cz] = [x, y, z].map(|coordinate| coordinate as f32);
+                let _ = writeln!(
+                    obj,
+                    "v {} {} {} {} {} {}",
+                    cx + (corner & 1) as f32,
+                    cy + (corner >> 1 & 1) as f32,
+                    cz + (corner >> 2 & 1) as f32,
+                    block.color.r,
+                    block.color.g,
+                    block.color.b,
+                );
+            }
+            for [a, b, c, d] in CUBE_FACES {
+                let _ = writeln!(
+                    obj,
+                    "f {} {} {} {}",
+                    vertex_count + a + 1,
+                    vertex_count + b + 1,
+                    vertex_count + c + 1,
+                    vertex_count + d + 1,
+                );
+            }
+            vertex_count += 8;
         }
+        obj
+    }
+
+    /// Returns whether the block containing `position` exists, positions outside the chunk are never solid
+    pub fn is_solid(&self, position: [f32; 4]) -> bool {
+        if !position.iter().all(|&coordinate| coordinate >= 0.0) {
+            return false;
+        }
+        Chunk::block_index(position.map(|coordinate| coordinate as usize))
+            .is_some_and(|index| self.data[index].exists != 0)
+    }
 
+    /// Returns the distance along `direction` to the first solid block, not counting the block
+    /// containing `origin`, or `None` for a zero or non-finite direction, a non-finite `origin` or
+    /// a NaN `max_distance`. An infinite `max_distance` stops once the ray leaves the chunk
+    pub fn raycast(&self, origin: [f32; 4], direction: [f32; 4], max_distance: f32) -> Option<f32> {
+        if direction.iter().any(|x| !x.is_finite())
+            || direction.iter().all(|&x| x == 0.0)
+            || origin.iter().any(|x| !x.is_finite())
+            || max_distance.is_nan()
         {
-            let mut buffer = StorageBuffer::new([0; Chunk::SHADER_SIZE.get() as _]);
-            buffer.write(&self.chunk)?;
-            self.queue
-                .write_buffer(&self.chunk_storage_buffer, 0, &buffer.into_inner());
+            return None;
+        }
+        let mut block = origin.map(|coordinate| coordinate.floor() as i32);
+        let step = direction.map(|direction| if direction < 0.0 { -1 } else { 1 });
+        let step_sizes = direction.map(|direction| direction.abs().recip());
+        let mut next_distance: [f32; 4] = std::array::from_fn(|axis| {
+            if direction[axis] == 0.0 {
+                return f32::INFINITY;
+            }
+            let boundary = block[axis] as f32 + if step[axis] < 0 { 0.0 } else { 1.0 };
+            (boundary - origin[axis]) / direction[axis]
+        });
+        loop {
+            let (axis, &distance) = next_distance
+                .iter()
+                .enumerate()
+                .min_by(|(_, a), (_, b)| a.total_cmp(b))?;
+            if distance > max_distance {
+                return None;
+            }
+            // past the last layer of blocks on this axis the ray can't come back into the chunk
+            let leaving = if step[axis] < 0 {
+                block[axis] <= 0
+            } else {
+                block[axis] >= CHUNK_SIZE as i32 - 1
+            };
+            if leaving {
+                return None;
+            }
+            block[axis] += step[axis];
+            next_distance[axis] += step_sizes[axis];
+            if self.is_solid(block.map(|coordinate| coordinate as f32 + 0.5)) {
+                return Some(distance);
+            }
         }
+    }
 
-        let mut encoder = self
-            .device
-            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
-                label: Some("Command Encoder"),
+    fn sphere_overlaps_solid(&self, center: [f32; 4], radius: f32) -> bool {
+        let min = center.map(|coordinate| (coordinate - radius).floor() as i32);
+        let max = center.map(|coordinate| (coordinate + radius).floor() as i32);
+        let counts: [i32; 4] = std::array::from_fn(|axis| max[axis] - min[axis] + 1);
+        (0..counts.iter().product::<i32>()).any(|mut index| {
+            let block: [i32; 4] = std::array::from_fn(|axis| {
+                let offset = index % counts[axis];
+                index /= counts[axis];
+                min[axis] + offset
             });
-        {
-            {
-                let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
-                    label: Some("Ray Tracing Compute Pass"),
-                    timestamp_writes: None,
-                });
+            let distance_squared = (0..4)
+                .map(|axis| {
+                    let closest = center[axis].clamp(block[axis] as f32, block[axis] as f32 + 1.0);
+                    (center[axis] - closest) * (center[axis] - closest)
+                })
+                .sum::<f32>();
+            distance_squared <= radius * radius
+                && self.is_solid(block.map(|coordinate| coordinate as f32 + 0.5))
+        })
+    }
 
-                compute_pass.set_pipeline(&self.compute_pipeline);
-                compute_pass.set_bind_group(0, &self.main_texture_bind_group, &[]);
-                compute_pass.set_bind_group(1, &self.camera_bind_group, &[]);
-                compute_pass.set_bind_group(2, &self.chunk_bind_group, &[]);
-                compute_pass.dispatch_workgroups(
-                    self.main_texture.size().width.div_ceil(16),
-                    self.main_texture.size().height.div_ceil(16),
-                    1,
-                );
+    fn resolve_collision(&self, from: [f32; 4], to: [f32; 4], radius: f32) -> [f32; 4] {
+        let largest_delta = (0..4)
+            .map(|axis| (to[axis] - from[axis]).abs())
+            .fold(0.0, f32::max);
+        let steps = (largest_delta / COLLISION_STEP).ceil().max(1.0) as u32;
+        let mut position = from;
+        for step in 1..=steps {
+            let t = step as f32 / steps as f32;
+            for axis in 0..4 {
+                let mut candidate = position;
+                candidate[axis] = from[axis] + (to[axis] - from[axis]) * t;
+                if !self.sphere_overlaps_solid(candidate, radius)
+                    || self.sphere_overlaps_solid(position, radius)
+                {
+                    position = candidate;
+                } else {
+                    // close the gap up to the point of contact
+                    let mut blocked = candidate[axis];
+                    for _ in 0..COLLISION_CONTACT_ITERATIONS {
+                        let mut halfway = position;
+                        halfway[axis] = (position[axis] + blocked) * 0.5;
+                        if self.sphere_overlaps_solid(halfway, radius) {
+                            blocked = halfway[axis];
+                        } else {
+                            position = halfway;
+                        }
+                    }
+                }
             }
-            encoder.copy_texture_to_texture(
-                self.main_texture.as_image_copy(),
-                texture.texture.as_image_copy(),
-                self.main_texture.size(),
-            );
         }
-        self.queue.submit([encoder.finish()]);
+        position
+    }
 
-        self.window.pre_present_notify();
-        texture.present();
+    fn block_index(position: [usize; 4]) -> Option<usize> {
+        let [x, y, z, w] = position;
+        position
+            .iter()
+            .all(|&coordinate| coordinate < CHUNK_SIZE)
+            .then(|| {
+                x + y * CHUNK_SIZE
+                    + z * CHUNK_SIZE * CHUNK_SIZE
+                    + w * CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE
+            })
+    }
+}
+
+pub struct Game {
+    window: Arc<Window>,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    surface_configuration: wgpu::SurfaceConfiguration,
+    surface: wgpu::Surface<'static>,
+    present_modes: Vec<wgpu::PresentMode>,
+    surface_usages: wgpu::TextureUsages,
+    direct_present: bool,
+    direct_bind_group: Option<(wgpu::Id<wgpu::TextureView>, wgpu::BindGroup)>,
+    blit: Blit,
+    main_texture: wgpu::Texture,
+    frame_target_pool: VecDeque<FrameTargets>,
+    accumulation_storage_buffer: wgpu::Buffer,
+    depth_texture: wgpu::Texture,
+    luminance_readback: LuminanceReadback,
+    main_texture_bind_group_layout: wgpu::BindGroupLayout,
+    camera_uniform_buffer: wgpu::Buffer,
+    second_camera_uniform_buffer: wgpu::Buffer,
+    render_settings_uniform_buffer: wgpu::Buffer,
+    debug_point_storage_buffer: wgpu::Buffer,
+    heatmap_gradient_storage_buffer: wgpu::Buffer,
+    view_plane_line_storage_buffer: wgpu::Buffer,
+    custom_heatmap_gradient: bool,
+    chunk_storage_buffer: wgpu::Buffer,
+    staging_belt: wgpu::util::StagingBelt,
+    bind_groups: BindGroups,
+    compute_shader: wgpu::ShaderModule,
+    compute_pipeline_layout: wgpu::PipelineLayout,
+    compute_pipeline: wgpu::ComputePipeline,
+
+    movement_state: MovementState,
+    walk_movement: bool,
+    gravity: [f32; 4],
+    velocity: [f32; 4],
+    camera_transform: Transform,
+    camera_vertical_look: Transform,
+    look_mode: LookMode,
+    absolute_pitch: f32,
+    fly_relative_to_view: bool,
+    grid_movement: bool,
+    v_fov: f32,
+    fixed_update_enabled: bool,
+    time_scale: f32,
+    single_steps: SingleSteps,
+    fixed_update_alpha: f32,
+    photo_mode: Option<PhotoMode>,
+    orbit: Option<Orbit>,
+    orbit_demo: Option<OrbitDemo>,
+    tour: Option<ActiveTour>,
+    console: Option<String>,
+    compass: bool,
+    title: String,
+    pending_scroll: f32,
+    xw_turn_smoothing: Option<XwTurnSmoothing>,
+    xw_turn_ease: XwTurnEase,
+    progressive_refinement: bool,
+    refinement_state: RefinementState,
+    render_mode: RenderMode,
+    render_mode_key: KeyCode,
+    split_view: bool,
+    second_camera: Option<Transform>,
+    wireframe: bool,
+    voxel_margin: f32,
+    letterbox: bool,
+    letterbox_aspect: f32,
+    letterbox_color: Color,
+    back_faces: bool,
+    block_outline: Option<OutlineSettings>,
+    world_border: Option<f32>,
+    view_plane_gizmo: bool,
+    shadows: bool,
+    shadow_bias: f32,
+    depth_of_field: Option<DepthOfField>,
+    autofocus: bool,
+    collision_radius: Option<f32>,
+    confine_to_loaded: bool,
+    pin_w: Option<f32>,
+    world_bounds: Option<WorldBounds>,
+    selection: Option<Selection>,
+    sky_gradient: SkyGradient,
+    tonemap_white_point: f32,
+    tonemap_contrast: f32,
+    exposure: f32,
+    auto_exposure: Option<AutoExposureSettings>,
+    ambient_color: Color,
+    ambient_strength: f32,
+    directional_light: bool,
+    chunk: Chunk,
+    chunk_dirty_blocks: Option<Range<usize>>,
+    scenes: Scenes,
+    undo_history: UndoHistory,
+    auto_save: Option<AutoSave>,
+    frame_callbacks: FrameCallbacks,
+    post_passes: Vec<Box<dyn PostPass>>,
+    camera_shakes: Vec<CameraShake>,
+    camera_shake_frame: u32,
+    frame_times: VecDeque<Duration>,
+    frame_budget: Option<FrameBudget>,
+    debug_points: Vec<(Point, Color, f32)>,
+    uploaded_debug_points: u32,
+    uploaded_view_plane_lines: u32,
+    memory_budget: Option<u64>,
+    frames_in_flight: FramesInFlight<wgpu::SubmissionIndex>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RenderMode {
+    #[default]
+    Normal = 0,
+    Normals = 1,
+    StepHeatmap = 2,
+    ChunkTint = 3,
+    Depth = 4,
+}
+
+impl RenderMode {
+    pub fn from_debug_heatmap(debug_heatmap: bool) -> Self {
+        if debug_heatmap {
+            RenderMode::StepHeatmap
+        } else {
+            RenderMode::Normal
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            RenderMode::Normal => RenderMode::Normals,
+            RenderMode::Normals => RenderMode::StepHeatmap,
+            RenderMode::StepHeatmap => RenderMode::ChunkTint,
+            RenderMode::ChunkTint => RenderMode::Depth,
+            RenderMode::Depth => RenderMode::Normal,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            RenderMode::Normal => "normal",
+            RenderMode::Normals => "normals",
+            RenderMode::StepHeatmap => "step_heatmap",
+            RenderMode::ChunkTint => "chunk_tint",
+            RenderMode::Depth => "depth",
+        }
+    }
+}
+
+/// Debug toggles that can be saved between runs, anything missing from a saved file is off
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct DebugSettings {
+    pub render_mode: RenderMode,
+    pub wireframe: bool,
+    pub split_view: bool,
+    pub back_faces: bool,
+    pub view_plane_gizmo: bool,
+    pub compass: bool,
+}
+
+impl DebugSettings {
+    pub fn to_text(self) -> String {
+        format!(
+            "render_mode = {}\nwireframe = {}\nsplit_view = {}\nback_faces = {}\nview_plane_gizmo = {}\ncompass = {}\n",
+            self.render_mode.name(),
+            self.wireframe,
+            self.split_view,
+            self.back_faces,
+            self.view_plane_gizmo,
+            self.compass,
+        )
+    }
+
+    pub fn parse(text: &str) -> anyhow::Result<Self> {
+        let mut settings = Self::default();
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            let (key, value) = line
+                .split_once('=')
+                .map(|(key, value)| (key.trim(), value.trim()))
+                .with_context(|| format!("Expected `key = value` but got `{line}`"))?;
+            let flag = || {
+                value
+                    .parse::<bool>()
+                    .with_context(|| format!("`{value}` is not `true` or `false` for `{key}`"))
+            };
+            match key {
+                "render_mode" => {
+                    settings.render_mode =
+                        std::iter::successors(Some(RenderMode::Normal), |mode| {
+                            Some(mode.next()).filter(|&mode| mode != RenderMode::Normal)
+                        })
+                        .find(|mode| mode.name() == value)
+                        .with_context(|| format!("Unknown render mode `{value}`"))?;
+                }
+                "wireframe" => settings.wireframe = flag()?,
+                "split_view" => settings.split_view = flag()?,
+                "back_faces" => settings.back_faces = flag()?,
+                "view_plane_gizmo" => settings.view_plane_gizmo = flag()?,
+                "compass" => settings.compass = flag()?,
+                _ => bail!("Unknown debug setting `{key}`"),
+            }
+        }
+        Ok(settings)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LookMode {
+    Relative,
+    Absolute,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RegionClip {
+    size: [usize; 4],
+    blocks: Vec<Block>,
+}
+
+impl RegionClip {
+    pub fn size(&self) -> [usize; 4] {
+        self.size
+    }
+}
+
+/// A box of blocks from `min` up to but not including `max`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Selection {
+    pub min: [usize; 4],
+    pub max: [usize; 4],
+}
+
+impl Selection {
+    pub fn is_empty(&self) -> bool {
+        (0..4).any(|axis| self.max[axis] <= self.min[axis])
+    }
+
+    /// The `selection_min` and `selection_max` the shader tints between, which are equal, and so
+    /// tint nothing, without a selection
+    fn shader_bounds(selection: Option<Self>) -> (BlockPosition, BlockPosition) {
+        match selection.filter(|selection| !selection.is_empty()) {
+            Some(selection) => (
+                selection.min.map(|x| x as i32).into(),
+                selection.max.map(|x| x as i32).into(),
+            ),
+            None => ([0; 4].into(), [0; 4].into()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct WorldBounds {
+    pub min: [f32; 4],
+    pub max: [f32; 4],
+}
+
+impl WorldBounds {
+    /// Moves `position` onto the nearest point inside the bounds, an axis with `max` below `min`
+    /// pins that coordinate to `min`
+    pub fn clamp(&self, position: [f32; 4]) -> [f32; 4] {
+        std::array::from_fn(|axis| {
+            position[axis].clamp(self.min[axis], self.max[axis].max(self.min[axis]))
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SkyGradient {
+    pub zenith: Color,
+    pub horizon: Color,
+    pub nadir: Color,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct AutoExposureSettings {
+    pub target_luminance: f32,
+    pub adaptation_speed: f32,
+    pub min_exposure: f32,
+    pub max_exposure: f32,
+}
+
+impl AutoExposureSettings {
+    /// Moves `exposure` towards the exposure that brings `average_luminance` to the target, by a
+    /// fraction that depends only on `dt` so the adaptation is the same at any frame rate
+    pub fn adapt(&self, exposure: f32, average_luminance: f32, dt: f32) -> f32 {
+        let target_exposure = (self.target_luminance / average_luminance.max(f32::EPSILON))
+            .clamp(self.min_exposure, self.max_exposure);
+        let blend = 1.0 - (-self.adaptation_speed * dt).exp();
+        exposure + (target_exposure - exposure) * blend
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct OutlineSettings {
+    pub thickness: f32,
+    pub darkness: f32,
+}
+
+impl OutlineSettings {
+    /// How much the shader scales the color of a hit at `position` on a face facing `normal`,
+    /// only the axes along the face count towards the distance to an edge
+    #[cfg(test)]
+    fn factor(self, position: [f32; 4], normal: [f32; 4]) -> f32 {
+        let edge_distance = std::iter::zip(position, normal)
+            .filter(|&(_, normal)| normal == 0.0)
+            .map(|(position, _)| {
+                let local = position - position.floor();
+                local.min(1.0 - local)
+            })
+            .fold(1.0, f32::min);
+        if edge_distance < self.thickness {
+            1.0 - self.darkness
+        } else {
+            1.0
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EasingCurve {
+    Linear,
+    SmoothStep,
+    EaseOutCubic,
+}
+
+impl EasingCurve {
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            EasingCurve::Linear => t,
+            EasingCurve::SmoothStep => t * t * (3.0 - 2.0 * t),
+            EasingCurve::EaseOutCubic => 1.0 - (1.0 - t).powi(3),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct XwTurnSmoothing {
+    pub duration: f32,
+    pub curve: EasingCurve,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct DepthOfField {
+    pub aperture: f32,
+    pub focal_distance: f32,
+}
+
+impl DepthOfField {
+    /// Moves the focal distance towards `focus_target` by a fraction that depends only on `dt`
+    fn autofocus(&mut self, focus_target: f32, dt: f32) {
+        let blend = 1.0 - (-AUTOFOCUS_SPEED * dt).exp();
+        self.focal_distance += (focus_target - self.focal_distance) * blend;
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct OrbitSettings {
+    pub center: [f32; 4],
+    pub distance: f32,
+    pub speed: f32,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PacingStats {
+    pub mean: Duration,
+    pub standard_deviation: Duration,
+    pub one_percent_low: Duration,
+}
+
+impl PacingStats {
+    pub fn from_frame_times(frame_times: impl IntoIterator<Item = Duration>) -> Self {
+        let mut frame_times = frame_times
+            .into_iter()
+            .map(|frame_time| frame_time.as_secs_f64())
+            .collect::<Vec<_>>();
+        if frame_times.is_empty() {
+            return Self::default();
+        }
+
+        let count = frame_times.len() as f64;
+        let mean = frame_times.iter().sum::<f64>() / count;
+        let variance = frame_times
+            .iter()
+            .map(|frame_time| (frame_time - mean) * (frame_time - mean))
+            .sum::<f64>()
+            / count;
+
+        frame_times.sort_by(|a, b| b.total_cmp(a));
+        let slowest = &frame_times[..frame_times.len().div_ceil(100)];
+        let one_percent_low = slowest.iter().sum::<f64>() / slowest.len() as f64;
+
+        Self {
+            mean: Duration::from_secs_f64(mean),
+            standard_deviation: Duration::from_secs_f64(variance.sqrt()),
+            one_percent_low: Duration::from_secs_f64(one_percent_low),
+        }
+    }
+}
+
+/// A user-provided pass encoded after the ray trace and before the frame is copied out
+pub trait PostPass {
+    fn encode(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        texture: &wgpu::Texture,
+        size: wgpu::Extent3d,
+    );
+}
+
+#[derive(Debug, Clone)]
+pub struct Tour {
+    pub waypoints: Vec<Transform>,
+    pub dwell_time: Duration,
+    pub transition_time: Duration,
+}
+
+impl Tour {
+    /// Returns the camera transform `time` into the tour, which loops after the last waypoint
+    pub fn transform(&self, time: Duration) -> Option<Transform> {
+        let &first = self.waypoints.first()?;
+        let segment = (self.dwell_time + self.transition_time).as_secs_f64();
+        if segment <= 0.0 {
+            return Some(first);
+        }
+
+        let time = time.as_secs_f64() % (segment * self.waypoints.len() as f64);
+        let index = ((time / segment) as usize).min(self.waypoints.len() - 1);
+        let from = self.waypoints[index];
+        let transition = time - index as f64 * segment - self.dwell_time.as_secs_f64();
+        if transition <= 0.0 {
+            return Some(from);
+        }
+
+        let to = self.waypoints[(index + 1) % self.waypoints.len()];
+        let t = (transition / self.transition_time.as_secs_f64()).min(1.0) as f32;
+        if t >= 1.0 {
+            return Some(to);
+        }
+        Some(from.slerp_shortest(to, t * t * (3.0 - 2.0 * t)))
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MemoryReport {
+    pub allocations: Vec<(&'static str, u64)>,
+}
+
+impl MemoryReport {
+    pub fn total(&self) -> u64 {
+        self.allocations.iter().map(|&(_, size)| size).sum()
+    }
+
+    /// Whether the total is within `MEMORY_BUDGET_WARNING_FRACTION` of `budget` bytes or over it
+    pub fn is_near_budget(&self, budget: u64) -> bool {
+        self.total() as f64 >= budget as f64 * MEMORY_BUDGET_WARNING_FRACTION
+    }
+}
+
+pub struct AutoSaveSettings {
+    pub path: PathBuf,
+    pub idle_time: Duration,
+}
+
+struct AutoSave {
+    settings: AutoSaveSettings,
+    time_since_edit: Option<Duration>,
+    save_thread: Option<JoinHandle<()>>,
+}
+
+impl AutoSave {
+    fn mark_edited(&mut self) {
+        self.time_since_edit = Some(Duration::ZERO);
+    }
+
+    /// Starts writing `chunk` on a background thread once it has gone `idle_time` without edits
+    fn update(&mut self, chunk: &Chunk, dt: Duration) -> anyhow::Result<()> {
+        let Some(time_since_edit) = &mut self.time_since_edit else {
+            return Ok(());
+        };
+        *time_since_edit += dt;
+        if *time_since_edit < self.settings.idle_time {
+            return Ok(());
+        }
+        self.time_since_edit = None;
+
+        let bytes = chunk.to_bytes()?;
+        let path = self.settings.path.clone();
+        if let Some(save_thread) = self.save_thread.take() {
+            _ = save_thread.join();
+        }
+        self.save_thread = Some(std::thread::spawn(move || {
+            if let Err(error) = std::fs::write(&path, bytes) {
+                log::error!("Could not auto-save chunk to {}: {error}", path.display());
+            }
+        }));
+        Ok(())
+    }
+
+    /// Waits for a save in progress, then saves `chunk` right away if it has unsaved edits
+    fn finish(&mut self, chunk: &Chunk) -> anyhow::Result<()> {
+        if let Some(save_thread) = self.save_thread.take() {
+            _ = save_thread.join();
+        }
+        if self.time_since_edit.take().is_some() {
+            chunk.save(&self.settings.path)?;
+        }
         Ok(())
     }
 }
 
 #[derive(Default)]
-struct MovementState {
-    forward: f32,
-    backward: f32,
-    left: f32,
-    right: f32,
-    up: f32,
-    down: f32,
-    ana: f32,
-    kata: f32,
+struct FrameCallbacks {
+    on_pre_update: Option<Box<dyn FnMut(Duration)>>,
+    on_post_draw: Option<Box<dyn FnMut()>>,
 }
 
-impl MovementState {
-    fn transform(&self, dt: f32) -> Transform {
-        Transform::translation(
-            [
-                self.forward - self.backward,
-                self.up - self.down,
-                self.right - self.left,
-                self.ana - self.kata,
-            ]
-            .map(|m| m * 5.0 * dt),
-        )
+impl FrameCallbacks {
+    fn pre_update(&mut self, dt: Duration) {
+        if let Some(on_pre_update) = &mut self.on_pre_update {
+            on_pre_update(dt);
+        }
+    }
+
+    fn post_draw(&mut self) {
+        if let Some(on_post_draw) = &mut self.on_post_draw {
+            on_post_draw();
+        }
+    }
+}
+
+impl Game {
+    pub async fn new(window: Arc<Window>, allow_fallback_adapter: bool) -> anyhow::Result<Self> {
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+            ..Default::default()
+        });
+        let surface = instance.create_surface(window.clone())?;
+
+        let adapter = request_adapter(&instance, Some(&surface), allow_fallback_adapter)
+            .await
+            .context("Could not find an adapter")?;
+        log::info!("Using adapter {:?}", adapter.get_info());
+
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    label: Some("Device"),
+                    required_features: wgpu::Features::default(),
+                    required_limits: wgpu::Limits::default(),
+                },
+                None,
+            )
+            .await?;
+        check_chunk_buffer_size(
+            Chunk::SHADER_SIZE.get(),
+            device.limits().max_storage_buffer_binding_size,
+        )?;
+
+        let surface_capabilities = surface.get_capabilities(&adapter);
+        let PhysicalSize { width, height } = window.inner_size();
+        let surface_configuration = surface_configuration(
+            [width, height],
+            false,
+            choose_present_mode(&surface_capabilities.present_modes, false),
+            DEFAULT_MAX_FRAME_LATENCY,
+        );
+        surface.configure(&device, &surface_configuration);
+        show_loading_progress(&surface, &queue, 0.0);
+
+        let main_texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Main Texture"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: surface_configuration.format,
+            usage: wgpu::TextureUsages::STORAGE_BINDING
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let accumulation_storage_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Accumulation Storage Buffer"),
+            size: width as u64 * height as u64 * std::mem::size_of::<[f32; 4]>() as u64,
+            usage: wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+        let depth_texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Depth Texture"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::R32Float,
+            usage: wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+
+        let luminance_storage_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Luminance Storage Buffer"),
+            size: std::mem::size_of::<u32>() as _,
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::COPY_SRC
+                | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let luminance_readback_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Luminance Readback Buffer"),
+            size: std::mem::size_of::<u32>() as _,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let bind_group_layouts =
+            ComputeBindGroupLayouts::new(&device, main_texture.format(), depth_texture.format());
+        let main_texture_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Main Texture Bind Group"),
+            layout: &bind_group_layouts.main_texture,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(
+                        &main_texture.create_view(&wgpu::TextureViewDescriptor::default()),
+                    ),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: accumulation_storage_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(
+                        &depth_texture.create_view(&wgpu::TextureViewDescriptor::default()),
+                    ),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: luminance_storage_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        let camera_uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Camera Uniform Buffer"),
+            size: Camera::SHADER_SIZE.get(),
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::UNIFORM,
+            mapped_at_creation: false,
+        });
+        let second_camera_uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Second Camera Uniform Buffer"),
+            size: Camera::SHADER_SIZE.get(),
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::UNIFORM,
+            mapped_at_creation: false,
+        });
+        let camera_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Camera Bind Group"),
+            layout: &bind_group_layouts.camera,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: camera_uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: second_camera_uniform_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        let render_settings_uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Render Settings Uniform Buffer"),
+            size: RenderSettings::SHADER_SIZE.get(),
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::UNIFORM,
+            mapped_at_creation: false,
+        });
+        let debug_point_storage_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Debug Point Storage Buffer"),
+            size: DebugPoints::SHADER_SIZE.get(),
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+        let heatmap_gradient_storage_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Heatmap Gradient Storage Buffer"),
+            size: HeatmapGradient::SHADER_SIZE.get(),
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+        let view_plane_line_storage_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("View Plane Line Storage Buffer"),
+            size: ViewPlaneLines::SHADER_SIZE.get(),
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+        let render_settings_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Render Settings Bind Group"),
+            layout: &bind_group_layouts.render_settings,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: render_settings_uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: debug_point_storage_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: heatmap_gradient_storage_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: view_plane_line_storage_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        let chunk_storage_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Chunk Storage Buffer"),
+            size: Chunk::SHADER_SIZE.get(),
+            usage: wgpu::BufferUsages::COPY_SRC
+                | wgpu::BufferUsages::COPY_DST
+                | wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+        let chunk_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Chunk Bind Group"),
+            layout: &bind_group_layouts.chunk,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: chunk_storage_buffer.as_entire_binding(),
+            }],
+        });
+
+        show_loading_progress(&surface, &queue, 0.5);
+
+        let compute_shader = create_compute_shader(&device, SHADER_SOURCE).await?;
+        let compute_pipeline_layout = bind_group_layouts.pipeline_layout(&device);
+        let compute_pipeline =
+            create_compute_pipeline(&device, &compute_pipeline_layout, &compute_shader, "main")
+                .await?;
+        show_loading_progress(&surface, &queue, 1.0);
+
+        let blit = Blit::new(&device, surface_configuration.format);
+
+        let debug_settings = DebugSettings::default();
+        Ok(Game {
+            window,
+            device,
+            queue,
+            surface_configuration,
+            surface,
+            present_modes: surface_capabilities.present_modes,
+            surface_usages: surface_capabilities.usages,
+            direct_present: false,
+            direct_bind_group: None,
+            blit,
+            main_texture,
+            frame_target_pool: VecDeque::new(),
+            accumulation_storage_buffer,
+            depth_texture,
+            luminance_readback: LuminanceReadback {
+                storage_buffer: luminance_storage_buffer,
+                readback_buffer: luminance_readback_buffer,
+                pending: None,
+                average: None,
+            },
+            main_texture_bind_group_layout: bind_group_layouts.main_texture,
+            camera_uniform_buffer,
+            second_camera_uniform_buffer,
+            render_settings_uniform_buffer,
+            debug_point_storage_buffer,
+            heatmap_gradient_storage_buffer,
+            view_plane_line_storage_buffer,
+            custom_heatmap_gradient: false,
+            chunk_storage_buffer,
+            staging_belt: wgpu::util::StagingBelt::new(Chunk::SHADER_SIZE.get()),
+            bind_groups: BindGroups {
+                main_texture: main_texture_bind_group,
+                camera: camera_bind_group,
+                chunk: chunk_bind_group,
+                render_settings: render_settings_bind_group,
+            },
+            compute_shader,
+            compute_pipeline_layout,
+            compute_pipeline,
+
+            movement_state: MovementState::default(),
+            walk_movement: false,
+            gravity: DEFAULT_GRAVITY,
+            velocity: [0.0; 4],
+            camera_transform: Transform::translation([-4.5, 0.5, -1.5, 0.5]),
+            camera_vertical_look: Transform::IDENTITY,
+            look_mode: LookMode::Relative,
+            absolute_pitch: 0.0,
+            fly_relative_to_view: false,
+            grid_movement: false,
+            v_fov: 90.0f32.to_radians(),
+            fixed_update_enabled: true,
+            time_scale: 1.0,
+            single_steps: SingleSteps::default(),
+            fixed_update_alpha: 0.0,
+            photo_mode: None,
+            orbit: None,
+            orbit_demo: None,
+            tour: None,
+            console: None,
+            compass: debug_settings.compass,
+            title: "Game".into(),
+            pending_scroll: 0.0,
+            xw_turn_smoothing: None,
+            xw_turn_ease: XwTurnEase::default(),
+            progressive_refinement: false,
+            refinement_state: RefinementState::default(),
+            render_mode: debug_settings.render_mode,
+            render_mode_key: KeyCode::KeyR,
+            split_view: debug_settings.split_view,
+            second_camera: None,
+            wireframe: debug_settings.wireframe,
+            voxel_margin: 0.0,
+            letterbox: false,
+            letterbox_aspect: 16.0 / 9.0,
+            letterbox_color: Color {
+                r: 0.0,
+                g: 0.0,
+                b: 0.0,
+            },
+            back_faces: debug_settings.back_faces,
+            block_outline: None,
+            world_border: None,
+            view_plane_gizmo: debug_settings.view_plane_gizmo,
+            shadows: false,
+            shadow_bias: 0.01,
+            depth_of_field: None,
+            autofocus: false,
+            collision_radius: None,
+            confine_to_loaded: false,
+            pin_w: None,
+            world_bounds: None,
+            selection: None,
+            tonemap_white_point: 0.0,
+            tonemap_contrast: 1.0,
+            exposure: 1.0,
+            auto_exposure: None,
+            sky_gradient: SkyGradient {
+                zenith: Color {
+                    r: 0.0,
+                    g: 0.0,
+                    b: 0.0,
+                },
+                horizon: Color {
+                    r: 0.0,
+                    g: 0.0,
+                    b: 0.0,
+                },
+                nadir: Color {
+                    r: 0.0,
+                    g: 0.0,
+                    b: 0.0,
+                },
+            },
+            ambient_color: Color {
+                r: 1.0,
+                g: 1.0,
+                b: 1.0,
+            },
+            ambient_strength: 0.0,
+            directional_light: true,
+            chunk: Chunk {
+                data: std::array::from_fn(|i| {
+                    if i % 3 == 0 {
+                        Block::new(Color {
+                            r: 0.0,
+                            g: 1.0,
+                            b: 0.0,
+                        })
+                    } else {
+                        Block {
+                            color: Color {
+                                r: 1.0,
+                                g: 0.0,
+                                b: 0.0,
+                            },
+                            exists: 0,
+                            health: MAX_BLOCK_HEALTH,
+                        }
+                    }
+                }),
+            },
+            chunk_dirty_blocks: Some(0..CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE),
+            scenes: Scenes::default(),
+            undo_history: UndoHistory::default(),
+            auto_save: None,
+            frame_callbacks: FrameCallbacks::default(),
+            post_passes: Vec::new(),
+            camera_shakes: vec![],
+            camera_shake_frame: 0,
+            frame_times: VecDeque::with_capacity(FRAME_TIME_HISTORY),
+            frame_budget: None,
+            debug_points: vec![],
+            uploaded_debug_points: 0,
+            uploaded_view_plane_lines: 0,
+            memory_budget: None,
+            frames_in_flight: FramesInFlight::default(),
+        })
+    }
+
+    pub fn keyboard(&mut self, key_event: KeyEvent) -> anyhow::Result<()> {
+        if self.console.is_some() {
+            if key_event.state.is_pressed() {
+                self.console_keyboard(&key_event);
+            }
+            return Ok(());
+        }
+
+        if key_event.physical_key == PhysicalKey::Code(self.render_mode_key) {
+            if key_event.state.is_pressed() && !key_event.repeat {
+                self.cycle_render_mode();
+            }
+            return Ok(());
+        }
+
+        if let Some(index) = scene_key_index(key_event.physical_key)
+            .filter(|&index| index < self.scenes.chunks.len())
+        {
+            if key_event.state.is_pressed() && !key_event.repeat {
+                self.set_active_scene(index)?;
+            }
+            return Ok(());
+        }
+
+        let value = if key_event.state.is_pressed() {
+            1.0
+        } else {
+            0.0
+        };
+        match key_event.physical_key {
+            PhysicalKey::Code(key_code) => match key_code {
+                KeyCode::KeyS => self.movement_state.backward = value,
+                KeyCode::KeyW => self.movement_state.forward = value,
+                KeyCode::KeyA => self.movement_state.left = value,
+                KeyCode::KeyD => self.movement_state.right = value,
+                KeyCode::ShiftLeft => self.movement_state.down = value,
+                KeyCode::Space => self.movement_state.up = value,
+                KeyCode::KeyQ => self.movement_state.kata = value,
+                KeyCode::KeyE => self.movement_state.ana = value,
+                KeyCode::KeyZ => self.movement_state.turn_kata = value,
+                KeyCode::KeyX => self.movement_state.turn_ana = value,
+                KeyCode::KeyP if key_event.state.is_pressed() && !key_event.repeat => {
+                    self.set_photo_mode(self.photo_mode.is_none());
+                }
+                KeyCode::Period if key_event.state.is_pressed() => self.request_fixed_step(),
+                KeyCode::KeyF if key_event.state.is_pressed() && !key_event.repeat => {
+                    self.frame_chunk();
+                }
+                KeyCode::KeyO if key_event.state.is_pressed() && !key_event.repeat => {
+                    if self.orbit.is_some() {
+                        self.stop_orbit();
+                    } else {
+                        self.orbit_around([CHUNK_SIZE as f32 * 0.5; 4]);
+                    }
+                }
+                KeyCode::Backquote if key_event.state.is_pressed() && !key_event.repeat => {
+                    self.movement_state.clear_input();
+                    self.console = Some(String::new());
+                    self.refresh_title();
+                }
+                _ => {}
+            },
+            PhysicalKey::Unidentified(_) => {}
+        }
+        Ok(())
+    }
+
+    fn console_keyboard(&mut self, key_event: &KeyEvent) {
+        let Some(line) = &mut self.console else {
+            return;
+        };
+        match key_event.physical_key {
+            PhysicalKey::Code(KeyCode::Backquote | KeyCode::Escape) => self.console = None,
+            PhysicalKey::Code(KeyCode::Enter) => {
+                let line = std::mem::take(line);
+                match console::parse(&line).and_then(|command| self.run_command(command)) {
+                    Ok(()) => {}
+                    Err(error) => log::error!("{error}"),
+                }
+            }
+            PhysicalKey::Code(KeyCode::Backspace) => {
+                line.pop();
+            }
+            _ => {
+                if let Some(text) = &key_event.text {
+                    line.extend(text.chars().filter(|c| !c.is_control()));
+                }
+            }
+        }
+        self.refresh_title();
+    }
+
+    fn refresh_title(&mut self) {
+        let title = match &self.console {
+            Some(line) => format!("> {line}"),
+            None if self.compass && self.photo_mode.is_none() => {
+                let [x, y, z, w] = compass_angles(self.camera_forward()).map(f32::to_degrees);
+                format!("Game | x {x:.0}\u{b0} y {y:.0}\u{b0} z {z:.0}\u{b0} w {w:.0}\u{b0}")
+            }
+            None => "Game".into(),
+        };
+        if title != self.title {
+            self.window.set_title(&title);
+            self.title = title;
+        }
+    }
+
+    pub fn run_command(&mut self, command: Command) -> anyhow::Result<()> {
+        match command {
+            Command::Fov(degrees) => self.v_fov = degrees.clamp(1.0, 179.0).to_radians(),
+            Command::Speed(speed) => self.movement_state.speed = speed,
+            Command::Teleport(position) => self.teleport(position),
+            Command::Save(path) => self.save_chunk(path)?,
+        }
+        Ok(())
+    }
+
+    pub fn camera_forward(&self) -> [f32; 4] {
+        (self.camera_transform * self.camera_vertical_look)
+            .transform_direction([1.0, 0.0, 0.0, 0.0])
+    }
+
+    /// Shows the angle between the view direction and each world axis in the window title
+    pub fn set_compass(&mut self, compass: bool) {
+        self.compass = compass;
+        self.refresh_title();
+    }
+
+    pub fn teleport(&mut self, position: [f32; 4]) {
+        let current_position = self.camera_transform.position();
+        let offset = std::array::from_fn(|i| position[i] - current_position[i]);
+        self.camera_transform = Transform::translation(offset) * self.camera_transform;
+    }
+
+    pub fn debug_settings(&self) -> DebugSettings {
+        DebugSettings {
+            render_mode: self.render_mode,
+            wireframe: self.wireframe,
+            split_view: self.split_view,
+            back_faces: self.back_faces,
+            view_plane_gizmo: self.view_plane_gizmo,
+            compass: self.compass,
+        }
+    }
+
+    pub fn set_debug_settings(&mut self, debug_settings: DebugSettings) {
+        self.set_render_mode(debug_settings.render_mode);
+        self.set_wireframe(debug_settings.wireframe);
+        self.set_split_view(debug_settings.split_view);
+        self.set_back_faces(debug_settings.back_faces);
+        self.set_view_plane_gizmo(debug_settings.view_plane_gizmo);
+        self.set_compass(debug_settings.compass);
+    }
+
+    pub fn save_debug_settings(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        std::fs::write(path, self.debug_settings().to_text())
+            .with_context(|| format!("Could not save debug settings to {}", path.display()))
+    }
+
+    /// Loads debug settings saved by `save_debug_settings`, leaving the current ones alone if the
+    /// file doesn't exist yet
+    pub fn load_debug_settings(&mut self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(error) => {
+                return Err(error).with_context(|| {
+                    format!("Could not load debug settings from {}", path.display())
+                })
+            }
+        };
+        let debug_settings = DebugSettings::parse(&text)
+            .with_context(|| format!("Could not parse debug settings in {}", path.display()))?;
+        self.set_debug_settings(debug_settings);
+        Ok(())
+    }
+
+    pub fn save_chunk(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        self.chunk.save(path)
+    }
+
+    pub fn set_auto_save(&mut self, settings: Option<AutoSaveSettings>) {
+        self.finish_auto_save();
+        self.auto_save = settings.map(|settings| AutoSave {
+            settings,
+            time_since_edit: None,
+            save_thread: None,
+        });
+    }
+
+    fn update_auto_save(&mut self, dt: Duration) -> anyhow::Result<()> {
+        let Some(auto_save) = &mut self.auto_save else {
+            return Ok(());
+        };
+        auto_save.update(&self.chunk, dt)
+    }
+
+    fn finish_auto_save(&mut self) {
+        let Some(auto_save) = &mut self.auto_save else {
+            return;
+        };
+        if let Err(error) = auto_save.finish(&self.chunk) {
+            log::error!("{error}");
+        }
+    }
+
+    pub fn set_block(&mut self, position: [usize; 4], block: Block) -> anyhow::Result<()> {
+        self.edit_batch(|editor| editor.set_block(position, block))
+    }
+
+    pub fn damage_block(&mut self, position: [usize; 4], amount: u32) -> anyhow::Result<()> {
+        if self.chunk.damage_block(position, amount)? {
+            let index = Chunk::block_index(position).unwrap();
+            self.mark_blocks_dirty(index..index + 1);
+        }
+        Ok(())
+    }
+
+    /// Runs `edit` with the chunk uploaded once afterwards, the whole batch is a single step for
+    /// `undo`
+    pub fn edit_batch<R>(&mut self, edit: impl FnOnce(&mut BlockEditor<'_>) -> R) -> R {
+        let (result, dirty_blocks) = self.undo_history.edit_batch(&mut self.chunk, edit);
+        if let Some(dirty_blocks) = dirty_blocks {
+            self.mark_blocks_dirty(dirty_blocks);
+        }
+        result
+    }
+
+    /// Puts back the blocks the latest edit batch overwrote, returning whether there was one
+    pub fn undo(&mut self) -> bool {
+        let Some(dirty_blocks) = self.undo_history.undo(&mut self.chunk) else {
+            return false;
+        };
+        self.mark_blocks_dirty(dirty_blocks);
+        true
+    }
+
+    pub fn chunk(&self) -> &Chunk {
+        &self.chunk
+    }
+
+    /// Adds a chunk to the scene list and returns its index, the number keys switch between the
+    /// first nine scenes
+    pub fn add_scene(&mut self, chunk: Chunk) -> usize {
+        self.scenes.chunks.push(chunk);
+        self.scenes.chunks.len() - 1
+    }
+
+    pub fn active_scene(&self) -> Option<usize> {
+        self.scenes.active
+    }
+
+    /// Makes scene `index` the active chunk, the previously active scene keeps any edits made to it
+    pub fn set_active_scene(&mut self, index: usize) -> anyhow::Result<()> {
+        if self.scenes.activate(&mut self.chunk, index)? {
+            // the history indexes into the chunk that was just swapped out
+            self.undo_history = UndoHistory::default();
+            self.chunk_dirty_blocks = Some(0..self.chunk.data.len());
+            self.refinement_state.reset();
+        }
+        Ok(())
+    }
+
+    pub fn apply_chunk_diff(&mut self, diff: &[(usize, Block)]) -> anyhow::Result<()> {
+        self.chunk.apply_diff(diff)?;
+        let start = diff.iter().map(|&(index, _)| index).min();
+        let end = diff.iter().map(|&(index, _)| index + 1).max();
+        if let (Some(start), Some(end)) = (start, end) {
+            self.mark_blocks_dirty(start..end);
+        }
+        Ok(())
+    }
+
+    pub fn paste_region(&mut self, clip: &RegionClip, at: [usize; 4]) -> Vec<(usize, Block)> {
+        let previous = self.chunk.paste_region(clip, at);
+        let start = previous.iter().map(|&(index, _)| index).min();
+        let end = previous.iter().map(|&(index, _)| index + 1).max();
+        if let (Some(start), Some(end)) = (start, end) {
+            self.mark_blocks_dirty(start..end);
+        }
+        previous
+    }
+
+    pub fn is_solid(&self, position: [f32; 4]) -> bool {
+        self.chunk.is_solid(position)
+    }
+
+    fn mark_blocks_dirty(&mut self, blocks: Range<usize>) {
+        if let Some(auto_save) = &mut self.auto_save {
+            auto_save.mark_edited();
+        }
+        self.chunk_dirty_blocks = Some(merge_dirty_blocks(self.chunk_dirty_blocks.take(), blocks));
+        self.refinement_state.reset();
+    }
+
+    pub fn set_look_mode(&mut self, look_mode: LookMode) {
+        if look_mode == LookMode::Absolute && self.look_mode != LookMode::Absolute {
+            let Transform { s, e12, .. } = self.camera_vertical_look;
+            self.absolute_pitch = (2.0 * e12.atan2(s)).clamp(-FRAC_PI_2, FRAC_PI_2);
+            self.camera_vertical_look = Transform::rotation_xy(self.absolute_pitch);
+        }
+        self.look_mode = look_mode;
+    }
+
+    pub fn set_fly_relative_to_view(&mut self, fly_relative_to_view: bool) {
+        self.fly_relative_to_view = fly_relative_to_view;
+    }
+
+    /// Moves along the world axes nearest to the camera's axes instead of the camera's axes
+    pub fn set_grid_movement(&mut self, grid_movement: bool) {
+        self.grid_movement = grid_movement;
+    }
+
+    pub fn set_orbit_demo(&mut self, settings: Option<OrbitSettings>) {
+        self.orbit_demo = settings.map(|settings| OrbitDemo {
+            settings,
+            time: Duration::ZERO,
+        });
+        self.camera_vertical_look = Transform::IDENTITY;
+        self.absolute_pitch = 0.0;
+        self.pending_scroll = 0.0;
+        self.xw_turn_ease = XwTurnEase::default();
+    }
+
+    pub fn set_tour(&mut self, tour: Option<Tour>) {
+        self.tour = tour.map(|tour| ActiveTour {
+            tour,
+            time: Duration::ZERO,
+        });
+        self.camera_vertical_look = Transform::IDENTITY;
+        self.absolute_pitch = 0.0;
+        self.pending_scroll = 0.0;
+        self.xw_turn_ease = XwTurnEase::default();
+    }
+
+    pub fn cursor(&mut self, x: f32, y: f32) -> anyhow::Result<()> {
+        if self.orbit_demo.is_some() || self.tour.is_some() {
+            return Ok(());
+        }
+        let (horizontal_look, vertical_look) = match self.look_mode {
+            LookMode::Relative => (
+                Transform::rotation_xz(x * 0.001).clamp_rotation_angle(MAX_LOOK_ROTATION_PER_EVENT),
+                Transform::rotation_xy(y * -0.001)
+                    .clamp_rotation_angle(MAX_LOOK_ROTATION_PER_EVENT),
+            ),
+            LookMode::Absolute => absolute_look(&mut self.absolute_pitch, x, y),
+        };
+        if let Some(orbit) = &mut self.orbit {
+            orbit.rotation = orbit.rotation * horizontal_look * vertical_look;
+            return Ok(());
+        }
+        self.camera_vertical_look = self.camera_vertical_look * vertical_look;
+        self.camera_transform = self.camera_transform * horizontal_look;
+        Ok(())
+    }
+
+    pub fn scroll(&mut self, _x: f32, y: f32) -> anyhow::Result<()> {
+        if self.orbit_demo.is_some() || self.tour.is_some() {
+            return Ok(());
+        }
+        if let Some(orbit) = &mut self.orbit {
+            orbit.distance = (orbit.distance * (1.0 - y * 0.1)).max(0.1);
+            return Ok(());
+        }
+        if self.xw_turn_smoothing.is_some() {
+            self.xw_turn_ease.retarget(y * 0.01);
+        } else {
+            self.pending_scroll += y;
+        }
+        Ok(())
+    }
+
+    pub fn orbit_around(&mut self, target: [f32; 4]) {
+        let position = self.camera_transform.position();
+        let distance = std::iter::zip(target, position)
+            .map(|(target, position)| (target - position) * (target - position))
+            .sum::<f32>()
+            .sqrt()
+            .max(0.1);
+        let rotation = Transform::translation(position.map(|x| -x))
+            * self.camera_transform
+            * self.camera_vertical_look;
+        self.orbit = Some(Orbit {
+            target,
+            distance,
+            rotation,
+        });
+        self.camera_vertical_look = Transform::IDENTITY;
+        self.absolute_pitch = 0.0;
+        self.pending_scroll = 0.0;
+        self.xw_turn_ease = XwTurnEase::default();
+    }
+
+    pub fn stop_orbit(&mut self) {
+        self.orbit = None;
+    }
+
+    pub fn frame_chunk(&mut self) {
+        let aspect =
+            self.surface_configuration.width as f32 / self.surface_configuration.height as f32;
+        self.camera_transform = frame_chunk_view(
+            self.camera_transform * self.camera_vertical_look,
+            self.v_fov,
+            aspect,
+        );
+        self.camera_vertical_look = Transform::IDENTITY;
+        self.absolute_pitch = 0.0;
+    }
+
+    pub fn set_axis_speeds(&mut self, axis_speeds: [f32; 4]) {
+        self.movement_state.axis_speeds = axis_speeds;
+    }
+
+    pub fn set_xw_turn_speed(&mut self, xw_turn_speed: f32) {
+        self.movement_state.xw_turn_speed = xw_turn_speed;
+    }
+
+    /// Makes scrolling set a target xw angle that is eased towards instead of turning instantly,
+    /// disabling it finishes any turn in progress at the usual scroll rate
+    pub fn set_xw_turn_smoothing(&mut self, xw_turn_smoothing: Option<XwTurnSmoothing>) {
+        if xw_turn_smoothing.is_none() {
+            self.pending_scroll += self.xw_turn_ease.remaining() / 0.01;
+            self.xw_turn_ease = XwTurnEase::default();
+        }
+        self.xw_turn_smoothing = xw_turn_smoothing;
+    }
+
+    pub fn set_ambient_color(&mut self, ambient_color: Color) {
+        self.ambient_color = ambient_color;
+        self.refinement_state.reset();
+    }
+
+    pub fn set_ambient_strength(&mut self, ambient_strength: f32) {
+        self.ambient_strength = ambient_strength.max(0.0);
+        self.refinement_state.reset();
+    }
+
+    /// Turns the sun on or off, with it off hits are lit by the ambient term alone
+    pub fn set_directional_light(&mut self, directional_light: bool) {
+        self.directional_light = directional_light;
+        self.refinement_state.reset();
+    }
+
+    pub fn set_progressive_refinement(&mut self, progressive_refinement: bool) {
+        self.progressive_refinement = progressive_refinement;
+        self.refinement_state.reset();
+    }
+
+    pub fn set_debug_heatmap(&mut self, debug_heatmap: bool) {
+        self.set_render_mode(RenderMode::from_debug_heatmap(debug_heatmap));
+    }
+
+    /// Colors the step and depth heatmaps with `gradient` instead of their built-in ramps
+    pub fn set_heatmap_gradient(&mut self, gradient: Option<Gradient>) -> anyhow::Result<()> {
+        if let Some(gradient) = &gradient {
+            self.queue.write_buffer(
+                &self.heatmap_gradient_storage_buffer,
+                0,
+                &HeatmapGradient::encode(gradient)?,
+            );
+        }
+        self.custom_heatmap_gradient = gradient.is_some();
+        self.refinement_state.reset();
+        Ok(())
+    }
+
+    pub fn render_mode(&self) -> RenderMode {
+        self.render_mode
+    }
+
+    pub fn set_render_mode(&mut self, render_mode: RenderMode) {
+        self.render_mode = render_mode;
+        self.refinement_state.reset();
+    }
+
+    /// Rebinds the key that cycles through the render modes, replacing whatever it did before
+    pub fn set_render_mode_key(&mut self, render_mode_key: KeyCode) {
+        self.render_mode_key = render_mode_key;
+    }
+
+    pub fn cycle_render_mode(&mut self) {
+        self.set_render_mode(self.render_mode.next());
+    }
+
+    pub fn set_split_view(&mut self, split_view: bool) {
+        self.split_view = split_view;
+        self.refinement_state.reset();
+    }
+
+    /// Renders the right half of the frame from `second_camera` when set
+    pub fn set_second_camera(&mut self, second_camera: Option<Transform>) {
+        self.second_camera = second_camera;
+        self.refinement_state.reset();
+    }
+
+    pub fn set_wireframe(&mut self, wireframe: bool) {
+        self.wireframe = wireframe;
+        self.refinement_state.reset();
+    }
+
+    pub fn set_back_faces(&mut self, back_faces: bool) {
+        self.back_faces = back_faces;
+        self.refinement_state.reset();
+    }
+
+    pub fn set_block_outline(&mut self, block_outline: Option<OutlineSettings>) {
+        self.block_outline = block_outline;
+        self.refinement_state.reset();
+    }
+
+    pub fn set_tonemap_params(&mut self, white_point: f32, contrast: f32) {
+        self.tonemap_white_point = white_point.max(0.0);
+        self.tonemap_contrast = contrast.max(f32::EPSILON);
+        self.refinement_state.reset();
+    }
+
+    pub fn exposure(&self) -> f32 {
+        self.exposure
+    }
+
+    pub fn set_exposure(&mut self, exposure: f32) {
+        self.exposure = exposure.max(0.0);
+    }
+
+    pub fn set_auto_exposure(&mut self, auto_exposure: Option<AutoExposureSettings>) {
+        self.auto_exposure = auto_exposure;
+        self.luminance_readback.average = None;
+    }
+
+    pub fn set_sky_gradient(&mut self, sky_gradient: SkyGradient) {
+        self.sky_gradient = sky_gradient;
+        self.refinement_state.reset();
+    }
+
+    pub fn selection(&self) -> Option<Selection> {
+        self.selection
+    }
+
+    pub fn set_selection(&mut self, selection: Option<Selection>) {
+        self.selection = selection;
+        self.refinement_state.reset();
+    }
+
+    pub fn set_world_bounds(&mut self, world_bounds: Option<WorldBounds>) {
+        self.world_bounds = world_bounds;
+    }
+
+    /// Keeps the camera's w coordinate at this value while it moves and looks freely
+    pub fn set_pin_w(&mut self, pin_w: Option<f32>) {
+        self.pin_w = pin_w;
+    }
+
+    pub fn set_confine_to_loaded(&mut self, confine_to_loaded: bool) {
+        self.confine_to_loaded = confine_to_loaded;
+    }
+
+    /// Collides the camera with solid blocks as a 4D sphere of this radius
+    pub fn set_collision_radius(&mut self, collision_radius: Option<f32>) {
+        self.collision_radius = collision_radius.map(|radius| radius.max(0.0));
+    }
+
+    /// Draws a grid aligned to the camera's viewing hyperplane over the scene
+    pub fn set_view_plane_gizmo(&mut self, view_plane_gizmo: bool) {
+        self.view_plane_gizmo = view_plane_gizmo;
+        self.refinement_state.reset();
+    }
+
+    pub fn set_shadows(&mut self, shadows: bool) {
+        self.shadows = shadows;
+        self.refinement_state.reset();
+    }
+
+    /// Offsets shadow rays along the surface normal so they don't hit the block they start on
+    pub fn set_shadow_bias(&mut self, shadow_bias: f32) {
+        self.shadow_bias = shadow_bias.max(0.0);
+        self.refinement_state.reset();
+    }
+
+    /// Blurs out-of-focus blocks across progressive refinement samples
+    pub fn set_depth_of_field(&mut self, depth_of_field: Option<DepthOfField>) {
+        self.depth_of_field = depth_of_field.map(|depth_of_field| DepthOfField {
+            aperture: depth_of_field.aperture.max(0.0),
+            focal_distance: depth_of_field.focal_distance.max(0.0),
+        });
+        self.refinement_state.reset();
+    }
+
+    /// Moves the focal distance towards whatever block is at the center of the view
+    pub fn set_autofocus(&mut self, autofocus: bool) {
+        self.autofocus = autofocus;
+    }
+
+    pub fn set_world_border(&mut self, world_border: Option<f32>) {
+        self.world_border = world_border.map(|radius| radius.max(0.0));
+        self.refinement_state.reset();
+    }
+
+    pub fn set_voxel_margin(&mut self, voxel_margin: f32) {
+        self.voxel_margin = clamp_voxel_margin(voxel_margin);
+        self.refinement_state.reset();
+    }
+
+    pub fn set_letterbox(&mut self, letterbox: bool) {
+        self.letterbox = letterbox;
+        self.refinement_state.reset();
+    }
+
+    pub fn set_letterbox_aspect(&mut self, letterbox_aspect: f32) {
+        self.letterbox_aspect = letterbox_aspect.max(f32::EPSILON);
+        self.refinement_state.reset();
+    }
+
+    pub fn set_letterbox_color(&mut self, letterbox_color: Color) {
+        self.letterbox_color = letterbox_color;
+    }
+
+    pub fn set_on_pre_update(&mut self, on_pre_update: Option<Box<dyn FnMut(Duration)>>) {
+        self.frame_callbacks.on_pre_update = on_pre_update;
+    }
+
+    pub fn add_camera_shake(&mut self, intensity: f32, duration: Duration) {
+        self.camera_shakes.push(CameraShake {
+            intensity,
+            duration,
+            elapsed: Duration::ZERO,
+        });
+    }
+
+    pub fn set_on_post_draw(&mut self, on_post_draw: Option<Box<dyn FnMut()>>) {
+        self.frame_callbacks.on_post_draw = on_post_draw;
+    }
+
+    pub fn add_post_pass(&mut self, pass: Box<dyn PostPass>) {
+        self.post_passes.push(pass);
+    }
+
+    pub fn set_frame_budget(&mut self, budget: Option<Duration>) {
+        self.frame_budget = budget.map(FrameBudget::new);
+    }
+
+    pub fn memory_report(&self) -> MemoryReport {
+        let texture_size =
+            |texture: &wgpu::Texture| texture_byte_size(texture.size(), texture.format());
+        MemoryReport {
+            allocations: vec![
+                ("Main Texture", texture_size(&self.main_texture)),
+                ("Depth Texture", texture_size(&self.depth_texture)),
+                (
+                    "Pooled Frame Targets",
+                    self.frame_target_pool
+                        .iter()
+                        .map(|frame_targets| {
+                            texture_size(&frame_targets.main_texture)
+                                + texture_size(&frame_targets.depth_texture)
+                                + frame_targets.accumulation_storage_buffer.size()
+                        })
+                        .sum(),
+                ),
+                (
+                    "Accumulation Storage Buffer",
+                    self.accumulation_storage_buffer.size(),
+                ),
+                ("Chunk Storage Buffer", self.chunk_storage_buffer.size()),
+                (
+                    "Debug Point Storage Buffer",
+                    self.debug_point_storage_buffer.size(),
+                ),
+                (
+                    "Heatmap Gradient Storage Buffer",
+                    self.heatmap_gradient_storage_buffer.size(),
+                ),
+                (
+                    "View Plane Line Storage Buffer",
+                    self.view_plane_line_storage_buffer.size(),
+                ),
+                ("Camera Uniform Buffer", self.camera_uniform_buffer.size()),
+                (
+                    "Second Camera Uniform Buffer",
+                    self.second_camera_uniform_buffer.size(),
+                ),
+                (
+                    "Render Settings Uniform Buffer",
+                    self.render_settings_uniform_buffer.size(),
+                ),
+                (
+                    "Luminance Storage Buffer",
+                    self.luminance_readback.storage_buffer.size(),
+                ),
+                (
+                    "Luminance Readback Buffer",
+                    self.luminance_readback.readback_buffer.size(),
+                ),
+            ],
+        }
+    }
+
+    /// Warns when the GPU allocations in `memory_report` come close to `budget` bytes
+    pub fn set_memory_budget(&mut self, budget: Option<u64>) {
+        self.memory_budget = budget;
+        self.check_memory_budget();
+    }
+
+    fn check_memory_budget(&self) {
+        let Some(budget) = self.memory_budget else {
+            return;
+        };
+        let memory_report = self.memory_report();
+        if memory_report.is_near_budget(budget) {
+            let total = memory_report.total();
+            log::warn!("GPU memory is close to the budget: total={total} budget={budget}");
+        }
+    }
+
+    /// Draws a cross at `point` in the next frame only
+    pub fn debug_point(&mut self, point: Point, color: Color, size: f32) {
+        self.debug_points.push((point, color, size));
+    }
+
+    pub fn frame_pacing_stats(&self) -> PacingStats {
+        PacingStats::from_frame_times(self.frame_times.iter().copied())
+    }
+
+    pub fn update(&mut self, dt: Duration) -> anyhow::Result<()> {
+        self.frame_callbacks.pre_update(dt);
+
+        if self.frame_times.len() == FRAME_TIME_HISTORY {
+            self.frame_times.pop_front();
+        }
+        self.frame_times.push_back(dt);
+        if let Some(frame_budget) = &mut self.frame_budget {
+            if let Some(overruns) = frame_budget.record(dt) {
+                log::warn!(
+                    "Frame budget exceeded: frame_time={dt:?} budget={:?} overruns={overruns}",
+                    frame_budget.budget
+                );
+            }
+        }
+
+        self.update_auto_save(dt)?;
+
+        let Some(dt) = movement_dt(dt) else {
+            return Ok(());
+        };
+
+        if let (Some(auto_exposure), Some(average_luminance)) =
+            (self.auto_exposure, self.luminance_readback.average)
+        {
+            self.exposure = auto_exposure.adapt(self.exposure, average_luminance, dt.as_secs_f32());
+        }
+
+        let focus_target = self
+            .autofocus
+            .then(|| {
+                self.chunk.raycast(
+                    self.camera_transform.position(),
+                    self.camera_forward(),
+                    AUTOFOCUS_MAX_DISTANCE,
+                )
+            })
+            .flatten();
+        if let (Some(depth_of_field), Some(focus_target)) = (&mut self.depth_of_field, focus_target)
+        {
+            let previous_focal_distance = depth_of_field.focal_distance;
+            depth_of_field.autofocus(focus_target, dt.as_secs_f32());
+            if (depth_of_field.focal_distance - previous_focal_distance).abs() > f32::EPSILON {
+                self.refinement_state.reset();
+            }
+        }
+
+        // the time scale only slows down the world, the camera, exposure and auto-save keep running
+        // in real time so a time scale of 0 freezes the world without freezing the player
+        let world_dt = scaled_dt(dt, self.time_scale);
+        for camera_shake in &mut self.camera_shakes {
+            camera_shake.elapsed += world_dt;
+        }
+        self.camera_shakes
+            .retain(|camera_shake| camera_shake.elapsed < camera_shake.duration);
+
+        if let Some(orbit_demo) = &mut self.orbit_demo {
+            orbit_demo.time += world_dt;
+            self.camera_transform = orbit_demo.transform();
+            return Ok(());
+        }
+
+        if let Some(active_tour) = &mut self.tour {
+            active_tour.time += world_dt;
+            if let Some(transform) = active_tour.tour.transform(active_tour.time) {
+                self.camera_transform = transform;
+            }
+            return Ok(());
+        }
+
+        if let Some(orbit) = &self.orbit {
+            self.camera_transform = orbit.transform();
+            return Ok(());
+        }
+
+        let dt = dt.as_secs_f32();
+        let previous_position = self.camera_transform.position();
+        if self.grid_movement || self.walk_movement {
+            let view = if self.fly_relative_to_view {
+                self.camera_transform * self.camera_vertical_look
+            } else {
+                self.camera_transform
+            };
+            let world_displacement = world_displacement(
+                self.movement_state.displacement(dt),
+                view,
+                self.walk_movement,
+                self.grid_movement,
+            );
+            self.camera_transform =
+                Transform::translation(world_displacement) * self.camera_transform;
+        } else {
+            self.camera_transform = self.camera_transform
+                * fly_movement(
+                    self.movement_state.displacement(dt),
+                    self.camera_vertical_look,
+                    self.fly_relative_to_view,
+                );
+        }
+
+        let position = self.camera_transform.position();
+        let mut clamped_position = position;
+        if let Some(radius) = self.collision_radius {
+            clamped_position = self
+                .chunk
+                .resolve_collision(previous_position, position, radius);
+        }
+        clamped_position = self.clamp_camera_position(clamped_position);
+        self.camera_transform = Transform::translation(std::array::from_fn(|axis| {
+            clamped_position[axis] - position[axis]
+        })) * self.camera_transform;
+
+        let scroll_turn = take_scroll(&mut self.pending_scroll, dt);
+        let eased_turn = self
+            .xw_turn_smoothing
+            .map_or(0.0, |smoothing| self.xw_turn_ease.advance(smoothing, dt));
+        let xw_turn = scroll_turn + eased_turn + self.movement_state.xw_turn(dt);
+        self.camera_transform = self.camera_transform * Transform::rotation_xw(xw_turn);
+        Ok(())
+    }
+
+    fn clamp_camera_position(&self, position: [f32; 4]) -> [f32; 4] {
+        clamp_camera_position(
+            position,
+            self.world_border,
+            self.world_bounds,
+            self.confine_to_loaded,
+            self.pin_w,
+        )
+    }
+
+    pub fn fixed_update_enabled(&self) -> bool {
+        self.fixed_update_enabled
+    }
+
+    pub fn set_fixed_update_enabled(&mut self, fixed_update_enabled: bool) {
+        self.fixed_update_enabled = fixed_update_enabled;
+        self.single_steps.clear();
+    }
+
+    pub fn photo_mode(&self) -> bool {
+        self.photo_mode.is_some()
+    }
+
+    pub fn set_photo_mode(&mut self, photo_mode: bool) {
+        if photo_mode && self.photo_mode.is_none() {
+            self.console = None;
+        }
+        let fixed_update_enabled =
+            PhotoMode::set(&mut self.photo_mode, photo_mode, self.fixed_update_enabled);
+        if fixed_update_enabled != self.fixed_update_enabled {
+            self.set_fixed_update_enabled(fixed_update_enabled);
+        }
+        self.refresh_title();
+        self.refinement_state.reset();
+    }
+
+    pub fn fixed_update_alpha(&self) -> f32 {
+        self.fixed_update_alpha
+    }
+
+    pub fn set_fixed_update_alpha(&mut self, fixed_update_alpha: f32) {
+        self.fixed_update_alpha = fixed_update_alpha.clamp(0.0, 1.0);
+    }
+
+    pub fn request_fixed_step(&mut self) {
+        if !self.fixed_update_enabled {
+            self.single_steps.request();
+        }
+    }
+
+    pub fn take_fixed_step(&mut self) -> bool {
+        self.single_steps.take()
+    }
+
+    /// Lets gravity pull the camera in `fixed_update` until it stands on a solid block, and keeps
+    /// forward, right and ana movement level with the ground, with up and down along world y
+    pub fn set_walk_movement(&mut self, walk_movement: bool) {
+        self.walk_movement = walk_movement;
+    }
+
+    pub fn gravity(&self) -> [f32; 4] {
+        self.gravity
+    }
+
+    /// Sets the acceleration that pulls the camera while walking, the axis it points along most
+    /// decides which neighbouring block counts as the ground
+    pub fn set_gravity(&mut self, gravity: [f32; 4]) {
+        self.gravity = gravity;
+    }
+
+    pub fn time_scale(&self) -> f32 {
+        self.time_scale
+    }
+
+    pub fn set_time_scale(&mut self, time_scale: f32) {
+        self.time_scale = time_scale.max(0.0);
+    }
+
+    pub fn fixed_update(&mut self, ts: Duration) -> anyhow::Result<()> {
+        if !self.walk_movement
+            || self.orbit.is_some()
+            || self.orbit_demo.is_some()
+            || self.tour.is_some()
+        {
+            self.velocity = [0.0; 4];
+            return Ok(());
+        }
+
+        let position = self.camera_transform.position();
+        let (fallen_position, velocity) = fall_step(
+            &self.chunk,
+            position,
+            self.velocity,
+            self.gravity,
+            ts.as_secs_f32(),
+            self.collision_radius,
+        );
+        self.velocity = velocity;
+        let fallen_position = self.clamp_camera_position(fallen_position);
+        self.camera_transform = Transform::translation(std::array::from_fn(|axis| {
+            fallen_position[axis] - position[axis]
+        })) * self.camera_transform;
+        Ok(())
+    }
+
+    pub async fn set_compute_entry_point(&mut self, entry_point: &str) -> anyhow::Result<()> {
+        self.compute_pipeline = create_compute_pipeline(
+            &self.device,
+            &self.compute_pipeline_layout,
+            &self.compute_shader,
+            entry_point,
+        )
+        .await?;
+        self.refinement_state.reset();
+        Ok(())
+    }
+
+    pub fn set_max_frame_latency(&mut self, max_frame_latency: u32) {
+        self.surface_configuration = surface_configuration(
+            [
+                self.surface_configuration.width,
+                self.surface_configuration.height,
+            ],
+            self.direct_present,
+            self.surface_configuration.present_mode,
+            max_frame_latency,
+        );
+        self.surface
+            .configure(&self.device, &self.surface_configuration);
+    }
+
+    /// Blocks before rendering a frame while this many earlier frames are still on the GPU
+    pub fn set_max_frames_in_flight(&mut self, max_frames_in_flight: Option<u32>) {
+        self.frames_in_flight.set_max(max_frames_in_flight);
+    }
+
+    /// Traces straight into the surface texture when it supports storage binding,
+    /// skipping the copy from `main_texture`, which `read_pixel` and screenshots then
+    /// no longer see. Off by default
+    pub fn set_direct_present(&mut self, direct_present: bool) {
+        let direct_present = direct_present && supports_direct_present(self.surface_usages);
+        if direct_present != self.direct_present {
+            self.direct_present = direct_present;
+            self.direct_bind_group = None;
+            self.surface_configuration.usage = surface_usage(direct_present);
+            self.surface
+                .configure(&self.device, &self.surface_configuration);
+        }
+    }
+
+    pub fn present_mode(&self) -> wgpu::PresentMode {
+        self.surface_configuration.present_mode
+    }
+
+    pub fn set_immediate_present(&mut self, immediate_present: bool) {
+        self.surface_configuration.present_mode =
+            choose_present_mode(&self.present_modes, immediate_present);
+        self.surface
+            .configure(&self.device, &self.surface_configuration);
+    }
+
+    pub fn resize(&mut self, width: u32, height: u32) -> anyhow::Result<()> {
+        if width > 0 && height > 0 {
+            log::debug!("Resizing to {width}x{height}");
+            self.surface_configuration.width = width;
+            self.surface_configuration.height = height;
+            self.surface
+                .configure(&self.device, &self.surface_configuration);
+
+            self.resize_frame_targets(width, height);
+            self.check_memory_budget();
+        }
+        Ok(())
+    }
+
+    fn resize_frame_targets(&mut self, width: u32, height: u32) {
+        let current_size = self.main_texture.size();
+        if [current_size.width, current_size.height] != [width, height] {
+            let frame_targets =
+                take_pooled_frame_targets(&mut self.frame_target_pool, [width, height])
+                    .unwrap_or_else(|| self.create_frame_targets(width, height));
+            let previous = FrameTargets {
+                main_texture: std::mem::replace(&mut self.main_texture, frame_targets.main_texture),
+                accumulation_storage_buffer: std::mem::replace(
+                    &mut self.accumulation_storage_buffer,
+                    frame_targets.accumulation_storage_buffer,
+                ),
+                depth_texture: std::mem::replace(
+                    &mut self.depth_texture,
+                    frame_targets.depth_texture,
+                ),
+                bind_group: std::mem::replace(
+                    &mut self.bind_groups.main_texture,
+                    frame_targets.bind_group,
+                ),
+            };
+            pool_frame_targets(&mut self.frame_target_pool, previous);
+            self.direct_bind_group = None;
+            self.refinement_state.reset();
+        }
+    }
+
+    fn create_frame_targets(&self, width: u32, height: u32) -> FrameTargets {
+        FrameTargets::new(
+            &self.device,
+            &self.main_texture_bind_group_layout,
+            self.surface_configuration.format,
+            &self.luminance_readback.storage_buffer,
+            [width, height],
+        )
+    }
+
+    fn create_main_texture_bind_group(
+        &self,
+        texture_view: &wgpu::TextureView,
+        accumulation_storage_buffer: &wgpu::Buffer,
+        depth_texture: &wgpu::Texture,
+    ) -> wgpu::BindGroup {
+        create_main_texture_bind_group(
+            &self.device,
+            &self.main_texture_bind_group_layout,
+            texture_view,
+            accumulation_storage_buffer,
+            depth_texture,
+            &self.luminance_readback.storage_buffer,
+        )
+    }
+
+    pub fn shutdown(mut self) {
+        self.finish_auto_save();
+        self.device.poll(wgpu::Maintain::Wait);
+
+        drop(self.compute_pipeline);
+        drop(self.compute_pipeline_layout);
+        drop(self.compute_shader);
+        drop(self.bind_groups);
+        drop(self.debug_point_storage_buffer);
+        drop(self.heatmap_gradient_storage_buffer);
+        drop(self.view_plane_line_storage_buffer);
+        drop(self.render_settings_uniform_buffer);
+        drop(self.staging_belt);
+        drop(self.chunk_storage_buffer);
+        drop(self.second_camera_uniform_buffer);
+        drop(self.camera_uniform_buffer);
+        drop(self.main_texture_bind_group_layout);
+        drop(self.luminance_readback);
+        drop(self.depth_texture);
+        drop(self.accumulation_storage_buffer);
+        drop(self.main_texture);
+        drop(self.frame_target_pool);
+        drop(self.surface);
+        drop(self.queue);
+        drop(self.device);
+    }
+
+    /// Gets the surface texture to draw the next frame into, or `None` when the frame should be
+    /// skipped
+    pub fn acquire_frame(&mut self) -> anyhow::Result<Option<wgpu::SurfaceTexture>> {
+        let texture = loop {
+            match self.surface.get_current_texture() {
+                Ok(texture) => break texture,
+                Err(e @ wgpu::SurfaceError::Timeout) => {
+                    log::warn!("{e}");
+                    return Ok(None);
+                }
+                Err(wgpu::SurfaceError::Outdated) | Err(wgpu::SurfaceError::Lost) => {
+                    let PhysicalSize { width, height } = self.window.inner_size();
+                    if width == 0 || height == 0 {
+                        return Ok(None);
+                    }
+                    self.resize(width, height)?;
+                }
+                Err(e @ wgpu::SurfaceError::OutOfMemory) => bail!(e),
+            }
+        };
+        self.refresh_title();
+        Ok(Some(texture))
+    }
+
+    pub fn present(&self, frame: wgpu::SurfaceTexture) {
+        self.window.pre_present_notify();
+        frame.present();
+    }
+
+    /// Ends a frame, drawn or skipped, so every update is paired with a post draw
+    pub fn finish_draw(&mut self) {
+        self.frame_callbacks.post_draw();
+    }
+
+    pub fn device(&self) -> &wgpu::Device {
+        &self.device
+    }
+
+    pub fn queue(&self) -> &wgpu::Queue {
+        &self.queue
+    }
+
+    /// Renders a frame into `view`, which must be of a `width` by `height` texture in the surface
+    /// format with render attachment usage, or storage binding too with direct present on. The
+    /// frame targets are resized to match, and sizes that come and go are kept pooled
+    pub fn render_to_view(
+        &mut self,
+        view: &wgpu::TextureView,
+        (width, height): (u32, u32),
+    ) -> anyhow::Result<()> {
+        if width == 0 || height == 0 {
+            bail!("Cannot render into an empty {width}x{height} target");
+        }
+        self.resize_frame_targets(width, height);
+        self.render(Some(view))
+    }
+
+    /// Saves the current view as a binary PPM, rendered at `supersample` times the resolution in
+    /// each direction and averaged back down
+    pub fn screenshot(
+        &mut self,
+        path: impl AsRef<Path>,
+        supersample: Option<u32>,
+    ) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        let size = self.main_texture.size();
+        let size = [size.width, size.height];
+        let supersample = supersample.unwrap_or(1).max(1);
+        let [width, height] = supersampled_size(
+            size,
+            supersample,
+            self.device.limits().max_texture_dimension_2d,
+        )?;
+
+        self.resize_frame_targets(width, height);
+        let pixels = self.render_and_read(width, height);
+        self.resize_frame_targets(size[0], size[1]);
+        if supersample > 1 {
+            // a one-off size, so don't keep it pooled
+            remove_pooled_frame_targets(&mut self.frame_target_pool, [width, height]);
+        }
+
+        let pixels = downsample(&pixels?, [width, height], supersample);
+        std::fs::write(path, encode_ppm(size, &pixels))
+            .with_context(|| format!("Could not save screenshot to {}", path.display()))
+    }
+
+    fn render_and_read(&mut self, width: u32, height: u32) -> anyhow::Result<Vec<u8>> {
+        self.resize_frame_targets(width, height);
+        self.render(None)?;
+        self.read_texture(&self.main_texture)
+    }
+
+    /// Renders a frame into the frame targets and then draws it into `target`, if there is one.
+    /// Direct present traces straight into `target` instead, unless post passes need the frame
+    fn render(&mut self, target: Option<&wgpu::TextureView>) -> anyhow::Result<()> {
+        let direct_target = target.filter(|_| self.direct_present && self.post_passes.is_empty());
+
+        self.frames_in_flight.wait_for_room(|submission_index| {
+            self.device
+                .poll(wgpu::Maintain::WaitForSubmissionIndex(submission_index));
+        });
+
+        let mut camera_transform = self.camera_transform * self.camera_vertical_look;
+        let camera_shake = self
+            .camera_shakes
+            .iter()
+            .map(CameraShake::amplitude)
+            .sum::<f32>();
+        if camera_shake > 0.0 {
+            self.camera_shake_frame = self.camera_shake_frame.wrapping_add(1);
+            camera_transform =
+                camera_transform * camera_shake_offset(camera_shake, self.camera_shake_frame);
+        }
+        let sample_index = self
+            .refinement_state
+            .advance(camera_transform, self.progressive_refinement);
+        let measure_luminance =
+            self.auto_exposure.is_some() && self.luminance_readback.poll(&self.device)?;
+
+        {
+            let mut buffer = UniformBuffer::new([0; Camera::SHADER_SIZE.get() as _]);
+            buffer.write(&Camera {
+                transform: camera_transform,
+                v_fov: self.v_fov,
+            })?;
+            self.queue
+                .write_buffer(&self.camera_uniform_buffer, 0, &buffer.into_inner());
+        }
+
+        if let Some(second_camera) = self.second_camera {
+            let mut buffer = UniformBuffer::new([0; Camera::SHADER_SIZE.get() as _]);
+            buffer.write(&Camera {
+                transform: second_camera,
+                v_fov: self.v_fov,
+            })?;
+            self.queue
+                .write_buffer(&self.second_camera_uniform_buffer, 0, &buffer.into_inner());
+        }
+
+        let size = self.main_texture.size();
+        let ([viewport_x, viewport_y], [viewport_width, viewport_height]) = if self.letterbox {
+            letterbox_viewport([size.width, size.height], self.letterbox_aspect)
+        } else {
+            ([0, 0], [size.width, size.height])
+        };
+        {
+            let (selection_min, selection_max) = Selection::shader_bounds(self.selection);
+            let mut render_settings = RenderSettings {
+                ambient_color: self.ambient_color,
+                ambient_strength: self.ambient_strength,
+                directional_light: self.directional_light as u32,
+                sample_index,
+                render_mode: self.render_mode as u32,
+                split_view: self.split_view as u32,
+                split_screen: self.second_camera.is_some() as u32,
+                wireframe: self.wireframe as u32,
+                voxel_margin: self.voxel_margin,
+                viewport_x,
+                viewport_y,
+                viewport_width,
+                viewport_height,
+                letterbox_color: self.letterbox_color,
+                back_faces: self.back_faces as u32,
+                outline_thickness: self.block_outline.map_or(0.0, |outline| outline.thickness),
+                outline_darkness: self.block_outline.map_or(0.0, |outline| outline.darkness),
+                world_border_radius: self.world_border.unwrap_or(0.0),
+                srgb_encode: shader_srgb_encode(self.surface_configuration.format) as u32,
+                sky_zenith_color: self.sky_gradient.zenith,
+                sky_horizon_color: self.sky_gradient.horizon,
+                sky_nadir_color: self.sky_gradient.nadir,
+                tonemap_white_point: self.tonemap_white_point,
+                tonemap_contrast: self.tonemap_contrast,
+                exposure: self.exposure,
+                measure_luminance: measure_luminance as u32,
+                view_plane_gizmo: self.view_plane_gizmo as u32,
+                shadows: self.shadows as u32,
+                shadow_bias: self.shadow_bias,
+                aperture: self
+                    .depth_of_field
+                    .map_or(0.0, |depth_of_field| depth_of_field.aperture),
+                focal_distance: self
+                    .depth_of_field
+                    .map_or(0.0, |depth_of_field| depth_of_field.focal_distance),
+                custom_heatmap_gradient: self.custom_heatmap_gradient as u32,
+                selection_min,
+                selection_max,
+            };
+            if self.photo_mode.is_some() {
+                render_settings.hide_overlays();
+            }
+            let mut buffer = UniformBuffer::new([0; RenderSettings::SHADER_SIZE.get() as _]);
+            buffer.write(&render_settings)?;
+            self.queue.write_buffer(
+                &self.render_settings_uniform_buffer,
+                0,
+                &buffer.into_inner(),
+            );
+        }
+
+        if self.photo_mode.is_some() {
+            self.debug_points.clear();
+        }
+        // an empty buffer only needs writing once
+        if !self.debug_points.is_empty() || self.uploaded_debug_points > 0 {
+            let theta = (self.v_fov / 2.0).tan();
+            let mut points = std::array::from_fn(|_| DebugPoint {
+                x: 0.0,
+                y: 0.0,
+                radius: 0.0,
+                color: Color {
+                    r: 0.0,
+                    g: 0.0,
+                    b: 0.0,
+                },
+            });
+            let mut count = 0;
+            let projected = self
+                .debug_points
+                .drain(..)
+                .filter_map(|(point, color, size)| {
+                    let ([x, y], depth) = project_point(
+                        camera_transform,
+                        self.v_fov,
+                        [viewport_width, viewport_height],
+                        point.to_cartesian(),
+                    )?;
+                    Some(DebugPoint {
+                        x: x + viewport_x as f32,
+                        y: y + viewport_y as f32,
+                        radius: (size * viewport_height as f32 * 0.5 / (depth * theta))
+                            .max(MIN_DEBUG_POINT_RADIUS),
+                        color,
+                    })
+                });
+            for (slot, point) in points.iter_mut().zip(projected) {
+                *slot = point;
+                count += 1;
+            }
+
+            let mut buffer = StorageBuffer::new([0; DebugPoints::SHADER_SIZE.get() as _]);
+            buffer.write(&DebugPoints { count, points })?;
+            self.queue
+                .write_buffer(&self.debug_point_storage_buffer, 0, &buffer.into_inner());
+            self.uploaded_debug_points = count;
+        }
+
+        let view_plane_gizmo = self.view_plane_gizmo && self.photo_mode.is_none();
+        if view_plane_gizmo || self.uploaded_view_plane_lines > 0 {
+            let mut lines = std::array::from_fn(|_| ViewPlaneLine {
+                start_x: 0.0,
+                start_y: 0.0,
+                end_x: 0.0,
+                end_y: 0.0,
+            });
+            let mut count = 0;
+            if view_plane_gizmo {
+                let projected = view_plane_grid_lines(camera_transform)
+                    .into_iter()
+                    .filter_map(|line| {
+                        let [[start_x, start_y], [end_x, end_y]] = project_line(
+                            camera_transform,
+                            self.v_fov,
+                            [viewport_width, viewport_height],
+                            line,
+                        )?;
+                        Some(ViewPlaneLine {
+                            start_x: start_x + viewport_x as f32,
+                            start_y: start_y + viewport_y as f32,
+                            end_x: end_x + viewport_x as f32,
+                            end_y: end_y + viewport_y as f32,
+                        })
+                    });
+                for (slot, line) in lines.iter_mut().zip(projected) {
+                    *slot = line;
+                    count += 1;
+                }
+            }
+
+            let mut buffer = StorageBuffer::new([0; ViewPlaneLines::SHADER_SIZE.get() as _]);
+            buffer.write(&ViewPlaneLines { count, lines })?;
+            self.queue.write_buffer(
+                &self.view_plane_line_storage_buffer,
+                0,
+                &buffer.into_inner(),
+            );
+            self.uploaded_view_plane_lines = count;
+        }
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Command Encoder"),
+            });
+        if let Some(dirty_blocks) = self.chunk_dirty_blocks.take() {
+            stage_chunk_upload(
+                &mut self.staging_belt,
+                &mut encoder,
+                &self.device,
+                &self.chunk_storage_buffer,
+                &self.chunk,
+                dirty_blocks,
+            )?;
+        }
+        if measure_luminance {
+            encoder.clear_buffer(&self.luminance_readback.storage_buffer, 0, None);
+        }
+        match direct_target {
+            None => self.direct_bind_group = None,
+            Some(target)
+                if self
+                    .direct_bind_group
+                    .as_ref()
+                    .is_none_or(|(id, _)| *id != target.global_id()) =>
+            {
+                let bind_group = self.create_main_texture_bind_group(
+                    target,
+                    &self.accumulation_storage_buffer,
+                    &self.depth_texture,
+                );
+                self.direct_bind_group = Some((target.global_id(), bind_group));
+            }
+            Some(_) => {}
+        }
+        {
+            {
+                let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                    label: Some("Ray Tracing Compute Pass"),
+                    timestamp_writes: None,
+                });
+
+                compute_pass.set_pipeline(&self.compute_pipeline);
+                self.bind_groups.set(&mut compute_pass);
+                if let Some((_, direct_bind_group)) = &self.direct_bind_group {
+                    compute_pass.set_bind_group(0, direct_bind_group, &[]);
+                }
+                compute_pass.dispatch_workgroups(
+                    self.main_texture.size().width.div_ceil(16),
+                    self.main_texture.size().height.div_ceil(16),
+                    1,
+                );
+            }
+            for post_pass in &self.post_passes {
+                post_pass.encode(&mut encoder, &self.main_texture, self.main_texture.size());
+            }
+            if let (Some(target), None) = (target, direct_target) {
+                self.blit
+                    .encode(&self.device, &mut encoder, &self.main_texture, target);
+            }
+        }
+        if measure_luminance {
+            encoder.copy_buffer_to_buffer(
+                &self.luminance_readback.storage_buffer,
+                0,
+                &self.luminance_readback.readback_buffer,
+                0,
+                self.luminance_readback.storage_buffer.size(),
+            );
+        }
+        self.staging_belt.finish();
+        let submission_index = self.queue.submit([encoder.finish()]);
+        self.frames_in_flight.submitted(submission_index);
+        self.staging_belt.recall();
+        if measure_luminance {
+            self.luminance_readback
+                .request(viewport_width * viewport_height);
+        }
+        Ok(())
+    }
+
+    pub fn draw_loading(&self, progress: f32) -> anyhow::Result<()> {
+        draw_loading_frame(&self.surface, &self.queue, progress)
+    }
+
+    pub fn read_pixel(&self, x: u32, y: u32) -> anyhow::Result<Color> {
+        let [r, g, b, _] = self.read_texel(&self.main_texture, x, y)?;
+        Ok(Color {
+            r: r as f32 / 255.0,
+            g: g as f32 / 255.0,
+            b: b as f32 / 255.0,
+        })
+    }
+
+    pub fn read_depth(&self, x: u32, y: u32) -> anyhow::Result<Option<f32>> {
+        let depth = f32::from_le_bytes(self.read_texel(&self.depth_texture, x, y)?);
+        Ok((depth >= 0.0).then_some(depth))
+    }
+
+    /// Reads the chunk back from the GPU, for checking that uploads round-trip
+    pub fn read_chunk_data(&self) -> anyhow::Result<Chunk> {
+        read_chunk_buffer(&self.device, &self.queue, &self.chunk_storage_buffer)
+    }
+
+    fn read_texture(&self, texture: &wgpu::Texture) -> anyhow::Result<Vec<u8>> {
+        let size = texture.size();
+        let row_size = size.width * 4;
+        let padded_row_size = row_size.next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+        let readback_buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Texture Readback Buffer"),
+            size: padded_row_size as u64 * size.height as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Texture Readback Encoder"),
+            });
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &readback_buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_row_size),
+                    rows_per_image: None,
+                },
+            },
+            size,
+        );
+        self.queue.submit([encoder.finish()]);
+
+        let slice = readback_buffer.slice(..);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            _ = sender.send(result);
+        });
+        self.device.poll(wgpu::Maintain::Wait);
+        receiver.recv()??;
+
+        let pixels = slice
+            .get_mapped_range()
+            .chunks_exact(padded_row_size as usize)
+            .flat_map(|row| row[..row_size as usize].iter().copied())
+            .collect();
+        readback_buffer.unmap();
+        Ok(pixels)
+    }
+
+    fn read_texel(&self, texture: &wgpu::Texture, x: u32, y: u32) -> anyhow::Result<[u8; 4]> {
+        read_texel(&self.device, &self.queue, texture, x, y)
+    }
+}
+
+struct MovementState {
+    speed: f32,
+    axis_speeds: [f32; 4],
+    forward: f32,
+    backward: f32,
+    left: f32,
+    right: f32,
+    up: f32,
+    down: f32,
+    ana: f32,
+    kata: f32,
+    xw_turn_speed: f32,
+    turn_ana: f32,
+    turn_kata: f32,
+}
+
+impl Default for MovementState {
+    fn default() -> Self {
+        Self {
+            speed: 5.0,
+            axis_speeds: [1.0; 4],
+            forward: 0.0,
+            backward: 0.0,
+            left: 0.0,
+            right: 0.0,
+            up: 0.0,
+            down: 0.0,
+            ana: 0.0,
+            kata: 0.0,
+            xw_turn_speed: MAX_SCROLL_PER_SECOND * 0.01,
+            turn_ana: 0.0,
+            turn_kata: 0.0,
+        }
+    }
+}
+
+impl MovementState {
+    fn clear_input(&mut self) {
+        *self = Self {
+            speed: self.speed,
+            axis_speeds: self.axis_speeds,
+            xw_turn_speed: self.xw_turn_speed,
+            ..Self::default()
+        };
+    }
+
+    fn displacement(&self, dt: f32) -> [f32; 4] {
+        let movement = [
+            self.forward - self.backward,
+            self.up - self.down,
+            self.right - self.left,
+            self.ana - self.kata,
+        ];
+        std::array::from_fn(|axis| movement[axis] * self.axis_speeds[axis] * self.speed * dt)
+    }
+
+    fn xw_turn(&self, dt: f32) -> f32 {
+        (self.turn_ana - self.turn_kata) * self.xw_turn_speed * dt
+    }
+}
+
+/// Angles are measured from where the current run of scrolling started, and everything resets to
+/// zero once the target is reached
+#[derive(Default)]
+struct XwTurnEase {
+    from: f32,
+    current: f32,
+    target: f32,
+    elapsed: f32,
+}
+
+impl XwTurnEase {
+    fn retarget(&mut self, angle: f32) {
+        self.from = self.current;
+        self.target += angle;
+        self.elapsed = 0.0;
+    }
+
+    fn remaining(&self) -> f32 {
+        self.target - self.current
+    }
+
+    /// Returns the angle to turn by this frame
+    fn advance(&mut self, smoothing: XwTurnSmoothing, dt: f32) -> f32 {
+        if self.target == self.current {
+            return 0.0;
+        }
+        self.elapsed += dt;
+        let t = if smoothing.duration > 0.0 {
+            self.elapsed / smoothing.duration
+        } else {
+            1.0
+        };
+        let next = self.from + (self.target - self.from) * smoothing.curve.apply(t);
+        let turn = next - self.current;
+        self.current = next;
+        if t >= 1.0 {
+            *self = Self::default();
+        }
+        turn
+    }
+}
+
+/// The camera-local movement for `displacement` along the camera's axes, turned by the vertical
+/// look when flying relative to the view
+fn fly_movement(
+    displacement: [f32; 4],
+    camera_vertical_look: Transform,
+    fly_relative_to_view: bool,
+) -> Transform {
+    let movement = Transform::translation(displacement);
+    if fly_relative_to_view {
+        movement.conjugate_by(camera_vertical_look)
+    } else {
+        movement
+    }
+}
+
+/// The horizontal and vertical turns for a cursor movement in absolute look mode. `absolute_pitch`
+/// sums every vertical movement, so only the clamped part of it turns the view
+fn absolute_look(absolute_pitch: &mut f32, x: f32, y: f32) -> (Transform, Transform) {
+    let previous_pitch = absolute_pitch.clamp(-FRAC_PI_2, FRAC_PI_2);
+    *absolute_pitch += y * -0.001;
+    let pitch = absolute_pitch.clamp(-FRAC_PI_2, FRAC_PI_2);
+    (
+        Transform::rotation_xz(x * 0.001),
+        Transform::rotation_xy(pitch - previous_pitch),
+    )
+}
+
+fn clamp_voxel_margin(voxel_margin: f32) -> f32 {
+    if voxel_margin.is_nan() {
+        return 0.0;
+    }
+    voxel_margin.clamp(0.0, MAX_VOXEL_MARGIN)
+}
+
+/// How much world time passes in a frame that took `dt` when running at `time_scale`
+pub fn scaled_dt(dt: Duration, time_scale: f32) -> Duration {
+    dt.mul_f32(time_scale.max(0.0))
+}
+
+/// The frame time `update` moves things by, capped at `MAX_UPDATE_DT`, or `None` for a frame that
+/// took no time and so moves nothing
+fn movement_dt(dt: Duration) -> Option<Duration> {
+    Some(dt.min(MAX_UPDATE_DT)).filter(|dt| !dt.is_zero())
+}
+
+/// Takes as much of `pending_scroll` as may be applied over `dt` seconds, returning the xw angle
+/// to turn by
+fn take_scroll(pending_scroll: &mut f32, dt: f32) -> f32 {
+    let max_scroll = MAX_SCROLL_PER_SECOND * dt;
+    let scroll = pending_scroll.clamp(-max_scroll, max_scroll);
+    *pending_scroll -= scroll;
+    scroll * 0.01
+}
+
+/// Encodes a copy of the `dirty_blocks` of `chunk` into `chunk_buffer` through `staging_belt`,
+/// which the caller finishes before submitting `encoder` and recalls afterwards
+fn stage_chunk_upload(
+    staging_belt: &mut wgpu::util::StagingBelt,
+    encoder: &mut wgpu::CommandEncoder,
+    device: &wgpu::Device,
+    chunk_buffer: &wgpu::Buffer,
+    chunk: &Chunk,
+    dirty_blocks: Range<usize>,
+) -> anyhow::Result<()> {
+    let mut buffer = StorageBuffer::new([0; Chunk::SHADER_SIZE.get() as _]);
+    buffer.write(chunk)?;
+    let dirty_bytes = dirty_byte_range(dirty_blocks, chunk.data.len());
+    if let Some(size) = wgpu::BufferSize::new(dirty_bytes.len() as _) {
+        staging_belt
+            .write_buffer(encoder, chunk_buffer, dirty_bytes.start as _, size, device)
+            .copy_from_slice(&buffer.into_inner()[dirty_bytes]);
+    }
+    Ok(())
+}
+
+fn read_chunk_buffer(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    chunk_buffer: &wgpu::Buffer,
+) -> anyhow::Result<Chunk> {
+    let readback_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Chunk Readback Buffer"),
+        size: chunk_buffer.size(),
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("Chunk Readback Encoder"),
+    });
+    encoder.copy_buffer_to_buffer(chunk_buffer, 0, &readback_buffer, 0, chunk_buffer.size());
+    queue.submit([encoder.finish()]);
+
+    let slice = readback_buffer.slice(..);
+    let (sender, receiver) = std::sync::mpsc::channel();
+    slice.map_async(wgpu::MapMode::Read, move |result| {
+        _ = sender.send(result);
+    });
+    device.poll(wgpu::Maintain::Wait);
+    receiver.recv()??;
+
+    let chunk = Chunk::from_bytes(&slice.get_mapped_range())?;
+    readback_buffer.unmap();
+    Ok(chunk)
+}
+
+fn read_texel(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    texture: &wgpu::Texture,
+    x: u32,
+    y: u32,
+) -> anyhow::Result<[u8; 4]> {
+    let size = texture.size();
+    if x >= size.width || y >= size.height {
+        bail!(
+            "Pixel ({x}, {y}) is outside the {}x{} frame",
+            size.width,
+            size.height
+        );
+    }
+
+    let readback_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Texel Readback Buffer"),
+        size: wgpu::COPY_BYTES_PER_ROW_ALIGNMENT as _,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("Texel Readback Encoder"),
+    });
+    encoder.copy_texture_to_buffer(
+        wgpu::ImageCopyTexture {
+            origin: wgpu::Origin3d { x, y, z: 0 },
+            ..texture.as_image_copy()
+        },
+        wgpu::ImageCopyBuffer {
+            buffer: &readback_buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT),
+                rows_per_image: None,
+            },
+        },
+        wgpu::Extent3d {
+            width: 1,
+            height: 1,
+            depth_or_array_layers: 1,
+        },
+    );
+    queue.submit([encoder.finish()]);
+
+    let slice = readback_buffer.slice(..);
+    let (sender, receiver) = std::sync::mpsc::channel();
+    slice.map_async(wgpu::MapMode::Read, move |result| {
+        _ = sender.send(result);
+    });
+    device.poll(wgpu::Maintain::Wait);
+    receiver.recv()??;
+
+    let texel = slice.get_mapped_range()[..4].try_into()?;
+    readback_buffer.unmap();
+    Ok(texel)
+}
+
+fn check_chunk_buffer_size(size: u64, max_storage_buffer_binding_size: u32) -> anyhow::Result<()> {
+    if size > max_storage_buffer_binding_size as u64 {
+        bail!(
+            "The chunk storage buffer needs {size} bytes but the device only allows {max_storage_buffer_binding_size} bytes per storage buffer binding, try a smaller CHUNK_SIZE (currently {CHUNK_SIZE})"
+        );
+    }
+    Ok(())
+}
+
+/// The bind group layouts of the compute shader, one per group
+struct ComputeBindGroupLayouts {
+    main_texture: wgpu::BindGroupLayout,
+    camera: wgpu::BindGroupLayout,
+    chunk: wgpu::BindGroupLayout,
+    render_settings: wgpu::BindGroupLayout,
+}
+
+impl ComputeBindGroupLayouts {
+    fn new(
+        device: &wgpu::Device,
+        output_format: wgpu::TextureFormat,
+        depth_format: wgpu::TextureFormat,
+    ) -> Self {
+        Self {
+            main_texture: device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Main Texture Bind Group Layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::StorageTexture {
+                            access: wgpu::StorageTextureAccess::WriteOnly,
+                            format: output_format,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: false },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::StorageTexture {
+                            access: wgpu::StorageTextureAccess::WriteOnly,
+                            format: depth_format,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 3,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: false },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
+            }),
+            camera: device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Camera Bind Group Layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: Some(Camera::SHADER_SIZE),
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: Some(Camera::SHADER_SIZE),
+                        },
+                        count: None,
+                    },
+                ],
+            }),
+            chunk: device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Chunk Bind Group Layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: Some(Chunk::SHADER_SIZE),
+                    },
+                    count: None,
+                }],
+            }),
+            render_settings: device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Render Settings Bind Group Layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: Some(RenderSettings::SHADER_SIZE),
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: true },
+                            has_dynamic_offset: false,
+                            min_binding_size: Some(DebugPoints::SHADER_SIZE),
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: true },
+                            has_dynamic_offset: false,
+                            min_binding_size: Some(HeatmapGradient::SHADER_SIZE),
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 3,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: true },
+                            has_dynamic_offset: false,
+                            min_binding_size: Some(ViewPlaneLines::SHADER_SIZE),
+                        },
+                        count: None,
+                    },
+                ],
+            }),
+        }
+    }
+
+    /// The pipeline layout with each group at its index in the shader
+    fn pipeline_layout(&self, device: &wgpu::Device) -> wgpu::PipelineLayout {
+        device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Compute Pipeline Layout"),
+            bind_group_layouts: &[
+                &self.main_texture,
+                &self.camera,
+                &self.chunk,
+                &self.render_settings,
+            ],
+            push_constant_ranges: &[],
+        })
+    }
+}
+
+async fn create_compute_shader(
+    device: &wgpu::Device,
+    source: &str,
+) -> anyhow::Result<wgpu::ShaderModule> {
+    device.push_error_scope(wgpu::ErrorFilter::Validation);
+    let compute_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Compute Shader"),
+        source: wgpu::ShaderSource::Wgsl(source.into()),
+    });
+    if let Some(error) = device.pop_error_scope().await {
+        bail!("Could not create the compute shader: {error}");
+    }
+    Ok(compute_shader)
+}
+
+async fn create_compute_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    module: &wgpu::ShaderModule,
+    entry_point: &str,
+) -> anyhow::Result<wgpu::ComputePipeline> {
+    device.push_error_scope(wgpu::ErrorFilter::Validation);
+    let compute_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: Some("Compute Pipeline"),
+        layout: Some(layout),
+        module,
+        entry_point,
+    });
+    if let Some(error) = device.pop_error_scope().await {
+        bail!("Could not create the compute pipeline with entry point `{entry_point}`: {error}");
+    }
+    Ok(compute_pipeline)
+}
+
+fn loading_bar_width(width: u32, progress: f32) -> u32 {
+    ((width as f32 * progress.clamp(0.0, 1.0)).round() as u32).min(width)
+}
+
+/// The loading frames are only feedback, failing to draw one shouldn't stop the game starting
+fn show_loading_progress(surface: &wgpu::Surface<'_>, queue: &wgpu::Queue, progress: f32) {
+    if let Err(error) = draw_loading_frame(surface, queue, progress) {
+        log::warn!("Could not draw a loading frame: {error}");
+    }
+}
+
+fn draw_loading_frame(
+    surface: &wgpu::Surface<'_>,
+    queue: &wgpu::Queue,
+    progress: f32,
+) -> anyhow::Result<()> {
+    let texture = surface.get_current_texture()?;
+    let size = texture.texture.size();
+    let bar_width = loading_bar_width(size.width, progress);
+    let bar_height = (size.height / 32).max(1);
+    let bar_rows = (size.height - bar_height) / 2..(size.height + bar_height) / 2;
+
+    let mut pixels = vec![0; size.width as usize * size.height as usize * 4];
+    for (y, row) in pixels.chunks_exact_mut(size.width as usize * 4).enumerate() {
+        for (x, pixel) in row.chunks_exact_mut(4).enumerate() {
+            let in_bar = (x as u32) < bar_width && bar_rows.contains(&(y as u32));
+            pixel.copy_from_slice(if in_bar {
+                &[255, 255, 255, 255]
+            } else {
+                &[32, 32, 32, 255]
+            });
+        }
+    }
+
+    queue.write_texture(
+        texture.texture.as_image_copy(),
+        &pixels,
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: Some(size.width * 4),
+            rows_per_image: None,
+        },
+        size,
+    );
+    queue.submit([]);
+    texture.present();
+    Ok(())
+}
+
+fn texture_byte_size(size: wgpu::Extent3d, format: wgpu::TextureFormat) -> u64 {
+    size.width as u64
+        * size.height as u64
+        * size.depth_or_array_layers as u64
+        * format.block_copy_size(None).unwrap_or(0) as u64
+}
+
+/// Requests a hardware adapter, then a software one such as llvmpipe or WARP if that's allowed
+async fn request_adapter(
+    instance: &wgpu::Instance,
+    compatible_surface: Option<&wgpu::Surface<'_>>,
+    allow_fallback_adapter: bool,
+) -> Option<wgpu::Adapter> {
+    let adapter = instance
+        .request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            force_fallback_adapter: false,
+            compatible_surface,
+        })
+        .await;
+    if adapter.is_some() || !allow_fallback_adapter {
+        return adapter;
+    }
+    log::warn!("No hardware adapter was found, falling back to a software adapter");
+    instance
+        .request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            force_fallback_adapter: true,
+            compatible_surface,
+        })
+        .await
+}
+
+fn supports_direct_present(surface_usages: wgpu::TextureUsages) -> bool {
+    surface_usages.contains(wgpu::TextureUsages::STORAGE_BINDING)
+}
+
+fn surface_usage(direct_present: bool) -> wgpu::TextureUsages {
+    let usage = wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::RENDER_ATTACHMENT;
+    if direct_present {
+        usage | wgpu::TextureUsages::STORAGE_BINDING
+    } else {
+        usage
+    }
+}
+
+fn surface_configuration(
+    size: [u32; 2],
+    direct_present: bool,
+    present_mode: wgpu::PresentMode,
+    max_frame_latency: u32,
+) -> wgpu::SurfaceConfiguration {
+    let [width, height] = size;
+    wgpu::SurfaceConfiguration {
+        usage: surface_usage(direct_present),
+        format: wgpu::TextureFormat::Rgba8Unorm,
+        width,
+        height,
+        present_mode,
+        desired_maximum_frame_latency: max_frame_latency.max(1),
+        alpha_mode: wgpu::CompositeAlphaMode::Auto,
+        view_formats: vec![],
+    }
+}
+
+/// `*Srgb` formats encode on write, anything else needs the shader to encode
+fn shader_srgb_encode(format: wgpu::TextureFormat) -> bool {
+    !format.is_srgb()
+}
+
+fn choose_present_mode(
+    present_modes: &[wgpu::PresentMode],
+    immediate_present: bool,
+) -> wgpu::PresentMode {
+    let (preferred_present_modes, fallback) = if immediate_present {
+        (
+            [wgpu::PresentMode::Immediate, wgpu::PresentMode::Mailbox],
+            wgpu::PresentMode::AutoNoVsync,
+        )
+    } else {
+        (
+            [wgpu::PresentMode::Mailbox, wgpu::PresentMode::Fifo],
+            wgpu::PresentMode::Fifo,
+        )
+    };
+    preferred_present_modes
+        .into_iter()
+        .find(|present_mode| present_modes.contains(present_mode))
+        .unwrap_or(fallback)
+}
+
+pub struct BlockEditor<'a> {
+    chunk: &'a mut Chunk,
+    dirty_blocks: Option<Range<usize>>,
+    previous: Vec<(usize, Block)>,
+}
+
+impl BlockEditor<'_> {
+    pub fn set_block(&mut self, position: [usize; 4], block: Block) -> anyhow::Result<()> {
+        let index = Chunk::block_index(position)
+            .with_context(|| format!("Block position {position:?} is outside the chunk"))?;
+        // only the first overwrite of a block is what undoing the batch goes back to
+        if !self.previous.iter().any(|&(previous, _)| previous == index) {
+            self.previous.push((index, self.chunk.data[index]));
+        }
+        self.chunk.data[index] = block;
+        self.dirty_blocks = Some(merge_dirty_blocks(
+            self.dirty_blocks.take(),
+            index..index + 1,
+        ));
+        Ok(())
+    }
+}
+
+/// The angle between `forward` and each of the world's axes, a right angle each for a zero vector
+pub fn compass_angles(forward: [f32; 4]) -> [f32; 4] {
+    let length = forward.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if length <= f32::EPSILON {
+        return [std::f32::consts::FRAC_PI_2; 4];
+    }
+    forward.map(|x| (x / length).clamp(-1.0, 1.0).acos())
+}
+
+fn region_offsets(size: [usize; 4]) -> impl Iterator<Item = [usize; 4]> {
+    (0..size.iter().product()).map(move |mut index| {
+        std::array::from_fn(|axis| {
+            let offset = index % size[axis];
+            index /= size[axis];
+            offset
+        })
+    })
+}
+
+fn merge_dirty_blocks(dirty_blocks: Option<Range<usize>>, blocks: Range<usize>) -> Range<usize> {
+    match dirty_blocks {
+        Some(dirty_blocks) => {
+            dirty_blocks.start.min(blocks.start)..dirty_blocks.end.max(blocks.end)
+        }
+        None => blocks,
+    }
+}
+
+fn dirty_byte_range(dirty_blocks: Range<usize>, block_count: usize) -> Range<usize> {
+    let block_stride = Chunk::SHADER_SIZE.get() as usize / block_count;
+    dirty_blocks.start * block_stride..dirty_blocks.end * block_stride
+}
+
+struct FrameTargets {
+    main_texture: wgpu::Texture,
+    accumulation_storage_buffer: wgpu::Buffer,
+    depth_texture: wgpu::Texture,
+    bind_group: wgpu::BindGroup,
+}
+
+impl FrameTargets {
+    /// Creates the per-size render targets along with the bind group the compute pass writes them
+    /// through, so the bind group is only ever rebuilt when the targets are
+    fn new(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        format: wgpu::TextureFormat,
+        luminance_storage_buffer: &wgpu::Buffer,
+        size: [u32; 2],
+    ) -> Self {
+        let [width, height] = size;
+        let main_texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Main Texture"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::STORAGE_BINDING
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let accumulation_storage_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Accumulation Storage Buffer"),
+            size: width as u64 * height as u64 * std::mem::size_of::<[f32; 4]>() as u64,
+            usage: wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+        let depth_texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Depth Texture"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::R32Float,
+            usage: wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let bind_group = create_main_texture_bind_group(
+            device,
+            layout,
+            &main_texture.create_view(&wgpu::TextureViewDescriptor::default()),
+            &accumulation_storage_buffer,
+            &depth_texture,
+            luminance_storage_buffer,
+        );
+        Self {
+            main_texture,
+            accumulation_storage_buffer,
+            depth_texture,
+            bind_group,
+        }
+    }
+
+    fn size(&self) -> [u32; 2] {
+        [self.main_texture.width(), self.main_texture.height()]
+    }
+}
+
+/// Takes the pooled frame targets of `size` out of `pool`, if there are any
+fn take_pooled_frame_targets(
+    pool: &mut VecDeque<FrameTargets>,
+    size: [u32; 2],
+) -> Option<FrameTargets> {
+    let index = pool
+        .iter()
+        .position(|frame_targets| frame_targets.size() == size)?;
+    pool.remove(index)
+}
+
+fn remove_pooled_frame_targets(pool: &mut VecDeque<FrameTargets>, size: [u32; 2]) {
+    pool.retain(|frame_targets| frame_targets.size() != size);
+}
+
+/// Keeps `frame_targets` for reuse, dropping the least recently pooled ones past
+/// `MAX_POOLED_FRAME_TARGETS`
+fn pool_frame_targets(pool: &mut VecDeque<FrameTargets>, frame_targets: FrameTargets) {
+    pool.push_back(frame_targets);
+    if pool.len() > MAX_POOLED_FRAME_TARGETS {
+        pool.pop_front();
+    }
+}
+
+fn create_main_texture_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    texture_view: &wgpu::TextureView,
+    accumulation_storage_buffer: &wgpu::Buffer,
+    depth_texture: &wgpu::Texture,
+    luminance_storage_buffer: &wgpu::Buffer,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Main Texture Bind Group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(texture_view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: accumulation_storage_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::TextureView(
+                    &depth_texture.create_view(&wgpu::TextureViewDescriptor::default()),
+                ),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: luminance_storage_buffer.as_entire_binding(),
+            },
+        ],
+    })
+}
+
+/// Draws the frame into a texture view, which unlike a texture copy works for any view the
+/// pipeline's format can render to
+struct Blit {
+    pipeline: wgpu::RenderPipeline,
+    bind_group: Option<(wgpu::Id<wgpu::Texture>, wgpu::BindGroup)>,
+}
+
+impl Blit {
+    fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Blit Shader"),
+            source: wgpu::ShaderSource::Wgsl(BLIT_SHADER_SOURCE.into()),
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Blit Pipeline"),
+            layout: None,
+            vertex: wgpu::VertexState {
+                module: &module,
+                entry_point: "vertex",
+                buffers: &[],
+            },
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            fragment: Some(wgpu::FragmentState {
+                module: &module,
+                entry_point: "fragment",
+                targets: &[Some(format.into())],
+            }),
+            multiview: None,
+        });
+        Self {
+            pipeline,
+            bind_group: None,
+        }
+    }
+
+    /// Encodes a pass drawing `frame` into `target`, which has to be the same size
+    fn encode(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        frame: &wgpu::Texture,
+        target: &wgpu::TextureView,
+    ) {
+        if self
+            .bind_group
+            .as_ref()
+            .is_none_or(|(id, _)| *id != frame.global_id())
+        {
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Blit Bind Group"),
+                layout: &self.pipeline.get_bind_group_layout(0),
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(
+                        &frame.create_view(&wgpu::TextureViewDescriptor::default()),
+                    ),
+                }],
+            });
+            self.bind_group = Some((frame.global_id(), bind_group));
+        }
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Blit Render Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        render_pass.set_pipeline(&self.pipeline);
+        if let Some((_, bind_group)) = &self.bind_group {
+            render_pass.set_bind_group(0, bind_group, &[]);
+        }
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+struct BindGroups {
+    main_texture: wgpu::BindGroup,
+    camera: wgpu::BindGroup,
+    chunk: wgpu::BindGroup,
+    render_settings: wgpu::BindGroup,
+}
+
+impl BindGroups {
+    fn set<'a>(&'a self, compute_pass: &mut wgpu::ComputePass<'a>) {
+        compute_pass.set_bind_group(0, &self.main_texture, &[]);
+        compute_pass.set_bind_group(1, &self.camera, &[]);
+        compute_pass.set_bind_group(2, &self.chunk, &[]);
+        compute_pass.set_bind_group(3, &self.render_settings, &[]);
+    }
+}
+
+/// Frames submitted to the GPU that may still be running, oldest first, tracked only while there's
+/// a limit on them
+struct FramesInFlight<T> {
+    max: Option<u32>,
+    submitted: VecDeque<T>,
+}
+
+impl<T> Default for FramesInFlight<T> {
+    fn default() -> Self {
+        Self {
+            max: None,
+            submitted: VecDeque::new(),
+        }
+    }
+}
+
+impl<T> FramesInFlight<T> {
+    fn set_max(&mut self, max: Option<u32>) {
+        self.max = max.map(|max| max.max(1));
+        if self.max.is_none() {
+            self.submitted.clear();
+        }
+    }
+
+    /// Calls `wait` on the oldest frames until there's room under the limit for another
+    fn wait_for_room(&mut self, mut wait: impl FnMut(T)) {
+        let Some(max) = self.max else {
+            return;
+        };
+        while self.submitted.len() >= max as usize {
+            let Some(frame) = self.submitted.pop_front() else {
+                break;
+            };
+            wait(frame);
+        }
+    }
+
+    fn submitted(&mut self, frame: T) {
+        if self.max.is_some() {
+            self.submitted.push_back(frame);
+        }
+    }
+}
+
+struct FrameBudget {
+    budget: Duration,
+    time_since_warning: Duration,
+    overruns: u32,
+}
+
+impl FrameBudget {
+    fn new(budget: Duration) -> Self {
+        Self {
+            budget,
+            time_since_warning: FRAME_BUDGET_WARNING_INTERVAL,
+            overruns: 0,
+        }
+    }
+
+    /// Returns how many frames overran the budget since the last warning when it's time to warn
+    /// again, at most once per `FRAME_BUDGET_WARNING_INTERVAL`
+    fn record(&mut self, frame_time: Duration) -> Option<u32> {
+        self.time_since_warning += frame_time;
+        if frame_time <= self.budget {
+            return None;
+        }
+        self.overruns += 1;
+        if self.time_since_warning < FRAME_BUDGET_WARNING_INTERVAL {
+            return None;
+        }
+        self.time_since_warning = Duration::ZERO;
+        Some(std::mem::take(&mut self.overruns))
+    }
+}
+
+struct LuminanceReadback {
+    storage_buffer: wgpu::Buffer,
+    readback_buffer: wgpu::Buffer,
+    pending: Option<(u32, Receiver<Result<(), wgpu::BufferAsyncError>>)>,
+    average: Option<f32>,
+}
+
+impl LuminanceReadback {
+    /// Returns whether the readback buffer is free for a new measurement
+    fn poll(&mut self, device: &wgpu::Device) -> anyhow::Result<bool> {
+        let Some((pixel_count, receiver)) = &self.pending else {
+            return Ok(true);
+        };
+        device.poll(wgpu::Maintain::Poll);
+        match receiver.try_recv() {
+            Ok(result) => result?,
+            Err(TryRecvError::Empty) => return Ok(false),
+            Err(TryRecvError::Disconnected) => bail!("Luminance readback was dropped"),
+        }
+
+        let pixel_count = *pixel_count;
+        self.pending = None;
+        let slice = self.readback_buffer.slice(..);
+        let sum = u32::from_le_bytes(slice.get_mapped_range()[..4].try_into()?);
+        self.readback_buffer.unmap();
+        self.average = Some(sum as f32 / (LUMINANCE_SCALE * pixel_count.max(1) as f32));
+        Ok(true)
+    }
+
+    fn request(&mut self, pixel_count: u32) {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        self.readback_buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                _ = sender.send(result);
+            });
+        self.pending = Some((pixel_count, receiver));
+    }
+}
+
+struct PhotoMode {
+    fixed_update_enabled: bool,
+}
+
+impl PhotoMode {
+    /// Turns `photo_mode` on or off, returning whether fixed updates should run. They are paused
+    /// for the whole photo and go back to how they were before it afterwards
+    fn set(photo_mode: &mut Option<Self>, enabled: bool, fixed_update_enabled: bool) -> bool {
+        match (enabled, photo_mode.take()) {
+            (true, Some(previous)) => {
+                *photo_mode = Some(previous);
+                fixed_update_enabled
+            }
+            (true, None) => {
+                *photo_mode = Some(PhotoMode {
+                    fixed_update_enabled,
+                });
+                false
+            }
+            (false, Some(previous)) => previous.fixed_update_enabled,
+            (false, None) => fixed_update_enabled,
+        }
+    }
+}
+
+struct OrbitDemo {
+    settings: OrbitSettings,
+    time: Duration,
+}
+
+impl OrbitDemo {
+    fn transform(&self) -> Transform {
+        let angle = self.settings.speed * self.time.as_secs_f32();
+        Transform::translation(self.settings.center)
+            * Transform::rotation_xz(angle)
+            * Transform::rotation_xw(angle * 0.5)
+            * Transform::rotation_yw(angle * 0.25)
+            * Transform::translation([-self.settings.distance, 0.0, 0.0, 0.0])
+    }
+}
+
+#[derive(Default)]
+struct Scenes {
+    chunks: Vec<Chunk>,
+    active: Option<usize>,
+}
+
+impl Scenes {
+    /// Swaps scene `index` into `chunk`, returning whether `chunk` changed
+    fn activate(&mut self, chunk: &mut Chunk, index: usize) -> anyhow::Result<bool> {
+        if index >= self.chunks.len() {
+            bail!(
+                "Scene {index} does not exist, there are {} scenes",
+                self.chunks.len()
+            );
+        }
+        if self.active == Some(index) {
+            return Ok(false);
+        }
+
+        // while a scene is active its slot holds the chunk that was displaced by it, swapping
+        // back first restores that chunk so it ends up in the newly activated slot instead
+        if let Some(active) = self.active {
+            std::mem::swap(chunk, &mut self.chunks[active]);
+        }
+        std::mem::swap(chunk, &mut self.chunks[index]);
+        self.active = Some(index);
+        Ok(true)
+    }
+}
+
+#[derive(Default)]
+struct UndoHistory {
+    entries: VecDeque<Vec<(usize, Block)>>,
+}
+
+impl UndoHistory {
+    /// Runs `edit` on `chunk` as one undo entry, returning its result and the blocks it dirtied
+    fn edit_batch<R>(
+        &mut self,
+        chunk: &mut Chunk,
+        edit: impl FnOnce(&mut BlockEditor<'_>) -> R,
+    ) -> (R, Option<Range<usize>>) {
+        let mut editor = BlockEditor {
+            chunk,
+            dirty_blocks: None,
+            previous: vec![],
+        };
+        let result = edit(&mut editor);
+        let BlockEditor {
+            dirty_blocks,
+            previous,
+            ..
+        } = editor;
+        if !previous.is_empty() {
+            if self.entries.len() == MAX_UNDO_ENTRIES {
+                self.entries.pop_front();
+            }
+            self.entries.push_back(previous);
+        }
+        (result, dirty_blocks)
+    }
+
+    /// Writes the latest entry's blocks back into `chunk`, returning the blocks it changed
+    fn undo(&mut self, chunk: &mut Chunk) -> Option<Range<usize>> {
+        let previous = self.entries.pop_back()?;
+        let mut dirty_blocks = None;
+        for (index, block) in previous {
+            chunk.data[index] = block;
+            dirty_blocks = Some(merge_dirty_blocks(dirty_blocks, index..index + 1));
+        }
+        dirty_blocks
+    }
+}
+
+struct ActiveTour {
+    tour: Tour,
+    time: Duration,
+}
+
+struct CameraShake {
+    intensity: f32,
+    duration: Duration,
+    elapsed: Duration,
+}
+
+impl CameraShake {
+    fn amplitude(&self) -> f32 {
+        if self.elapsed >= self.duration {
+            return 0.0;
+        }
+        let remaining = 1.0 - self.elapsed.as_secs_f32() / self.duration.as_secs_f32();
+        self.intensity * remaining * remaining
+    }
+}
+
+fn camera_shake_offset(amplitude: f32, frame: u32) -> Transform {
+    let random = |index: u32| {
+        let mut x = frame
+            .wrapping_mul(8)
+            .wrapping_add(index)
+            .wrapping_mul(0x9E3779B9);
+        x ^= x >> 16;
+        x = x.wrapping_mul(0x85EBCA6B);
+        x ^= x >> 13;
+        x as f32 / u32::MAX as f32 * 2.0 - 1.0
+    };
+    Transform::translation([0.0, random(0), random(1), random(2)].map(|x| x * amplitude * 0.1))
+        * Transform::rotation_xy(random(3) * amplitude * 0.02)
+        * Transform::rotation_xz(random(4) * amplitude * 0.02)
+}
+
+/// Projects `position` to pixel coordinates the same way the shader casts rays,
+/// returning the pixel and the distance along the view direction, or `None` behind the camera
+pub fn project_point(
+    camera: Transform,
+    v_fov: f32,
+    viewport_size: [u32; 2],
+    position: [f32; 4],
+) -> Option<([f32; 2], f32)> {
+    let [forward, up, right, _] = (!camera).transform(position);
+    if forward <= f32::EPSILON {
+        return None;
+    }
+    let theta = (v_fov / 2.0).tan();
+    let [width, height] = viewport_size.map(|size| size as f32);
+    let u = right / (forward * (width / height) * theta);
+    let v = up / (forward * theta);
+    Some(([(u + 1.0) * 0.5 * width, (1.0 - v) * 0.5 * height], forward))
+}
+
+/// Projects `line` to pixel coordinates with `project_point`, after clipping off the part of it
+/// closer than the near plane, or `None` when all of it is
+fn project_line(
+    camera: Transform,
+    v_fov: f32,
+    viewport_size: [u32; 2],
+    line: [[f32; 4]; 2],
+) -> Option<[[f32; 2]; 2]> {
+    let (normal, distance) = camera.transform_plane([1.0, 0.0, 0.0, 0.0], VIEW_PLANE_GRID_NEAR);
+    let [start, end] = line;
+    let [start_side, end_side] =
+        line.map(|point| normal.iter().zip(point).map(|(a, b)| a * b).sum::<f32>() - distance);
+    if start_side < 0.0 && end_side < 0.0 {
+        return None;
+    }
+    let crossing = || {
+        let t = start_side / (start_side - end_side);
+        std::array::from_fn(|axis| start[axis] + (end[axis] - start[axis]) * t)
+    };
+    let [start, end] = match (start_side < 0.0, end_side < 0.0) {
+        (true, _) => [crossing(), end],
+        (_, true) => [start, crossing()],
+        _ => line,
+    };
+    let (start, _) = project_point(camera, v_fov, viewport_size, start)?;
+    let (end, _) = project_point(camera, v_fov, viewport_size, end)?;
+    Some([start, end])
+}
+
+/// The lines of a unit grid on the viewing hyperplane of `camera`, the hyperplane its own fourth
+/// axis is normal to, each running along its forward, up or right axis
+pub fn view_plane_grid_lines(camera: Transform) -> Vec<[[f32; 4]; 2]> {
+    let extent = VIEW_PLANE_GRID_EXTENT as f32;
+    let offsets = -VIEW_PLANE_GRID_EXTENT..=VIEW_PLANE_GRID_EXTENT;
+    let mut lines = Vec::with_capacity(MAX_VIEW_PLANE_LINES);
+    for axis in 0..3 {
+        for a in offsets.clone() {
+            for b in offsets.clone() {
+                let mut start = [0.0; 4];
+                start[(axis + 1) % 3] = a as f32;
+                start[(axis + 2) % 3] = b as f32;
+                let mut end = start;
+                start[axis] = -extent;
+                end[axis] = extent;
+                lines.push([camera.transform(start), camera.transform(end)]);
+            }
+        }
+    }
+    lines
+}
+
+/// Moves `view` along the line from the chunk's center through it until the whole chunk fits in the
+/// frame, then turns it to face the center
+fn frame_chunk_view(view: Transform, v_fov: f32, aspect: f32) -> Transform {
+    let half_size = CHUNK_SIZE as f32 * 0.5;
+    let center = [half_size; 4];
+    let radius = (4.0 * half_size * half_size).sqrt();
+    let half_v_fov = v_fov * 0.5;
+    let half_h_fov = (half_v_fov.tan() * aspect).atan();
+    let distance = radius / half_v_fov.min(half_h_fov).sin();
+
+    let position = view.position();
+    let offset: [f32; 4] = std::array::from_fn(|axis| position[axis] - center[axis]);
+    let length = offset.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let direction = if length > f32::EPSILON {
+        offset.map(|x| x / length)
+    } else {
+        view.transform_direction([-1.0, 0.0, 0.0, 0.0])
+    };
+    let framed_position: [f32; 4] =
+        std::array::from_fn(|axis| center[axis] + direction[axis] * distance);
+    (Transform::translation(std::array::from_fn(|axis| {
+        framed_position[axis] - position[axis]
+    })) * view)
+        .look_at(center)
+}
+
+/// Drops the vertical part of `direction` and renormalizes it, or returns zero when it points
+/// straight up or down
+fn flatten_to_horizontal(direction: [f32; 4]) -> [f32; 4] {
+    let [x, _, z, w] = direction;
+    let length = (x * x + z * z + w * w).sqrt();
+    if length <= f32::EPSILON {
+        return [0.0; 4];
+    }
+    [x / length, 0.0, z / length, w / length]
+}
+
+/// Turns a camera-local `displacement` into world space, keeping walking level and snapping each
+/// camera axis onto the nearest world axis for grid movement
+fn world_displacement(
+    displacement: [f32; 4],
+    view: Transform,
+    walk_movement: bool,
+    grid_movement: bool,
+) -> [f32; 4] {
+    let mut world_displacement = [0.0; 4];
+    for (axis, distance) in displacement.into_iter().enumerate() {
+        let mut direction = [0.0; 4];
+        direction[axis] = 1.0;
+        let mut direction = view.transform_direction(direction);
+        if walk_movement {
+            direction = if axis == 1 {
+                [0.0, 1.0, 0.0, 0.0]
+            } else {
+                flatten_to_horizontal(direction)
+            };
+        }
+        if grid_movement {
+            direction = snap_to_axis(direction);
+        }
+        for (world, direction) in world_displacement.iter_mut().zip(direction) {
+            *world += direction * distance;
+        }
+    }
+    world_displacement
+}
+
+/// Applies the world border, the world bounds, the loaded region and the pinned w, in that order
+fn clamp_camera_position(
+    mut position: [f32; 4],
+    world_border: Option<f32>,
+    world_bounds: Option<WorldBounds>,
+    confine_to_loaded: bool,
+    pin_w: Option<f32>,
+) -> [f32; 4] {
+    if let Some(radius) = world_border {
+        position = clamp_to_world_border(position, radius);
+    }
+    if let Some(world_bounds) = world_bounds {
+        position = world_bounds.clamp(position);
+    }
+    if confine_to_loaded {
+        position = clamp_to_loaded_region(position);
+    }
+    if let Some(w) = pin_w {
+        position[3] = w;
+    }
+    position
+}
+
+fn clamp_to_world_border(position: [f32; 4], radius: f32) -> [f32; 4] {
+    let distance = position.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if distance <= radius {
+        return position;
+    }
+    position.map(|x| x * radius / distance)
+}
+
+fn clamp_to_loaded_region(position: [f32; 4]) -> [f32; 4] {
+    position.map(|coordinate| {
+        coordinate.clamp(
+            -LOADED_REGION_MARGIN,
+            CHUNK_SIZE as f32 + LOADED_REGION_MARGIN,
+        )
+    })
+}
+
+fn scene_key_index(key: PhysicalKey) -> Option<usize> {
+    let PhysicalKey::Code(key_code) = key else {
+        return None;
+    };
+    [
+        KeyCode::Digit1,
+        KeyCode::Digit2,
+        KeyCode::Digit3,
+        KeyCode::Digit4,
+        KeyCode::Digit5,
+        KeyCode::Digit6,
+        KeyCode::Digit7,
+        KeyCode::Digit8,
+        KeyCode::Digit9,
+    ]
+    .iter()
+    .position(|&digit| digit == key_code)
+}
+
+/// The size to render a screenshot at for `supersample` samples per pixel along each axis
+fn supersampled_size(size: [u32; 2], supersample: u32, max_size: u32) -> anyhow::Result<[u32; 2]> {
+    let [width, height] = size;
+    match size.map(|x| x.checked_mul(supersample).filter(|&x| x <= max_size)) {
+        [Some(width), Some(height)] => Ok([width, height]),
+        _ => bail!(
+            "A {supersample}x supersampled {width}x{height} screenshot is larger than the {max_size} texture size limit"
+        ),
+    }
+}
+
+/// Averages each `factor` by `factor` square of rgba pixels into one, dropping any partial squares
+/// at the right and bottom edges
+fn downsample(pixels: &[u8], size: [u32; 2], factor: u32) -> Vec<u8> {
+    let stride = size[0] as usize;
+    let [width, height] = size.map(|x| (x / factor) as usize);
+    let factor = factor as usize;
+    let mut downsampled = Vec::with_capacity(width * height * 4);
+    for y in 0..height {
+        for x in 0..width {
+            let mut sum = [0u32; 4];
+            for sample in region_offsets([factor, factor, 1, 1]) {
+                let index = ((y * factor + sample[1]) * stride + x * factor + sample[0]) * 4;
+                for (sum, &channel) in sum.iter_mut().zip(&pixels[index..index + 4]) {
+                    *sum += channel as u32;
+                }
+            }
+            let count = (factor * factor) as u32;
+            downsampled.extend(sum.map(|sum| ((sum + count / 2) / count) as u8));
+        }
+    }
+    downsampled
+}
+
+fn encode_ppm(size: [u32; 2], pixels: &[u8]) -> Vec<u8> {
+    let [width, height] = size;
+    let mut ppm = format!("P6\n{width} {height}\n255\n").into_bytes();
+    ppm.extend(pixels.chunks_exact(4).flat_map(|pixel| &pixel[..3]));
+    ppm
+}
+
+fn letterbox_viewport(size: [u32; 2], aspect: f32) -> ([u32; 2], [u32; 2]) {
+    let [width, height] = size;
+    if width as f32 > height as f32 * aspect {
+        let viewport_width = ((height as f32 * aspect).round() as u32).clamp(1, width);
+        ([(width - viewport_width) / 2, 0], [viewport_width, height])
+    } else {
+        let viewport_height = ((width as f32 / aspect).round() as u32).clamp(1, height);
+        (
+            [0, (height - viewport_height) / 2],
+            [width, viewport_height],
+        )
+    }
+}
+
+/// Whether `local`, a position within a unit cell, is within `width` of one of the cell's edges, the
+/// same way the shader decides what to draw in wireframe mode
+#[cfg(test)]
+fn near_cell_edge(local: [f32; 4], width: f32) -> bool {
+    local.iter().filter(|&&x| x.min(1.0 - x) < width).count() >= 3
+}
+
+/// Picks the half of a split view that column `x` of a viewport `width` pixels wide falls in, the
+/// same way the shader does, returning the width of a half, the column within that half, and
+/// whether it's the right half
+#[cfg(test)]
+fn split_view_column(x: u32, width: u32) -> (u32, u32, bool) {
+    let half_width = width / 2;
+    if x >= half_width {
+        (half_width, x - half_width, true)
+    } else {
+        (half_width, x, false)
+    }
+}
+
+/// The curve the shader tonemaps each color channel with, which maps `white_point` to 1, or
+/// leaves the value alone when there's no white point
+#[cfg(test)]
+fn tonemap_curve(value: f32, white_point: f32, contrast: f32) -> f32 {
+    if white_point <= 0.0 {
+        return value;
+    }
+    let curved = value.max(0.0).powf(contrast);
+    let white = white_point.powf(contrast);
+    curved * (1.0 + curved / (white * white)) / (1.0 + curved)
+}
+
+struct Orbit {
+    target: [f32; 4],
+    distance: f32,
+    rotation: Transform,
+}
+
+impl Orbit {
+    fn transform(&self) -> Transform {
+        Transform::translation(self.target)
+            * self.rotation
+            * Transform::translation([-self.distance, 0.0, 0.0, 0.0])
+    }
+}
+
+#[derive(Default)]
+struct RefinementState {
+    last_camera_transform: Option<Transform>,
+    still_frames: u32,
+    sample_index: u32,
+}
+
+impl RefinementState {
+    fn reset(&mut self) {
+        self.still_frames = 0;
+        self.sample_index = 0;
+    }
+
+    fn advance(&mut self, camera_transform: Transform, enabled: bool) -> u32 {
+        if self.last_camera_transform != Some(camera_transform) {
+            self.last_camera_transform = Some(camera_transform);
+            self.reset();
+        } else {
+            self.still_frames = self.still_frames.saturating_add(1);
+        }
+
+        if !enabled || self.still_frames < STILL_FRAMES_BEFORE_REFINEMENT {
+            self.sample_index = 0;
+        } else {
+            self.sample_index = (self.sample_index + 1).min(MAX_REFINEMENT_SAMPLES - 1);
+        }
+        self.sample_index
+    }
+}
+
+/// The world axis `direction` points along most, or zero for a zero direction
+fn snap_to_axis(direction: [f32; 4]) -> [f32; 4] {
+    if direction.iter().all(|&x| x == 0.0) {
+        return [0.0; 4];
+    }
+    let axis = (0..4)
+        .max_by(|&a, &b| direction[a].abs().total_cmp(&direction[b].abs()))
+        .unwrap_or(0);
+    let mut snapped = [0.0; 4];
+    snapped[axis] = direction[axis].signum();
+    snapped
+}
+
+/// Returns the block next to the one containing `position` in the direction `gravity` pulls most,
+/// or `None` without gravity
+fn ground_cell(position: [f32; 4], gravity: [f32; 4]) -> Option<[i32; 4]> {
+    if gravity.iter().all(|&x| x == 0.0) {
+        return None;
+    }
+    let down = snap_to_axis(gravity);
+    Some(std::array::from_fn(|axis| {
+        position[axis].floor() as i32 + down[axis] as i32
+    }))
+}
+
+/// Advances a body falling under `gravity` by `ts` seconds, returning its new position and
+/// velocity, standing on a solid block stops it from falling any further
+fn fall_step(
+    chunk: &Chunk,
+    position: [f32; 4],
+    velocity: [f32; 4],
+    gravity: [f32; 4],
+    ts: f32,
+    collision_radius: Option<f32>,
+) -> ([f32; 4], [f32; 4]) {
+    let mut velocity: [f32; 4] = std::array::from_fn(|axis| velocity[axis] + gravity[axis] * ts);
+    let grounded = ground_cell(position, gravity)
+        .is_some_and(|cell| chunk.is_solid(cell.map(|coordinate| coordinate as f32 + 0.5)));
+    if grounded {
+        let down = snap_to_axis(gravity);
+        let falling_speed = std::iter::zip(velocity, down)
+            .map(|(velocity, down)| velocity * down)
+            .sum::<f32>();
+        if falling_speed > 0.0 {
+            velocity = std::array::from_fn(|axis| velocity[axis] - down[axis] * falling_speed);
+        }
+    }
+
+    let target = std::array::from_fn(|axis| position[axis] + velocity[axis] * ts);
+    let position = match collision_radius {
+        Some(radius) => chunk.resolve_collision(position, target, radius),
+        None => target,
+    };
+    (position, velocity)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{cell::RefCell, rc::Rc};
+
+    fn empty_chunk() -> Chunk {
+        Chunk {
+            data: std::array::from_fn(|_| Block {
+                color: Color {
+                    r: 0.0,
+                    g: 0.0,
+                    b: 0.0,
+                },
+                exists: 0,
+                health: 0,
+            }),
+        }
+    }
+
+    fn solid_block(r: f32) -> Block {
+        Block::new(Color { r, g: 0.5, b: 0.5 })
+    }
+
+    #[test]
+    fn ground_cell_follows_gravity() {
+        let position = [1.5, 1.5, 1.5, 1.5];
+        assert_eq!(ground_cell(position, DEFAULT_GRAVITY), Some([1, 0, 1, 1]));
+        assert_eq!(
+            ground_cell(position, [0.0, 9.81, 0.0, 0.0]),
+            Some([1, 2, 1, 1])
+        );
+        assert_eq!(
+            ground_cell(position, [0.5, 0.0, 0.0, -2.0]),
+            Some([1, 1, 1, 0])
+        );
+        assert_eq!(ground_cell(position, [0.0; 4]), None);
+    }
+
+    #[test]
+    fn upward_gravity_falls_up_onto_the_block_above() {
+        let gravity = [0.0, 9.81, 0.0, 0.0];
+        let mut chunk = empty_chunk();
+        // the block below would be the ground under the default gravity
+        chunk.data[Chunk::block_index([1, 0, 1, 1]).unwrap()] = solid_block(1.0);
+        chunk.data[Chunk::block_index([1, 3, 1, 1]).unwrap()] = solid_block(1.0);
+
+        let mut position = [1.5, 1.5, 1.5, 1.5];
+        let mut velocity = [0.0; 4];
+        (position, velocity) = fall_step(&chunk, position, velocity, gravity, 0.01, None);
+        assert!(position[1] > 1.5 && velocity[1] > 0.0);
+
+        for _ in 0..200 {
+            (position, velocity) = fall_step(&chunk, position, velocity, gravity, 0.01, None);
+        }
+        // resting in the cell under the ceiling block
+        assert_eq!(position[1].floor(), 2.0);
+        assert_eq!(velocity, [0.0; 4]);
+        assert_eq!(position[0], 1.5);
+        assert_eq!(position[2], 1.5);
+        assert_eq!(position[3], 1.5);
+    }
+
+    #[test]
+    fn default_gravity_rests_on_the_block_below() {
+        let mut chunk = empty_chunk();
+        chunk.data[Chunk::block_index([1, 0, 1, 1]).unwrap()] = solid_block(1.0);
+        let (position, velocity) = fall_step(
+            &chunk,
+            [1.5, 1.5, 1.5, 1.5],
+            [0.0; 4],
+            DEFAULT_GRAVITY,
+            0.01,
+            None,
+        );
+        assert_eq!(position, [1.5, 1.5, 1.5, 1.5]);
+        assert_eq!(velocity, [0.0; 4]);
+    }
+
+    fn shader_module() -> naga::Module {
+        naga::front::wgsl::parse_str(SHADER_SOURCE).unwrap()
+    }
+
+    fn shader_u32_constant(name: &str) -> u32 {
+        let module = shader_module();
+        let (_, constant) = module
+            .constants
+            .iter()
+            .find(|(_, constant)| constant.name.as_deref() == Some(name))
+            .unwrap_or_else(|| panic!("The shader has no constant `{name}`"));
+        match module.const_expressions[constant.init] {
+            naga::Expression::Literal(naga::Literal::U32(value)) => value,
+            ref expression => panic!("`{name}` is not a u32 literal: {expression:?}"),
+        }
+    }
+
+    fn shader_struct_members(name: &str) -> Vec<(String, u32)> {
+        let module = shader_module();
+        let members = module
+            .types
+            .iter()
+            .find_map(|(_, ty)| match &ty.inner {
+                naga::TypeInner::Struct { members, .. } if ty.name.as_deref() == Some(name) => {
+                    Some(members)
+                }
+                _ => None,
+            })
+            .unwrap_or_else(|| panic!("The shader has no struct `{name}`"));
+        members
+            .iter()
+            .map(|member| (member.name.clone().unwrap(), member.offset))
+            .collect()
+    }
+
+    fn shader_struct_offset(name: &str, member: &str) -> usize {
+        shader_struct_members(name)
+            .into_iter()
+            .find(|(name, _)| name == member)
+            .unwrap_or_else(|| panic!("The shader's `{name}` has no member `{member}`"))
+            .1 as usize
+    }
+
+    fn render_settings() -> RenderSettings {
+        let black = Color {
+            r: 0.0,
+            g: 0.0,
+            b: 0.0,
+        };
+        RenderSettings {
+            ambient_color: black,
+            ambient_strength: 0.0,
+            directional_light: 1,
+            sample_index: 0,
+            render_mode: 0,
+            split_view: 0,
+            split_screen: 0,
+            wireframe: 0,
+            voxel_margin: 0.0,
+            viewport_x: 0,
+            viewport_y: 0,
+            viewport_width: 0,
+            viewport_height: 0,
+            letterbox_color: black,
+            back_faces: 0,
+            outline_thickness: 0.0,
+            outline_darkness: 0.0,
+            world_border_radius: 0.0,
+            srgb_encode: 0,
+            sky_zenith_color: black,
+            sky_horizon_color: black,
+            sky_nadir_color: black,
+            tonemap_white_point: 0.0,
+            tonemap_contrast: 0.0,
+            exposure: 0.0,
+            measure_luminance: 0,
+            view_plane_gizmo: 0,
+            shadows: 0,
+            shadow_bias: 0.0,
+            aperture: 0.0,
+            focal_distance: 0.0,
+            custom_heatmap_gradient: 0,
+            selection_min: [0; 4].into(),
+            selection_max: [0; 4].into(),
+        }
+    }
+
+    /// A device on any adapter that's available, the tests that need one are ignored by default
+    /// and run with `cargo test -- --ignored` on a machine with a GPU or software adapter
+    fn test_device() -> (wgpu::Device, wgpu::Queue) {
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+        let adapter =
+            pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions::default()))
+                .expect("No adapter is available");
+        pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor::default(), None))
+            .unwrap()
+    }
+
+    /// Render settings for a plain, fully lit frame over the whole of `size`
+    fn frame_render_settings(size: [u32; 2]) -> RenderSettings {
+        RenderSettings {
+            viewport_width: size[0],
+            viewport_height: size[1],
+            tonemap_contrast: 1.0,
+            exposure: 1.0,
+            ..render_settings()
+        }
+    }
+
+    /// The main texture bind group layout and luminance buffer `FrameTargets::new` needs
+    fn frame_target_resources(device: &wgpu::Device) -> (wgpu::BindGroupLayout, wgpu::Buffer) {
+        let module = pollster::block_on(create_compute_shader(device, SHADER_SOURCE)).unwrap();
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: None,
+            layout: None,
+            module: &module,
+            entry_point: "main",
+        });
+        let luminance = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: 4,
+            usage: wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+        (pipeline.get_bind_group_layout(0), luminance)
+    }
+
+    struct HeadlessFrame {
+        device: wgpu::Device,
+        queue: wgpu::Queue,
+        main_texture: wgpu::Texture,
+        depth_texture: wgpu::Texture,
+        chunk_buffer: wgpu::Buffer,
+    }
+
+    /// Runs the compute shader once into fresh textures, with the pipeline laying out its own
+    /// bind groups from the shader
+    fn render_headless(
+        size: [u32; 2],
+        camera: Transform,
+        chunk: &Chunk,
+        render_settings: &RenderSettings,
+    ) -> HeadlessFrame {
+        render_headless_split(size, [camera; 2], chunk, render_settings)
+    }
+
+    /// `render_headless` with a separate camera for the right half of a split screen
+    fn render_headless_split(
+        size: [u32; 2],
+        cameras: [Transform; 2],
+        chunk: &Chunk,
+        render_settings: &RenderSettings,
+    ) -> HeadlessFrame {
+        use wgpu::util::DeviceExt;
+
+        let (device, queue) = test_device();
+        let [width, height] = size;
+        let texture = |format| {
+            device.create_texture(&wgpu::TextureDescriptor {
+                label: None,
+                size: wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: wgpu::TextureUsages::STORAGE_BINDING
+                    | wgpu::TextureUsages::TEXTURE_BINDING
+                    | wgpu::TextureUsages::COPY_SRC,
+                view_formats: &[],
+            })
+        };
+        let main_texture = texture(wgpu::TextureFormat::Rgba8Unorm);
+        let depth_texture = texture(wgpu::TextureFormat::R32Float);
+        let buffer = |contents: &[u8], usage| {
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: None,
+                contents,
+                usage,
+            })
+        };
+        let uniform = |value: &dyn Fn(&mut UniformBuffer<Vec<u8>>)| {
+            let mut bytes = UniformBuffer::new(vec![]);
+            value(&mut bytes);
+            buffer(&bytes.into_inner(), wgpu::BufferUsages::UNIFORM)
+        };
+        let storage = |value: &dyn Fn(&mut StorageBuffer<Vec<u8>>)| {
+            let mut bytes = StorageBuffer::new(vec![]);
+            value(&mut bytes);
+            buffer(&bytes.into_inner(), wgpu::BufferUsages::STORAGE)
+        };
+
+        let [camera, second_camera] = cameras.map(|camera| {
+            uniform(&|bytes| {
+                bytes
+                    .write(&Camera {
+                        transform: camera,
+                        v_fov: 90.0f32.to_radians(),
+                    })
+                    .unwrap()
+            })
+        });
+        // the grid of the main camera, projected the way `Game::render` does
+        let projected_lines = if render_settings.view_plane_gizmo != 0 {
+            view_plane_grid_lines(cameras[0])
+                .into_iter()
+                .filter_map(|line| project_line(cameras[0], 90.0f32.to_radians(), size, line))
+                .collect()
+        } else {
+            vec![]
+        };
+        let render_settings = uniform(&|bytes| bytes.write(render_settings).unwrap());
+        let debug_points = storage(&|bytes| {
+            bytes
+                .write(&DebugPoints {
+                    count: 0,
+                    points: std::array::from_fn(|_| DebugPoint {
+                        x: 0.0,
+                        y: 0.0,
+                        radius: 0.0,
+                        color: Color {
+                            r: 0.0,
+                            g: 0.0,
+                            b: 0.0,
+                        },
+                    }),
+                })
+                .unwrap()
+        });
+        let heatmap_gradient = storage(&|bytes| {
+            bytes
+                .write(&HeatmapGradient {
+                    colors: std::array::from_fn(|_| Color {
+                        r: 0.0,
+                        g: 0.0,
+                        b: 0.0,
+                    }),
+                })
+                .unwrap()
+        });
+        let view_plane_lines = storage(&|bytes| {
+            let mut lines = std::array::from_fn(|_| ViewPlaneLine {
+                start_x: 0.0,
+                start_y: 0.0,
+                end_x: 0.0,
+                end_y: 0.0,
+            });
+            for (slot, &[[start_x, start_y], [end_x, end_y]]) in
+                lines.iter_mut().zip(&projected_lines)
+            {
+                *slot = ViewPlaneLine {
+                    start_x,
+                    start_y,
+                    end_x,
+                    end_y,
+                };
+            }
+            bytes
+                .write(&ViewPlaneLines {
+                    count: projected_lines.len() as u32,
+                    lines,
+                })
+                .unwrap()
+        });
+        let chunk_buffer = {
+            let mut bytes = StorageBuffer::new(vec![]);
+            bytes.write(chunk).unwrap();
+            buffer(
+                &bytes.into_inner(),
+                wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            )
+        };
+        let accumulation = buffer(
+            &vec![0; width as usize * height as usize * std::mem::size_of::<[f32; 4]>()],
+            wgpu::BufferUsages::STORAGE,
+        );
+        let luminance = buffer(&[0; 4], wgpu::BufferUsages::STORAGE);
+
+        let module = pollster::block_on(create_compute_shader(&device, SHADER_SOURCE)).unwrap();
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: None,
+            layout: None,
+            module: &module,
+            entry_point: "main",
+        });
+        let main_texture_view = main_texture.create_view(&Default::default());
+        let depth_texture_view = depth_texture.create_view(&Default::default());
+        let bind_group = |group: u32, resources: &[wgpu::BindingResource]| {
+            let entries = resources
+                .iter()
+                .enumerate()
+                .map(|(binding, resource)| wgpu::BindGroupEntry {
+                    binding: binding as u32,
+                    resource: resource.clone(),
+                })
+                .collect::<Vec<_>>();
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: None,
+                layout: &pipeline.get_bind_group_layout(group),
+                entries: &entries,
+            })
+        };
+        let bind_groups = [
+            bind_group(
+                0,
+                &[
+                    wgpu::BindingResource::TextureView(&main_texture_view),
+                    accumulation.as_entire_binding(),
+                    wgpu::BindingResource::TextureView(&depth_texture_view),
+                    luminance.as_entire_binding(),
+                ],
+            ),
+            bind_group(
+                1,
+                &[
+                    camera.as_entire_binding(),
+                    second_camera.as_entire_binding(),
+                ],
+            ),
+            bind_group(2, &[chunk_buffer.as_entire_binding()]),
+            bind_group(
+                3,
+                &[
+                    render_settings.as_entire_binding(),
+                    debug_points.as_entire_binding(),
+                    heatmap_gradient.as_entire_binding(),
+                    view_plane_lines.as_entire_binding(),
+                ],
+            ),
+        ];
+
+        let mut encoder = device.create_command_encoder(&Default::default());
+        {
+            let mut compute_pass = encoder.begin_compute_pass(&Default::default());
+            compute_pass.set_pipeline(&pipeline);
+            for (index, bind_group) in bind_groups.iter().enumerate() {
+                compute_pass.set_bind_group(index as u32, bind_group, &[]);
+            }
+            compute_pass.dispatch_workgroups(width.div_ceil(16), height.div_ceil(16), 1);
+        }
+        queue.submit([encoder.finish()]);
+
+        HeadlessFrame {
+            device,
+            queue,
+            main_texture,
+            depth_texture,
+            chunk_buffer,
+        }
+    }
+
+    impl HeadlessFrame {
+        fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+            read_texel(&self.device, &self.queue, &self.main_texture, x, y).unwrap()
+        }
+
+        fn depth(&self, x: u32, y: u32) -> f32 {
+            f32::from_le_bytes(
+                read_texel(&self.device, &self.queue, &self.depth_texture, x, y).unwrap(),
+            )
+        }
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("game2-test-{}-{name}", std::process::id()))
+    }
+
+    fn encode_render_settings(render_settings: &RenderSettings) -> Vec<u8> {
+        let mut buffer = UniformBuffer::new(vec![]);
+        buffer.write(render_settings).unwrap();
+        buffer.into_inner()
+    }
+
+    fn read_f32s<const N: usize>(bytes: &[u8], offset: usize) -> [f32; N] {
+        std::array::from_fn(|i| {
+            f32::from_le_bytes(
+                bytes[offset + i * 4..offset + i * 4 + 4]
+                    .try_into()
+                    .unwrap(),
+            )
+        })
+    }
+
+    #[test]
+    fn render_settings_match_the_shader_layout() {
+        let bytes = encode_render_settings(&render_settings());
+        assert_eq!(bytes.len(), RenderSettings::SHADER_SIZE.get() as usize);
+        let (last_member, last_offset) = shader_struct_members("RenderSettings").pop().unwrap();
+        assert_eq!(last_member, "selection_max");
+        assert_eq!(last_offset as usize + 16, bytes.len());
+    }
+
+    #[test]
+    fn ambient_is_written_where_the_shader_reads_it() {
+        let bytes = encode_render_settings(&RenderSettings {
+            ambient_color: Color {
+                r: 0.25,
+                g: 0.5,
+                b: 0.75,
+            },
+            ambient_strength: 2.0,
+            ..render_settings()
+        });
+        assert_eq!(
+            read_f32s(
+                &bytes,
+                shader_struct_offset("RenderSettings", "ambient_color")
+            ),
+            [0.25, 0.5, 0.75]
+        );
+        assert_eq!(
+            read_f32s(
+                &bytes,
+                shader_struct_offset("RenderSettings", "ambient_strength")
+            ),
+            [2.0]
+        );
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn ambient_alone_lights_hits_with_the_sun_off() {
+        let mut chunk = empty_chunk();
+        chunk.data[Chunk::block_index([3, 1, 1, 1]).unwrap()] = solid_block(1.0);
+        let camera = Transform::translation([0.5, 1.5, 1.5, 1.5]);
+        let size = [32, 24];
+        let center = |directional_light, ambient_strength| {
+            let settings = RenderSettings {
+                ambient_color: Color {
+                    r: 1.0,
+                    g: 1.0,
+                    b: 1.0,
+                },
+                ambient_strength,
+                directional_light,
+                ..frame_render_settings(size)
+            };
+            let frame = render_headless(size, camera, &chunk, &settings);
+            assert!(frame.depth(16, 12) > 0.0);
+            frame.pixel(16, 12)
+        };
+        let unlit = center(0, 0.0);
+        assert_eq!(unlit[..3], [0, 0, 0]);
+        let ambient = center(0, 0.5);
+        assert!(
+            ambient[..3].iter().all(|&channel| channel > 0),
+            "{ambient:?}"
+        );
+        let sunlit = center(1, 0.0);
+        assert!(sunlit[0] > 0, "{sunlit:?}");
+    }
+
+    #[test]
+    fn finishing_auto_save_writes_pending_edits() {
+        let path = temp_path("finish-auto-save.chunk");
+        let mut chunk = empty_chunk();
+        chunk.data[5] = solid_block(1.0);
+        let mut auto_save = AutoSave {
+            settings: AutoSaveSettings {
+                path: path.clone(),
+                idle_time: Duration::from_secs(60),
+            },
+            time_since_edit: Some(Duration::ZERO),
+            save_thread: None,
+        };
+
+        auto_save.finish(&chunk).unwrap();
+        let saved = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(Chunk::from_bytes(&saved).unwrap() == chunk);
+        assert_eq!(auto_save.time_since_edit, None);
+
+        // nothing is pending any more, so finishing again doesn't write
+        auto_save.finish(&chunk).unwrap();
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn scroll_turns_the_same_at_any_frame_rate() {
+        let net_angle = |frames: u32| {
+            let dt = 2.0 / frames as f32;
+            let mut pending_scroll = 30.0;
+            let mut rotation = Transform::IDENTITY;
+            for _ in 0..frames {
+                rotation = rotation * Transform::rotation_xw(take_scroll(&mut pending_scroll, dt));
+            }
+            assert_eq!(pending_scroll, 0.0);
+            rotation.rotation_plane().1
+        };
+        let angle = net_angle(30);
+        assert!((angle - 0.3).abs() <= 1e-4);
+        for frames in [45, 120, 1000] {
+            assert!((net_angle(frames) - angle).abs() <= 1e-4);
+        }
+    }
+
+    #[test]
+    fn scroll_is_clamped_per_frame() {
+        let mut pending_scroll = -100.0;
+        let turn = take_scroll(&mut pending_scroll, 0.1);
+        assert!((turn + MAX_SCROLL_PER_SECOND * 0.1 * 0.01).abs() <= 1e-6);
+        assert!((pending_scroll + 100.0 - MAX_SCROLL_PER_SECOND * 0.1).abs() <= 1e-4);
+    }
+
+    #[test]
+    fn refinement_starts_after_still_frames() {
+        let camera = Transform::translation([1.0, 0.0, 0.0, 0.0]);
+        let mut refinement_state = RefinementState::default();
+        for _ in 0..STILL_FRAMES_BEFORE_REFINEMENT {
+            assert_eq!(refinement_state.advance(camera, true), 0);
+        }
+        assert_eq!(refinement_state.advance(camera, true), 1);
+        assert_eq!(refinement_state.advance(camera, true), 2);
+
+        // moving goes back to single samples
+        assert_eq!(refinement_state.advance(Transform::IDENTITY, true), 0);
+        assert_eq!(refinement_state.advance(Transform::IDENTITY, true), 0);
+    }
+
+    #[test]
+    fn refinement_is_capped_and_can_be_disabled() {
+        let mut refinement_state = RefinementState::default();
+        let sample_index = (0..STILL_FRAMES_BEFORE_REFINEMENT + MAX_REFINEMENT_SAMPLES * 2)
+            .map(|_| refinement_state.advance(Transform::IDENTITY, true))
+            .last();
+        assert_eq!(sample_index, Some(MAX_REFINEMENT_SAMPLES - 1));
+
+        let mut refinement_state = RefinementState::default();
+        for _ in 0..STILL_FRAMES_BEFORE_REFINEMENT * 2 {
+            assert_eq!(refinement_state.advance(Transform::IDENTITY, false), 0);
+        }
+    }
+
+    fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn render_modes_match_the_shader() {
+        for (render_mode, constant) in [
+            (RenderMode::Normals, "RENDER_MODE_NORMALS"),
+            (RenderMode::StepHeatmap, "RENDER_MODE_STEP_HEATMAP"),
+            (RenderMode::ChunkTint, "RENDER_MODE_CHUNK_TINT"),
+            (RenderMode::Depth, "RENDER_MODE_DEPTH"),
+        ] {
+            assert_eq!(render_mode as u32, shader_u32_constant(constant));
+        }
+    }
+
+    #[test]
+    fn debug_heatmap_toggles_the_render_mode_uniform() {
+        let offset = shader_struct_offset("RenderSettings", "render_mode");
+        let encoded_mode = |debug_heatmap| {
+            let bytes = encode_render_settings(&RenderSettings {
+                render_mode: RenderMode::from_debug_heatmap(debug_heatmap) as u32,
+                ..render_settings()
+            });
+            read_u32(&bytes, offset)
+        };
+        assert_eq!(
+            encoded_mode(true),
+            shader_u32_constant("RENDER_MODE_STEP_HEATMAP")
+        );
+        // normal shading is the shader's default case, which no other mode shares
+        assert_eq!(encoded_mode(false), RenderMode::Normal as u32);
+    }
+
+    #[test]
+    fn framing_fits_every_chunk_corner_in_view() {
+        let views = [
+            Transform::IDENTITY,
+            Transform::translation([10.0, -3.0, 1.0, 2.0]) * Transform::rotation_xz(2.0),
+            Transform::translation([CHUNK_SIZE as f32 * 0.5; 4]) * Transform::rotation_yw(0.7),
+            Transform::translation([-1.0, 5.0, 0.0, -4.0])
+                * Transform::rotation_xw(1.0)
+                * Transform::rotation_xy(-0.4),
+        ];
+        for view in views {
+            for (size, v_fov) in [
+                ([1600, 900], 90.0f32),
+                ([900, 1600], 60.0),
+                ([500, 500], 30.0),
+            ] {
+                let aspect = size[0] as f32 / size[1] as f32;
+                let framed = frame_chunk_view(view, v_fov.to_radians(), aspect);
+                for corner in region_offsets([2; 4]) {
+                    let corner = corner.map(|bit| (bit * CHUNK_SIZE) as f32);
+                    let ([x, y], _) = project_point(framed, v_fov.to_radians(), size, corner)
+                        .expect("Corner is behind the camera");
+                    assert!((0.0..=size[0] as f32).contains(&x), "{corner:?} at x {x}");
+                    assert!((0.0..=size[1] as f32).contains(&y), "{corner:?} at y {y}");
+                }
+
+                let ([x, y], _) = project_point(
+                    framed,
+                    v_fov.to_radians(),
+                    size,
+                    [CHUNK_SIZE as f32 * 0.5; 4],
+                )
+                .unwrap();
+                assert!((x - size[0] as f32 * 0.5).abs() <= 1e-2);
+                assert!((y - size[1] as f32 * 0.5).abs() <= 1e-2);
+            }
+        }
+    }
+
+    #[test]
+    fn w_speed_scales_only_w_movement() {
+        let mut movement_state = MovementState {
+            forward: 1.0,
+            up: 1.0,
+            right: 1.0,
+            ana: 1.0,
+            ..MovementState::default()
+        };
+        let uniform = movement_state.displacement(0.5);
+        movement_state.axis_speeds = [1.0, 1.0, 1.0, 0.25];
+        let slowed = movement_state.displacement(0.5);
+        assert_eq!(slowed[..3], uniform[..3]);
+        assert_eq!(slowed[3], uniform[3] * 0.25);
+        assert_eq!(uniform, [movement_state.speed * 0.5; 4]);
+    }
+
+    #[test]
+    fn orbit_stays_at_its_distance_from_the_target() {
+        let mut orbit = Orbit {
+            target: [1.0, 2.0, -3.0, 0.5],
+            distance: 7.5,
+            rotation: Transform::IDENTITY,
+        };
+        for step in 0..50 {
+            let angle = step as f32 * 0.3;
+            orbit.rotation = orbit.rotation
+                * Transform::rotation_xz(angle)
+                * Transform::rotation_xy(-angle * 0.5)
+                * Transform::rotation_xw(angle * 0.2);
+            let position = orbit.transform().position();
+            let distance = std::iter::zip(position, orbit.target)
+                .map(|(position, target)| (position - target) * (position - target))
+                .sum::<f32>()
+                .sqrt();
+            assert!((distance - orbit.distance).abs() <= 1e-3, "{distance}");
+        }
+    }
+
+    #[test]
+    fn split_view_switches_halves_at_the_boundary() {
+        assert_eq!(split_view_column(0, 640), (320, 0, false));
+        assert_eq!(split_view_column(319, 640), (320, 319, false));
+        assert_eq!(split_view_column(320, 640), (320, 0, true));
+        assert_eq!(split_view_column(639, 640), (320, 319, true));
+
+        // the extra column of an odd width goes to the right half
+        assert_eq!(split_view_column(319, 641), (320, 319, false));
+        assert_eq!(split_view_column(320, 641), (320, 0, true));
+        assert_eq!(split_view_column(640, 641), (320, 320, true));
+    }
+
+    #[test]
+    fn wireframe_only_draws_near_cell_edges() {
+        let width = 0.05;
+        // an edge of a tesseract cell has three of its four coordinates on the cell's boundary
+        assert!(near_cell_edge([0.0, 0.0, 0.0, 0.5], width));
+        assert!(near_cell_edge([0.5, 0.02, 0.98, 0.01], width));
+        assert!(near_cell_edge([0.01, 0.99, 0.03, 0.97], width));
+
+        assert!(!near_cell_edge([0.5; 4], width));
+        // faces aren't edges
+        assert!(!near_cell_edge([0.0, 0.0, 0.5, 0.5], width));
+        assert!(!near_cell_edge([0.0, 0.0, 0.06, 0.5], width));
+        assert!(!near_cell_edge([0.0, 1.0, 0.9, 0.5], width));
+    }
+
+    #[test]
+    fn editing_a_block_uploads_only_its_bytes() {
+        let mut chunk = empty_chunk();
+        let mut before = StorageBuffer::new(vec![0; Chunk::SHADER_SIZE.get() as usize]);
+        before.write(&chunk).unwrap();
+        let before = before.into_inner();
+
+        let (result, dirty_blocks) = UndoHistory::default().edit_batch(&mut chunk, |editor| {
+            editor.set_block([1, 2, 3, 0], solid_block(0.5))
+        });
+        result.unwrap();
+        let dirty_blocks = dirty_blocks.unwrap();
+        let index = Chunk::block_index([1, 2, 3, 0]).unwrap();
+        assert_eq!(dirty_blocks, index..index + 1);
+
+        let mut after = StorageBuffer::new(vec![0; Chunk::SHADER_SIZE.get() as usize]);
+        after.write(&chunk).unwrap();
+        let after = after.into_inner();
+
+        let dirty_bytes = dirty_byte_range(dirty_blocks, chunk.data.len());
+        assert_eq!(
+            dirty_bytes.len() as u64,
+            Chunk::SHADER_SIZE.get() / chunk.data.len() as u64
+        );
+        let changed = (0..before.len())
+            .filter(|&i| before[i] != after[i])
+            .collect::<Vec<_>>();
+        assert!(!changed.is_empty());
+        assert!(
+            changed.iter().all(|i| dirty_bytes.contains(i)),
+            "{changed:?}"
+        );
+    }
+
+    #[test]
+    fn dirty_blocks_are_coalesced() {
+        assert_eq!(merge_dirty_blocks(None, 4..5), 4..5);
+        assert_eq!(merge_dirty_blocks(Some(4..5), 9..10), 4..10);
+        assert_eq!(merge_dirty_blocks(Some(4..10), 0..1), 0..10);
+        assert_eq!(merge_dirty_blocks(Some(4..10), 5..6), 4..10);
+    }
+
+    #[test]
+    fn auto_save_fires_after_idle_time_and_edits_reset_it() {
+        let path = temp_path("idle-auto-save.chunk");
+        let mut chunk = empty_chunk();
+        let mut auto_save = AutoSave {
+            settings: AutoSaveSettings {
+                path: path.clone(),
+                idle_time: Duration::from_secs(5),
+            },
+            time_since_edit: None,
+            save_thread: None,
+        };
+
+        // nothing to save before the first edit
+        auto_save.update(&chunk, Duration::from_secs(60)).unwrap();
+        assert!(auto_save.save_thread.is_none());
+
+        chunk.data[3] = solid_block(1.0);
+        auto_save.mark_edited();
+        auto_save.update(&chunk, Duration::from_secs(4)).unwrap();
+        assert!(auto_save.save_thread.is_none());
+
+        // editing again restarts the idle time
+        chunk.data[4] = solid_block(0.5);
+        auto_save.mark_edited();
+        auto_save.update(&chunk, Duration::from_secs(4)).unwrap();
+        assert!(auto_save.save_thread.is_none());
+
+        auto_save.update(&chunk, Duration::from_secs(1)).unwrap();
+        assert_eq!(auto_save.time_since_edit, None);
+        auto_save.save_thread.take().unwrap().join().unwrap();
+        let saved = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(Chunk::from_bytes(&saved).unwrap() == chunk);
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn the_shader_compiles() {
+        let (device, _queue) = test_device();
+        pollster::block_on(create_compute_shader(&device, SHADER_SOURCE)).unwrap();
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn shader_errors_point_at_the_source() {
+        let (device, _queue) = test_device();
+        let error = pollster::block_on(create_compute_shader(
+            &device,
+            "fn main() {\n    let x = 1.0 +;\n}\n",
+        ))
+        .unwrap_err()
+        .to_string();
+        assert!(
+            error.starts_with("Could not create the compute shader"),
+            "{error}"
+        );
+        assert!(error.contains("let x = 1.0 +;"), "{error}");
+        assert!(error.contains("wgsl:2:"), "{error}");
+    }
+
+    #[test]
+    fn world_time_is_scaled() {
+        let dt = Duration::from_millis(16);
+        for time_scale in [0.0, 0.25, 1.0, 4.0] {
+            let expected = dt.as_secs_f64() * time_scale as f64;
+            let scaled = scaled_dt(dt, time_scale).as_secs_f64();
+            assert!((scaled - expected).abs() <= 1e-6, "{scaled} != {expected}");
+        }
+        assert_eq!(scaled_dt(dt, 0.0), Duration::ZERO);
+    }
+
+    #[test]
+    fn absolute_look_ignores_how_movement_is_split_into_events() {
+        let look = |deltas: &[(f32, f32)]| {
+            let mut absolute_pitch = 0.0;
+            let mut camera_transform = Transform::IDENTITY;
+            let mut camera_vertical_look = Transform::IDENTITY;
+            for &(x, y) in deltas {
+                let (horizontal_look, vertical_look) = absolute_look(&mut absolute_pitch, x, y);
+                camera_vertical_look = camera_vertical_look * vertical_look;
+                camera_transform = camera_transform * horizontal_look;
+            }
+            camera_transform * camera_vertical_look
+        };
+
+        // both sum to (1200, -300)
+        let whole = look(&[(1200.0, -300.0)]);
+        let split = look(&[
+            (100.0, -50.0),
+            (700.0, 20.0),
+            (-200.0, -170.0),
+            (600.0, -100.0),
+        ]);
+        assert!(
+            whole.represents_same_rotation(split, 1e-4),
+            "{whole:?} != {split:?}"
+        );
+
+        // going past the pitch clamp and back lands where the summed movement says
+        let whole = look(&[(0.0, -1000.0)]);
+        let split = look(&[(0.0, -3000.0), (0.0, 2000.0)]);
+        assert!(
+            whole.represents_same_rotation(split, 1e-4),
+            "{whole:?} != {split:?}"
+        );
+        let clamped = look(&[(0.0, -3000.0)]);
+        assert!(clamped.represents_same_rotation(Transform::rotation_xy(FRAC_PI_2), 1e-4));
+    }
+
+    #[test]
+    fn voxel_margin_is_clamped_and_uploaded() {
+        assert_eq!(clamp_voxel_margin(0.0), 0.0);
+        assert_eq!(clamp_voxel_margin(0.1), 0.1);
+        assert_eq!(clamp_voxel_margin(0.5), 0.5);
+        assert_eq!(clamp_voxel_margin(0.8), 0.5);
+        assert_eq!(clamp_voxel_margin(-0.2), 0.0);
+        assert_eq!(clamp_voxel_margin(f32::NAN), 0.0);
+
+        let bytes = encode_render_settings(&RenderSettings {
+            voxel_margin: clamp_voxel_margin(0.125),
+            ..render_settings()
+        });
+        assert_eq!(
+            read_f32s(
+                &bytes,
+                shader_struct_offset("RenderSettings", "voxel_margin")
+            ),
+            [0.125]
+        );
+    }
+
+    #[test]
+    fn letterbox_fits_the_aspect_inside_the_surface() {
+        // wider than 16:9 gets bars on the sides
+        assert_eq!(
+            letterbox_viewport([1000, 360], 16.0 / 9.0),
+            ([180, 0], [640, 360])
+        );
+        // taller than 16:9 gets bars on the top and bottom
+        assert_eq!(
+            letterbox_viewport([640, 1000], 16.0 / 9.0),
+            ([0, 320], [640, 360])
+        );
+        // a matching aspect fills the surface
+        assert_eq!(
+            letterbox_viewport([1280, 720], 16.0 / 9.0),
+            ([0, 0], [1280, 720])
+        );
+        // extreme aspects still leave a pixel to draw
+        assert_eq!(letterbox_viewport([100, 100], 1e-6), ([49, 0], [1, 100]));
+        assert_eq!(letterbox_viewport([100, 100], 1e6), ([0, 49], [100, 1]));
+    }
+
+    #[test]
+    fn split_view_divides_the_letterboxed_viewport() {
+        let ([viewport_x, _], [viewport_width, _]) = letterbox_viewport([1000, 360], 16.0 / 9.0);
+        let split = |x: u32| split_view_column(x - viewport_x, viewport_width);
+        assert_eq!(split(viewport_x), (320, 0, false));
+        assert_eq!(split(viewport_x + 319), (320, 319, false));
+        assert_eq!(split(viewport_x + 320), (320, 0, true));
+        assert_eq!(split(viewport_x + viewport_width - 1), (320, 319, true));
+    }
+
+    #[test]
+    fn frame_callbacks_fire_once_per_frame_in_order() {
+        let calls = Rc::new(RefCell::new(vec![]));
+        let mut frame_callbacks = FrameCallbacks {
+            on_pre_update: Some(Box::new({
+                let calls = calls.clone();
+                move |dt| {
+                    calls
+                        .borrow_mut()
+                        .push(format!("pre_update {}", dt.as_millis()))
+                }
+            })),
+            on_post_draw: Some(Box::new({
+                let calls = calls.clone();
+                move || calls.borrow_mut().push("post_draw".into())
+            })),
+        };
+
+        for frame in 1..=3 {
+            frame_callbacks.pre_update(Duration::from_millis(frame));
+            frame_callbacks.post_draw();
+        }
+        assert_eq!(
+            *calls.borrow(),
+            [
+                "pre_update 1",
+                "post_draw",
+                "pre_update 2",
+                "post_draw",
+                "pre_update 3",
+                "post_draw",
+            ]
+        );
+
+        // removing the callbacks stops them firing
+        frame_callbacks.on_pre_update = None;
+        frame_callbacks.on_post_draw = None;
+        frame_callbacks.pre_update(Duration::from_millis(4));
+        frame_callbacks.post_draw();
+        assert_eq!(calls.borrow().len(), 6);
+    }
+
+    #[test]
+    fn back_faces_are_off_by_default_and_uploaded() {
+        // `Game::new` starts from the default debug settings
+        let debug_settings = DebugSettings::default();
+        assert!(!debug_settings.back_faces);
+
+        let offset = shader_struct_offset("RenderSettings", "back_faces");
+        for (back_faces, expected) in [(false, 0), (true, 1)] {
+            let bytes = encode_render_settings(&RenderSettings {
+                back_faces: back_faces as u32,
+                ..render_settings()
+            });
+            assert_eq!(read_u32(&bytes, offset), expected);
+        }
+    }
+
+    #[test]
+    fn outlines_darken_only_near_face_edges() {
+        let outline = OutlineSettings {
+            thickness: 0.1,
+            darkness: 0.75,
+        };
+        let normal = [0.0, 1.0, 0.0, 0.0];
+        // the middle of a face, and the y coordinate on the face's own plane doesn't count
+        assert_eq!(outline.factor([0.5, 3.0, 0.5, 0.5], normal), 1.0);
+        // near an edge on each side, including across negative cells
+        assert_eq!(outline.factor([0.05, 3.0, 0.5, 0.5], normal), 0.25);
+        assert_eq!(outline.factor([0.5, 3.0, 0.95, 0.5], normal), 0.25);
+        assert_eq!(outline.factor([0.5, 3.0, 0.5, -1.02], normal), 0.25);
+        assert_eq!(outline.factor([0.5, 3.0, 0.5, -1.5], normal), 1.0);
+        // just outside the thickness
+        assert_eq!(outline.factor([0.2, 3.0, 0.5, 0.5], normal), 1.0);
+
+        let disabled = OutlineSettings {
+            thickness: 0.0,
+            darkness: 0.75,
+        };
+        assert_eq!(disabled.factor([0.0, 3.0, 0.0, 0.0], normal), 1.0);
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn reads_back_a_rendered_block() {
+        let mut chunk = empty_chunk();
+        chunk.data[Chunk::block_index([1, 1, 1, 1]).unwrap()] = Block {
+            color: Color {
+                r: 1.0,
+                g: 0.0,
+                b: 0.0,
+            },
+            exists: 1,
+            health: MAX_BLOCK_HEALTH,
+        };
+        // looking along x at the middle of the block
+        let camera = Transform::translation([-2.0, 1.5, 1.5, 1.5]);
+        let size = [64, 48];
+        let frame = render_headless(size, camera, &chunk, &frame_render_settings(size));
+
+        let [r, g, b, a] = frame.pixel(32, 24);
+        assert!(r > 64 && g == 0 && b == 0 && a == 255, "{:?}", [r, g, b, a]);
+        assert!((frame.depth(32, 24) - 3.0).abs() <= 0.01);
+
+        // the sky is black, and odd coordinates still land on the right texel despite the copy
+        // being padded out to a whole aligned row
+        assert_eq!(frame.pixel(0, 0), [0, 0, 0, 255]);
+        assert_eq!(frame.pixel(63, 47), [0, 0, 0, 255]);
+        assert_eq!(frame.pixel(33, 23), [r, g, b, a]);
+        assert!(read_texel(&frame.device, &frame.queue, &frame.main_texture, 64, 0).is_err());
+    }
+
+    #[test]
+    fn flying_relative_to_view_follows_pitch() {
+        let pitched_up = Transform::rotation_xy(0.5);
+        let forward = [1.0, 0.0, 0.0, 0.0];
+
+        let [x, y, z, w] = fly_movement(forward, pitched_up, true).position();
+        assert!((x - 0.5f32.cos()).abs() <= 1e-5, "{x}");
+        assert!((y - 0.5f32.sin()).abs() <= 1e-5, "{y}");
+        assert!(z.abs() <= 1e-5 && w.abs() <= 1e-5);
+
+        // without the toggle looking up doesn't change where forward goes
+        let position = fly_movement(forward, pitched_up, false).position();
+        for (position, expected) in std::iter::zip(position, forward) {
+            assert!((position - expected).abs() <= 1e-5, "{position}");
+        }
+    }
+
+    #[test]
+    fn holding_a_turn_key_matches_the_same_scroll() {
+        let dt = 1.0 / 60.0;
+        let frames = 30;
+        let movement_state = MovementState {
+            turn_ana: 1.0,
+            ..MovementState::default()
+        };
+        // the default key speed turns as fast as scrolling is allowed to
+        let mut pending_scroll = MAX_SCROLL_PER_SECOND * dt * frames as f32;
+
+        let mut key_turn = Transform::IDENTITY;
+        let mut scroll_turn = Transform::IDENTITY;
+        for _ in 0..frames {
+            key_turn = key_turn * Transform::rotation_xw(movement_state.xw_turn(dt));
+            scroll_turn =
+                scroll_turn * Transform::rotation_xw(take_scroll(&mut pending_scroll, dt));
+        }
+        assert!(pending_scroll.abs() <= 1e-4);
+        assert!(
+            key_turn.represents_same_rotation(scroll_turn, 1e-4),
+            "{key_turn:?} != {scroll_turn:?}"
+        );
+
+        // holding the opposite key while scrolling cancels out
+        let movement_state = MovementState {
+            turn_kata: 1.0,
+            ..MovementState::default()
+        };
+        let mut pending_scroll = MAX_SCROLL_PER_SECOND * dt;
+        let turn = movement_state.xw_turn(dt) + take_scroll(&mut pending_scroll, dt);
+        assert!(turn.abs() <= 1e-6, "{turn}");
+    }
+
+    #[test]
+    fn solid_queries_find_the_containing_block() {
+        let mut chunk = empty_chunk();
+        chunk.data[Chunk::block_index([1, 2, 0, 3]).unwrap()] = solid_block(1.0);
+
+        assert!(chunk.is_solid([1.5, 2.5, 0.5, 3.5]));
+        assert!(chunk.is_solid([1.0, 2.0, 0.0, 3.0]));
+        assert!(chunk.is_solid([1.999, 2.999, 0.999, 3.999]));
+        // the far faces belong to the neighbouring blocks
+        assert!(!chunk.is_solid([2.0, 2.5, 0.5, 3.5]));
+        assert!(!chunk.is_solid([1.5, 3.0, 0.5, 3.5]));
+        assert!(!chunk.is_solid([0.5, 2.5, 0.5, 3.5]));
+
+        // outside the chunk there's nothing loaded, even where the indices would wrap around
+        assert!(!chunk.is_solid([1.5, 2.5, -0.5, 3.5]));
+        assert!(!chunk.is_solid([1.5 + CHUNK_SIZE as f32, 2.5, 0.5, 3.5]));
+        assert!(!chunk.is_solid([1.5, 2.5, 0.5, 3.5 + CHUNK_SIZE as f32]));
+        assert!(!chunk.is_solid([f32::NAN, 2.5, 0.5, 3.5]));
+    }
+
+    #[test]
+    fn frame_latency_flows_into_the_surface_configuration() {
+        let configuration = |max_frame_latency| {
+            surface_configuration(
+                [640, 480],
+                false,
+                wgpu::PresentMode::Immediate,
+                max_frame_latency,
+            )
+        };
+        assert_eq!(configuration(1).desired_maximum_frame_latency, 1);
+        assert_eq!(configuration(3).desired_maximum_frame_latency, 3);
+        // a latency of 0 isn't possible
+        assert_eq!(configuration(0).desired_maximum_frame_latency, 1);
+
+        let configuration = configuration(DEFAULT_MAX_FRAME_LATENCY);
+        assert_eq!(configuration.desired_maximum_frame_latency, 2);
+        assert_eq!(configuration.present_mode, wgpu::PresentMode::Immediate);
+        assert_eq!([configuration.width, configuration.height], [640, 480]);
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn world_border_tints_rays_that_cross_it() {
+        let size = [16, 16];
+        let center_pixel = |camera: Transform, chunk: &Chunk| {
+            let render_settings = RenderSettings {
+                world_border_radius: 5.0,
+                ..frame_render_settings(size)
+            };
+            render_headless(size, camera, chunk, &render_settings).pixel(8, 8)
+        };
+        let is_tinted = |[r, g, b, _]: [u8; 4]| r > 0 && g > r && b > g;
+
+        let empty = empty_chunk();
+        // from inside, every ray leaves through the border
+        let inside = center_pixel(Transform::translation([0.5, 0.0, 0.0, 0.0]), &empty);
+        assert!(is_tinted(inside), "{inside:?}");
+        // from outside, only rays heading towards the border cross it
+        let outside = Transform::translation([-10.0, 0.0, 0.0, 0.0]);
+        let towards = center_pixel(outside, &empty);
+        assert!(is_tinted(towards), "{towards:?}");
+        let away = center_pixel(
+            outside * Transform::rotation_xz(std::f32::consts::PI),
+            &empty,
+        );
+        assert_eq!(away, [0, 0, 0, 255]);
+
+        // a block in front of the border hides it
+        let mut chunk = empty_chunk();
+        chunk.data[Chunk::block_index([1, 1, 1, 1]).unwrap()] = solid_block(1.0);
+        let [_, _, b, _] = center_pixel(Transform::translation([-2.0, 1.5, 1.5, 1.5]), &chunk);
+        let [_, _, unbordered_b, _] = render_headless(
+            size,
+            Transform::translation([-2.0, 1.5, 1.5, 1.5]),
+            &chunk,
+            &frame_render_settings(size),
+        )
+        .pixel(8, 8);
+        assert_eq!(b, unbordered_b);
+    }
+
+    #[test]
+    fn movement_is_clamped_inside_the_world_border() {
+        assert_eq!(
+            clamp_to_world_border([1.0, 2.0, -2.0, 0.0], 5.0),
+            [1.0, 2.0, -2.0, 0.0]
+        );
+        let clamped = clamp_to_world_border([0.0, 6.0, 0.0, -8.0], 5.0);
+        assert_eq!(clamped, [0.0, 3.0, 0.0, -4.0]);
+        assert_eq!(clamp_to_world_border([3.0, 0.0, 0.0, 0.0], 0.0), [0.0; 4]);
+    }
+
+    #[test]
+    fn camera_shake_decays_to_nothing() {
+        let mut camera_shake = CameraShake {
+            intensity: 2.0,
+            duration: Duration::from_millis(500),
+            elapsed: Duration::ZERO,
+        };
+        assert_eq!(camera_shake.amplitude(), 2.0);
+        let mut previous = camera_shake.amplitude();
+        for _ in 0..50 {
+            camera_shake.elapsed += Duration::from_millis(10);
+            let amplitude = camera_shake.amplitude();
+            assert!(amplitude <= previous, "{amplitude} > {previous}");
+            previous = amplitude;
+        }
+        assert_eq!(camera_shake.amplitude(), 0.0);
+        camera_shake.elapsed += Duration::from_millis(10);
+        assert_eq!(camera_shake.amplitude(), 0.0);
+        assert_eq!(camera_shake_offset(0.0, 7), Transform::IDENTITY);
+    }
+
+    #[test]
+    fn zero_intensity_shake_has_no_offset() {
+        let camera_shake = CameraShake {
+            intensity: 0.0,
+            duration: Duration::from_secs(1),
+            elapsed: Duration::ZERO,
+        };
+        for frame in 0..100 {
+            assert_eq!(
+                camera_shake_offset(camera_shake.amplitude(), frame),
+                Transform::IDENTITY
+            );
+        }
+        // while a real shake does move the camera
+        assert_ne!(camera_shake_offset(1.0, 3), Transform::IDENTITY);
+    }
+
+    #[test]
+    fn orbit_demo_pose_depends_only_on_time() {
+        let settings = OrbitSettings {
+            center: [2.0, 2.0, 2.0, 2.0],
+            distance: 6.0,
+            speed: 0.7,
+        };
+        let whole = OrbitDemo {
+            settings,
+            time: Duration::from_millis(2500),
+        };
+        let mut stepped = OrbitDemo {
+            settings,
+            time: Duration::ZERO,
+        };
+        for _ in 0..250 {
+            stepped.time += Duration::from_millis(10);
+        }
+        assert_eq!(whole.transform(), stepped.transform());
+
+        let start = OrbitDemo {
+            settings,
+            time: Duration::ZERO,
+        };
+        assert!(start
+            .transform()
+            .represents_same_rotation(Transform::translation([-4.0, 2.0, 2.0, 2.0]), 1e-5));
+        assert_ne!(start.transform(), whole.transform());
+
+        let position = whole.transform().position();
+        let distance = std::iter::zip(position, settings.center)
+            .map(|(position, center)| (position - center) * (position - center))
+            .sum::<f32>()
+            .sqrt();
+        assert!((distance - settings.distance).abs() <= 1e-4, "{distance}");
+    }
+
+    #[test]
+    fn shader_encodes_srgb_only_for_linear_formats() {
+        assert!(shader_srgb_encode(wgpu::TextureFormat::Rgba8Unorm));
+        assert!(shader_srgb_encode(wgpu::TextureFormat::Bgra8Unorm));
+        assert!(!shader_srgb_encode(wgpu::TextureFormat::Rgba8UnormSrgb));
+        assert!(!shader_srgb_encode(wgpu::TextureFormat::Bgra8UnormSrgb));
+        // the format the game configures today
+        assert!(shader_srgb_encode(
+            surface_configuration(
+                [1, 1],
+                false,
+                wgpu::PresentMode::Fifo,
+                DEFAULT_MAX_FRAME_LATENCY
+            )
+            .format
+        ));
+    }
+
+    #[test]
+    fn loading_bar_fills_with_progress() {
+        assert_eq!(loading_bar_width(200, 0.0), 0);
+        assert_eq!(loading_bar_width(200, 0.5), 100);
+        assert_eq!(loading_bar_width(200, 1.0), 200);
+        assert_eq!(loading_bar_width(3, 0.5), 2);
+        // out of range progress stays inside the bar
+        assert_eq!(loading_bar_width(200, -1.0), 0);
+        assert_eq!(loading_bar_width(200, 2.0), 200);
+        assert_eq!(loading_bar_width(200, f32::NAN), 0);
+        assert_eq!(loading_bar_width(0, 0.5), 0);
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn compute_entry_points_are_validated() {
+        let (device, _queue) = test_device();
+        let module = pollster::block_on(create_compute_shader(&device, SHADER_SOURCE)).unwrap();
+        // borrow the bind group layouts the shader implies for `main`
+        let main_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: None,
+            layout: None,
+            module: &module,
+            entry_point: "main",
+        });
+        let bind_group_layouts = (0..4)
+            .map(|group| main_pipeline.get_bind_group_layout(group))
+            .collect::<Vec<_>>();
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &bind_group_layouts.iter().collect::<Vec<_>>(),
+            push_constant_ranges: &[],
+        });
+
+        pollster::block_on(create_compute_pipeline(&device, &layout, &module, "main")).unwrap();
+        let error = pollster::block_on(create_compute_pipeline(
+            &device,
+            &layout,
+            &module,
+            "does_not_exist",
+        ))
+        .unwrap_err()
+        .to_string();
+        assert!(
+            error.starts_with(
+                "Could not create the compute pipeline with entry point `does_not_exist`"
+            ),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn photo_mode_hides_overlays() {
+        let mut render_settings = RenderSettings {
+            render_mode: RenderMode::StepHeatmap as u32,
+            split_view: 1,
+            split_screen: 1,
+            view_plane_gizmo: 1,
+            wireframe: 1,
+            world_border_radius: 2.0,
+            selection_min: [1, 1, 1, 1].into(),
+            selection_max: [3, 3, 3, 3].into(),
+            ..frame_render_settings([64, 48])
+        };
+        render_settings.hide_overlays();
+        assert_eq!(render_settings.render_mode, RenderMode::Normal as u32);
+        assert_eq!(render_settings.split_view, 0);
+        assert_eq!(render_settings.split_screen, 0);
+        assert_eq!(render_settings.view_plane_gizmo, 0);
+        assert_eq!(render_settings.wireframe, 0);
+        assert_eq!(render_settings.world_border_radius, 0.0);
+        assert_eq!(
+            [render_settings.selection_min, render_settings.selection_max].map(<[i32; 4]>::from),
+            [[0; 4]; 2]
+        );
+        // the world itself still renders the same
+        assert_eq!(render_settings.exposure, 1.0);
+        assert_eq!(render_settings.viewport_width, 64);
+    }
+
+    #[test]
+    fn photo_mode_pauses_fixed_updates_until_it_ends() {
+        let mut photo_mode = None;
+        assert!(!PhotoMode::set(&mut photo_mode, true, true));
+        assert!(photo_mode.is_some());
+        // turning it on again keeps what to go back to
+        assert!(!PhotoMode::set(&mut photo_mode, true, false));
+        assert!(PhotoMode::set(&mut photo_mode, false, false));
+        assert!(photo_mode.is_none());
+
+        // fixed updates that were already paused stay paused
+        assert!(!PhotoMode::set(&mut photo_mode, true, false));
+        assert!(!PhotoMode::set(&mut photo_mode, false, false));
+        assert!(PhotoMode::set(&mut photo_mode, false, true));
+    }
+
+    #[test]
+    fn a_batch_of_edits_is_one_upload_and_one_undo() {
+        let mut chunk = empty_chunk();
+        chunk.data[Chunk::block_index([0, 0, 0, 1]).unwrap()] = solid_block(0.25);
+        let original = chunk.clone();
+        let mut undo_history = UndoHistory::default();
+
+        let positions = [[0, 0, 0, 1], [3, 1, 0, 2], [0, 0, 0, 1], [2, 2, 2, 0]];
+        let (result, dirty_blocks) = undo_history.edit_batch(&mut chunk, |editor| {
+            positions
+                .into_iter()
+                .enumerate()
+                .try_for_each(|(i, position)| {
+                    editor.set_block(position, solid_block(i as f32 / 4.0))
+                })
+        });
+        result.unwrap();
+
+        let indices = positions.map(|position| Chunk::block_index(position).unwrap());
+        let start = *indices.iter().min().unwrap();
+        let end = indices.iter().max().unwrap() + 1;
+        assert_eq!(dirty_blocks, Some(start..end));
+        assert_eq!(undo_history.entries.len(), 1);
+        // the block edited twice only remembers what it was before the batch
+        assert_eq!(undo_history.entries[0].len(), 3);
+
+        let undone = undo_history.undo(&mut chunk).unwrap();
+        assert!(chunk.diff(&original).is_empty());
+        assert!(undone.start >= start && undone.end <= end);
+        assert_eq!(undo_history.undo(&mut chunk), None);
+
+        // a batch that fails before editing anything leaves no entry behind
+        let (result, dirty_blocks) = undo_history.edit_batch(&mut chunk, |editor| {
+            editor.set_block([CHUNK_SIZE, 0, 0, 0], solid_block(1.0))
+        });
+        assert!(result.is_err());
+        assert_eq!(dirty_blocks, None);
+        assert!(undo_history.entries.is_empty());
+    }
+
+    #[test]
+    fn the_undo_history_drops_its_oldest_entries() {
+        let mut chunk = empty_chunk();
+        let mut undo_history = UndoHistory::default();
+        for i in 0..MAX_UNDO_ENTRIES + 2 {
+            let block = solid_block(i as f32 / MAX_UNDO_ENTRIES as f32);
+            let (result, _) =
+                undo_history.edit_batch(&mut chunk, |editor| editor.set_block([0, 0, 0, 0], block));
+            result.unwrap();
+        }
+        assert_eq!(undo_history.entries.len(), MAX_UNDO_ENTRIES);
+        while undo_history.undo(&mut chunk).is_some() {}
+        // the first two edits can no longer be undone
+        assert_eq!(chunk.data[0], solid_block(1.0 / MAX_UNDO_ENTRIES as f32));
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn depth_is_the_distance_to_the_hit() {
+        let mut chunk = empty_chunk();
+        chunk.data[Chunk::block_index([2, 1, 1, 1]).unwrap()] = solid_block(1.0);
+        // the block's near face is at x = 2, five units in front of the camera
+        let camera = Transform::translation([-3.0, 1.5, 1.5, 1.5]);
+        let size = [64, 48];
+        let frame = render_headless(size, camera, &chunk, &frame_render_settings(size));
+
+        let depth = frame.depth(32, 24);
+        assert!((depth - 5.0).abs() <= 0.01, "{depth}");
+        // rays that hit nothing have no depth
+        assert_eq!(frame.depth(0, 0), -1.0);
+    }
+
+    #[test]
+    fn applying_a_diff_reproduces_the_other_chunk() {
+        let chunk = empty_chunk();
+        assert!(chunk.diff(&chunk.clone()).is_empty());
+
+        let mut edited = chunk.clone();
+        let index = Chunk::block_index([3, 0, 2, 1]).unwrap();
+        edited.data[index] = solid_block(0.75);
+        let diff = chunk.diff(&edited);
+        assert_eq!(diff, [(index, solid_block(0.75))]);
+
+        let mut target = edited.clone();
+        target.data[0] = solid_block(0.25);
+        target.data[index] = Block {
+            exists: 0,
+            ..solid_block(0.0)
+        };
+        let mut applied = chunk.clone();
+        applied.apply_diff(&chunk.diff(&target)).unwrap();
+        assert!(applied.diff(&target).is_empty());
+
+        // a bad index leaves the chunk alone
+        let mut untouched = chunk.clone();
+        assert!(untouched
+            .apply_diff(&[(0, solid_block(1.0)), (chunk.data.len(), solid_block(1.0))])
+            .is_err());
+        assert!(untouched.diff(&chunk).is_empty());
+    }
+
+    #[test]
+    fn sky_gradient_is_written_where_the_shader_reads_it() {
+        let color = |r| Color { r, g: 0.5, b: 0.5 };
+        let bytes = encode_render_settings(&RenderSettings {
+            sky_zenith_color: color(0.25),
+            sky_horizon_color: color(0.5),
+            sky_nadir_color: color(0.75),
+            ..render_settings()
+        });
+        for (member, r) in [
+            ("sky_zenith_color", 0.25),
+            ("sky_horizon_color", 0.5),
+            ("sky_nadir_color", 0.75),
+        ] {
+            assert_eq!(
+                read_f32s(&bytes, shader_struct_offset("RenderSettings", member)),
+                [r, 0.5, 0.5],
+                "{member}"
+            );
+        }
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn a_sky_gradient_with_one_color_is_flat() {
+        let sky = |zenith, horizon, nadir| {
+            let size = [64, 48];
+            let frame = render_headless(
+                size,
+                Transform::translation([-2.0, 1.5, 1.5, 1.5]),
+                &empty_chunk(),
+                &RenderSettings {
+                    sky_zenith_color: zenith,
+                    sky_horizon_color: horizon,
+                    sky_nadir_color: nadir,
+                    ..frame_render_settings(size)
+                },
+            );
+            [(32, 0), (0, 24), (32, 24), (63, 24), (32, 47)].map(|(x, y)| frame.pixel(x, y))
+        };
+
+        let color = Color {
+            r: 0.5,
+            g: 0.25,
+            b: 0.125,
+        };
+        let flat = sky(color, color, color);
+        assert_ne!(flat[0], [0, 0, 0, 255]);
+        assert!(flat.iter().all(|&pixel| pixel == flat[0]), "{flat:?}");
+
+        // whereas a real gradient is brighter looking up than down
+        let white = Color {
+            r: 1.0,
+            g: 1.0,
+            b: 1.0,
+        };
+        let black = Color {
+            r: 0.0,
+            g: 0.0,
+            b: 0.0,
+        };
+        let [up, .., down] = sky(white, color, black);
+        assert!(
+            up[0] > flat[0][0] && down[0] < flat[0][0],
+            "{up:?} {down:?}"
+        );
+    }
+
+    #[test]
+    fn confining_to_loaded_space_clamps_to_its_boundary() {
+        let inside = [1.0, -2.0, 3.5, CHUNK_SIZE as f32 + 1.0];
+        assert_eq!(clamp_to_loaded_region(inside), inside);
+
+        let max = CHUNK_SIZE as f32 + LOADED_REGION_MARGIN;
+        assert_eq!(
+            clamp_to_loaded_region([100.0, 2.0, -100.0, max + 0.5]),
+            [max, 2.0, -LOADED_REGION_MARGIN, max]
+        );
+    }
+
+    #[test]
+    fn tonemapping_maps_the_white_point_to_one() {
+        for (white_point, contrast) in [(1.0, 1.0), (4.0, 1.0), (4.0, 2.0), (16.0, 0.5)] {
+            assert!((tonemap_curve(white_point, white_point, contrast) - 1.0).abs() <= 1e-5);
+            assert_eq!(tonemap_curve(0.0, white_point, contrast), 0.0);
+            assert!(tonemap_curve(white_point * 2.0, white_point, contrast) > 1.0);
+            let mut previous = 0.0;
+            for value in [0.1, 0.5, 1.0, 2.0] {
+                let mapped = tonemap_curve(value, white_point, contrast);
+                assert!(mapped > previous, "{value} {white_point} {contrast}");
+                previous = mapped;
+            }
+        }
+        // more contrast darkens what's below the white point
+        assert!(tonemap_curve(0.5, 4.0, 2.0) < tonemap_curve(0.5, 4.0, 1.0));
+        // without a white point colors pass through
+        assert_eq!(tonemap_curve(3.0, 0.0, 2.0), 3.0);
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn the_shader_tonemaps_along_the_same_curve() {
+        let size = [64, 48];
+        let sky = Color {
+            r: 0.5,
+            g: 1.0,
+            b: 2.0,
+        };
+        let frame = render_headless(
+            size,
+            Transform::IDENTITY,
+            &empty_chunk(),
+            &RenderSettings {
+                sky_zenith_color: sky,
+                sky_horizon_color: sky,
+                sky_nadir_color: sky,
+                tonemap_white_point: 2.0,
+                tonemap_contrast: 1.5,
+                ..frame_render_settings(size)
+            },
+        );
+        let pixel = frame.pixel(32, 24);
+        for (channel, value) in [sky.r, sky.g, sky.b].into_iter().enumerate() {
+            let expected = tonemap_curve(value, 2.0, 1.5) * 255.0;
+            assert!(
+                (pixel[channel] as f32 - expected).abs() <= 1.0,
+                "{pixel:?} {expected}"
+            );
+        }
+    }
+
+    #[test]
+    fn pacing_stats_of_known_frame_times() {
+        let close = |a: Duration, b: Duration| a.abs_diff(b) <= Duration::from_micros(1);
+
+        // two hitches in two hundred frames make up the slowest 1%
+        let frame_times = std::iter::repeat_n(Duration::from_millis(10), 198)
+            .chain([Duration::from_millis(50); 2]);
+        let stats = PacingStats::from_frame_times(frame_times);
+        assert!(
+            close(stats.mean, Duration::from_micros(10_400)),
+            "{stats:?}"
+        );
+        assert!(
+            close(
+                stats.standard_deviation,
+                Duration::from_secs_f64(15.84e-6f64.sqrt())
+            ),
+            "{stats:?}"
+        );
+        assert!(
+            close(stats.one_percent_low, Duration::from_millis(50)),
+            "{stats:?}"
+        );
+
+        let steady = PacingStats::from_frame_times([Duration::from_millis(16); 10]);
+        assert!(close(steady.mean, Duration::from_millis(16)));
+        assert!(close(steady.standard_deviation, Duration::ZERO));
+        assert!(close(steady.one_percent_low, Duration::from_millis(16)));
+
+        let empty = PacingStats::from_frame_times([]);
+        assert_eq!(empty.mean, Duration::ZERO);
+        assert_eq!(empty.one_percent_low, Duration::ZERO);
+    }
+
+    #[test]
+    fn repeated_damage_removes_a_block() {
+        let block = Block::new(Color {
+            r: 1.0,
+            g: 1.0,
+            b: 1.0,
+        });
+        assert_eq!((block.exists, block.health), (1, MAX_BLOCK_HEALTH));
+        assert_eq!(MAX_BLOCK_HEALTH, shader_u32_constant("MAX_BLOCK_HEALTH"));
+
+        let mut chunk = empty_chunk();
+        let position = [2, 0, 1, 3];
+        let index = Chunk::block_index(position).unwrap();
+        chunk.data[index] = block;
+        for health in (1..MAX_BLOCK_HEALTH).rev() {
+            assert!(chunk.damage_block(position, 1).unwrap());
+            assert_eq!(
+                (chunk.data[index].exists, chunk.data[index].health),
+                (1, health)
+            );
+        }
+        assert!(chunk.damage_block(position, 1).unwrap());
+        assert_eq!(chunk.data[index].exists, 0);
+        // there's nothing left to damage
+        assert!(!chunk.damage_block(position, 1).unwrap());
+
+        chunk.data[index] = block;
+        assert!(chunk.damage_block(position, u32::MAX).unwrap());
+        assert_eq!(chunk.data[index].exists, 0);
+        assert!(chunk.damage_block([0, 0, 0, CHUNK_SIZE], 1).is_err());
+    }
+
+    #[test]
+    fn cycling_render_modes_wraps_back_to_normal() {
+        let mut render_mode = RenderMode::Normal;
+        let mut visited = vec![];
+        for _ in 0..5 {
+            render_mode = render_mode.next();
+            visited.push(render_mode);
+        }
+        assert_eq!(
+            visited,
+            [
+                RenderMode::Normals,
+                RenderMode::StepHeatmap,
+                RenderMode::ChunkTint,
+                RenderMode::Depth,
+                RenderMode::Normal,
+            ]
+        );
+    }
+
+    #[test]
+    fn world_bounds_clamp_to_the_boundary() {
+        let bounds = WorldBounds {
+            min: [0.0, -1.0, 0.0, 2.0],
+            max: [4.0, 3.0, 4.0, 1.0],
+        };
+        assert_eq!(bounds.clamp([1.0, 2.0, 3.5, 2.0]), [1.0, 2.0, 3.5, 2.0]);
+        assert_eq!(bounds.clamp([9.0, -5.0, 4.5, 0.0]), [4.0, -1.0, 4.0, 2.0]);
+        // an inverted axis doesn't panic
+        assert_eq!(bounds.clamp([0.0, 0.0, 0.0, 7.0])[3], 2.0);
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn blits_the_frame_into_a_caller_view() {
+        let mut chunk = empty_chunk();
+        chunk.data[Chunk::block_index([1, 1, 1, 1]).unwrap()] = solid_block(1.0);
+        let size = [64, 48];
+        let frame = render_headless(
+            size,
+            Transform::translation([-2.0, 1.5, 1.5, 1.5]),
+            &chunk,
+            &frame_render_settings(size),
+        );
+        let target = frame.device.create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size: frame.main_texture.size(),
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: frame.main_texture.format(),
+            // no copy destination, so only a render pass can fill it
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+
+        let mut blit = Blit::new(&frame.device, frame.main_texture.format());
+        let mut encoder = frame.device.create_command_encoder(&Default::default());
+        blit.encode(
+            &frame.device,
+            &mut encoder,
+            &frame.main_texture,
+            &target.create_view(&Default::default()),
+        );
+        frame.queue.submit([encoder.finish()]);
+        for (x, y) in [(32, 24), (0, 0), (63, 47)] {
+            assert_eq!(
+                read_texel(&frame.device, &frame.queue, &target, x, y).unwrap(),
+                frame.pixel(x, y)
+            );
+        }
+        assert_ne!(frame.pixel(32, 24), frame.pixel(0, 0));
+    }
+
+    #[test]
+    fn only_frames_over_budget_warn() {
+        let budget = Duration::from_millis(16);
+        let mut frame_budget = FrameBudget::new(budget);
+        for _ in 0..100 {
+            assert_eq!(frame_budget.record(budget), None);
+        }
+        assert_eq!(frame_budget.record(Duration::from_millis(40)), Some(1));
+
+        // overruns right after a warning are counted towards the next one
+        assert_eq!(frame_budget.record(Duration::from_millis(40)), None);
+        assert_eq!(frame_budget.record(Duration::from_millis(40)), None);
+        assert_eq!(frame_budget.record(FRAME_BUDGET_WARNING_INTERVAL), Some(3));
+        assert_eq!(frame_budget.record(FRAME_BUDGET_WARNING_INTERVAL / 2), None);
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn resizing_rebuilds_the_main_texture_bind_group() {
+        let (device, _queue) = test_device();
+        let (layout, luminance) = frame_target_resources(&device);
+        let mut pool = VecDeque::new();
+        // swaps the current targets for ones of `size` the way `Game::resize_frame_targets` does
+        let mut resize = |current: &mut FrameTargets, size| {
+            let frame_targets = take_pooled_frame_targets(&mut pool, size).unwrap_or_else(|| {
+                FrameTargets::new(
+                    &device,
+                    &layout,
+                    wgpu::TextureFormat::Rgba8Unorm,
+                    &luminance,
+                    size,
+                )
+            });
+            pool_frame_targets(&mut pool, std::mem::replace(current, frame_targets));
+        };
+
+        let mut current = FrameTargets::new(
+            &device,
+            &layout,
+            wgpu::TextureFormat::Rgba8Unorm,
+            &luminance,
+            [64, 48],
+        );
+        let original = current.bind_group.global_id();
+        resize(&mut current, [32, 32]);
+        assert_eq!(current.size(), [32, 32]);
+        assert_eq!(current.accumulation_storage_buffer.size(), 32 * 32 * 16);
+        assert_ne!(current.bind_group.global_id(), original);
+
+        // going back to a pooled size reuses its bind group instead of building another
+        let resized = current.bind_group.global_id();
+        resize(&mut current, [64, 48]);
+        assert_eq!(current.size(), [64, 48]);
+        assert_eq!(current.bind_group.global_id(), original);
+        resize(&mut current, [32, 32]);
+        assert_eq!(current.bind_group.global_id(), resized);
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn split_screen_renders_each_half_from_its_own_camera() {
+        let mut chunk = empty_chunk();
+        chunk.data[Chunk::block_index([1, 1, 1, 1]).unwrap()] = solid_block(1.0);
+        let facing_block = Transform::translation([-2.0, 1.5, 1.5, 1.5]);
+        let facing_away = facing_block * Transform::rotation_xy(std::f32::consts::PI);
+        let size = [64, 48];
+        let render = |cameras, split_screen| {
+            let frame = render_headless_split(
+                size,
+                cameras,
+                &chunk,
+                &RenderSettings {
+                    split_screen,
+                    ..frame_render_settings(size)
+                },
+            );
+            [
+                frame.pixel(16, 24),
+                frame.pixel(48, 24),
+                frame.pixel(32, 24),
+            ]
+        };
+        let black = [0, 0, 0, 255];
+
+        let [left, right, _] = render([facing_block, facing_away], 1);
+        assert_ne!(left, black);
+        assert_eq!(right, black);
+        let [left, right, _] = render([facing_away, facing_block], 1);
+        assert_eq!(left, black);
+        assert_ne!(right, black);
+
+        // without split screen the second camera isn't used at all
+        let [.., centre] = render([facing_block, facing_away], 0);
+        assert_ne!(centre, black);
+        let [.., centre] = render([facing_away, facing_block], 0);
+        assert_eq!(centre, black);
+    }
+
+    #[test]
+    fn auto_exposure_adapts_towards_the_target() {
+        let settings = AutoExposureSettings {
+            target_luminance: 0.5,
+            adaptation_speed: 2.0,
+            min_exposure: 0.25,
+            max_exposure: 4.0,
+        };
+
+        // a scene twice as bright as the target wants half the exposure, the luminance is measured
+        // before exposure so it doesn't change as the exposure does
+        let mut exposure = 1.0;
+        let mut previous = exposure;
+        for _ in 0..100 {
+            exposure = settings.adapt(exposure, 1.0, 1.0 / 60.0);
+            assert!(exposure < previous && exposure > 0.5, "{exposure}");
+            previous = exposure;
+        }
+        assert!((settings.adapt(exposure, 1.0, 10.0) - 0.5).abs() <= 1e-3);
+
+        // one long frame adapts as much as many short ones
+        let one_step = settings.adapt(1.0, 1.0, 0.5);
+        let mut many_steps = 1.0;
+        for _ in 0..30 {
+            many_steps = settings.adapt(many_steps, 1.0, 0.5 / 30.0);
+        }
+        assert!(
+            (one_step - many_steps).abs() <= 1e-4,
+            "{one_step} {many_steps}"
+        );
+
+        assert_eq!(settings.adapt(1.0, 1.0, 0.0), 1.0);
+        assert!((settings.adapt(1.0, 0.0, 100.0) - 4.0).abs() <= 1e-4);
+        assert!((settings.adapt(1.0, 1000.0, 100.0) - 0.25).abs() <= 1e-4);
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn staged_chunk_uploads_reach_the_gpu() {
+        let (device, queue) = test_device();
+        let chunk_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: Chunk::SHADER_SIZE.get(),
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::COPY_DST
+                | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let mut staging_belt = wgpu::util::StagingBelt::new(Chunk::SHADER_SIZE.get());
+        let mut upload = |chunk: &Chunk, dirty_blocks| {
+            let mut encoder = device.create_command_encoder(&Default::default());
+            stage_chunk_upload(
+                &mut staging_belt,
+                &mut encoder,
+                &device,
+                &chunk_buffer,
+                chunk,
+                dirty_blocks,
+            )
+            .unwrap();
+            staging_belt.finish();
+            queue.submit([encoder.finish()]);
+            staging_belt.recall();
+            read_chunk_buffer(&device, &queue, &chunk_buffer).unwrap()
+        };
+
+        let mut chunk = empty_chunk();
+        chunk.data[3] = solid_block(0.25);
+        chunk.data[100] = solid_block(0.75);
+        assert!(upload(&chunk, 0..chunk.data.len()).diff(&chunk).is_empty());
+
+        // only the dirty blocks are sent, and the belt's recycled chunks are reused cleanly
+        let mut edited = chunk.clone();
+        edited.data[3].exists = 0;
+        edited.data[200] = solid_block(1.0);
+        assert_eq!(chunk.diff(&upload(&edited, 3..4)), [(3, edited.data[3])]);
+        assert!(upload(&edited, 200..201).diff(&edited).is_empty());
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn post_passes_run_on_the_rendered_frame() {
+        struct InvertPass {
+            scratch: wgpu::Texture,
+            pipeline: wgpu::ComputePipeline,
+            bind_group: wgpu::BindGroup,
+        }
+
+        impl PostPass for InvertPass {
+            fn encode(
+                &self,
+                encoder: &mut wgpu::CommandEncoder,
+                texture: &wgpu::Texture,
+                size: wgpu::Extent3d,
+            ) {
+                encoder.copy_texture_to_texture(
+                    texture.as_image_copy(),
+                    self.scratch.as_image_copy(),
+                    size,
+                );
+                let mut compute_pass = encoder.begin_compute_pass(&Default::default());
+                compute_pass.set_pipeline(&self.pipeline);
+                compute_pass.set_bind_group(0, &self.bind_group, &[]);
+                compute_pass.dispatch_workgroups(size.width, size.height, 1);
+            }
+        }
+
+        let mut chunk = empty_chunk();
+        chunk.data[Chunk::block_index([1, 1, 1, 1]).unwrap()] = solid_block(1.0);
+        let size = [64, 48];
+        let frame = render_headless(
+            size,
+            Transform::translation([-2.0, 1.5, 1.5, 1.5]),
+            &chunk,
+            &frame_render_settings(size),
+        );
+        let pixels = [(32, 24), (0, 0)].map(|(x, y)| frame.pixel(x, y));
+
+        let device = &frame.device;
+        let scratch = device.create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size: frame.main_texture.size(),
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: frame.main_texture.format(),
+            usage: wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(
+                "@group(0) @binding(0)
+                var input: texture_2d<f32>;
+                @group(0) @binding(1)
+                var output: texture_storage_2d<rgba8unorm, write>;
+
+                @compute @workgroup_size(1)
+                fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+                    let color = textureLoad(input, id.xy, 0);
+                    textureStore(output, id.xy, vec4<f32>(1.0 - color.rgb, color.a));
+                }"
+                .into(),
+            ),
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: None,
+            layout: None,
+            module: &module,
+            entry_point: "main",
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(
+                        &scratch.create_view(&Default::default()),
+                    ),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(
+                        &frame.main_texture.create_view(&Default::default()),
+                    ),
+                },
+            ],
+        });
+        let post_passes: Vec<Box<dyn PostPass>> = vec![Box::new(InvertPass {
+            scratch,
+            pipeline,
+            bind_group,
+        })];
+
+        let mut encoder = device.create_command_encoder(&Default::default());
+        for post_pass in &post_passes {
+            post_pass.encode(&mut encoder, &frame.main_texture, frame.main_texture.size());
+        }
+        frame.queue.submit([encoder.finish()]);
+
+        for ((x, y), [r, g, b, a]) in std::iter::zip([(32, 24), (0, 0)], pixels) {
+            assert_eq!(frame.pixel(x, y), [255 - r, 255 - g, 255 - b, a]);
+        }
+    }
+
+    #[test]
+    fn movement_dt_is_clamped_and_zero_moves_nothing() {
+        assert_eq!(movement_dt(Duration::from_secs(10)), Some(MAX_UPDATE_DT));
+        assert_eq!(
+            movement_dt(Duration::from_millis(16)),
+            Some(Duration::from_millis(16))
+        );
+        assert_eq!(movement_dt(Duration::ZERO), None);
+
+        let movement_state = MovementState {
+            forward: 1.0,
+            ana: 1.0,
+            ..MovementState::default()
+        };
+        let clamped = movement_dt(Duration::from_secs(10)).unwrap().as_secs_f32();
+        assert_ne!(movement_state.displacement(clamped), [0.0; 4]);
+        assert_eq!(
+            movement_state.displacement(clamped),
+            movement_state.displacement(MAX_UPDATE_DT.as_secs_f32())
+        );
+        assert_eq!(movement_state.displacement(0.0), [0.0; 4]);
+    }
+
+    #[test]
+    fn compass_angles_for_known_orientations() {
+        use std::f32::consts::{FRAC_PI_2, FRAC_PI_4, PI};
+        let assert_angles = |forward, expected: [f32; 4]| {
+            let angles = compass_angles(forward);
+            for (angle, expected) in std::iter::zip(angles, expected) {
+                assert!(
+                    (angle - expected).abs() <= 1e-4,
+                    "{angles:?} != {expected:?}"
+                );
+            }
+        };
+
+        assert_angles([1.0, 0.0, 0.0, 0.0], [0.0, FRAC_PI_2, FRAC_PI_2, FRAC_PI_2]);
+        // turning the camera in the xw plane swings its forward direction towards w
+        let forward = Transform::rotation_xw(FRAC_PI_4).transform_direction([1.0, 0.0, 0.0, 0.0]);
+        assert_angles(forward, [FRAC_PI_4, FRAC_PI_2, FRAC_PI_2, FRAC_PI_4]);
+        // the length of forward doesn't matter
+        assert_angles([0.0, 0.0, -3.0, 0.0], [FRAC_PI_2, FRAC_PI_2, PI, FRAC_PI_2]);
+        assert_angles([0.0; 4], [FRAC_PI_2; 4]);
+    }
+
+    #[test]
+    fn frames_in_flight_gate_submission() {
+        let mut frames_in_flight = FramesInFlight::default();
+        let mut waited = vec![];
+        // without a limit nothing is tracked or waited on
+        for frame in 0..5 {
+            frames_in_flight.wait_for_room(|frame| waited.push(frame));
+            frames_in_flight.submitted(frame);
+        }
+        assert!(waited.is_empty());
+
+        frames_in_flight.set_max(Some(2));
+        for frame in 5..10 {
+            frames_in_flight.wait_for_room(|frame| waited.push(frame));
+            frames_in_flight.submitted(frame);
+        }
+        // the third frame onwards waits for the one two frames before it
+        assert_eq!(waited, [5, 6, 7]);
+
+        // a lower limit waits for every frame over it at once
+        frames_in_flight.set_max(Some(0));
+        waited.clear();
+        frames_in_flight.wait_for_room(|frame| waited.push(frame));
+        assert_eq!(waited, [8, 9]);
+
+        frames_in_flight.submitted(10);
+        frames_in_flight.set_max(None);
+        frames_in_flight.set_max(Some(1));
+        waited.clear();
+        frames_in_flight.wait_for_room(|frame| waited.push(frame));
+        assert!(waited.is_empty());
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn chunk_data_round_trips_through_the_gpu() {
+        let mut chunk = empty_chunk();
+        chunk.data[0] = solid_block(0.25);
+        chunk.data[Chunk::block_index([1, 1, 1, 1]).unwrap()] = solid_block(1.0);
+        chunk.data[chunk.data.len() - 1] = Block {
+            health: 1,
+            ..solid_block(0.5)
+        };
+        assert!(Chunk::from_bytes(&chunk.to_bytes().unwrap())
+            .unwrap()
+            .diff(&chunk)
+            .is_empty());
+
+        let size = [64, 48];
+        let frame = render_headless(
+            size,
+            Transform::translation([-2.0, 1.5, 1.5, 1.5]),
+            &chunk,
+            &frame_render_settings(size),
+        );
+        // the frame really was drawn from this buffer
+        assert_ne!(frame.pixel(32, 24), [0, 0, 0, 255]);
+        let read_back = read_chunk_buffer(&frame.device, &frame.queue, &frame.chunk_buffer);
+        assert!(read_back.unwrap().diff(&chunk).is_empty());
+    }
+
+    #[test]
+    fn direct_present_needs_storage_binding_and_is_opt_in() {
+        assert!(supports_direct_present(
+            wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::STORAGE_BINDING
+        ));
+        assert!(!supports_direct_present(
+            wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_DST
+        ));
+
+        let copy = surface_configuration([64, 48], false, wgpu::PresentMode::Fifo, 2);
+        assert_eq!(
+            copy.usage,
+            wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::RENDER_ATTACHMENT
+        );
+        let direct = surface_configuration([64, 48], true, wgpu::PresentMode::Fifo, 2);
+        assert!(direct.usage.contains(wgpu::TextureUsages::STORAGE_BINDING));
+        assert!(direct.usage.contains(wgpu::TextureUsages::COPY_DST));
+    }
+
+    #[test]
+    fn approaching_a_block_edge_stops_a_radius_away() {
+        let mut chunk = empty_chunk();
+        chunk.data[Chunk::block_index([2, 1, 1, 1]).unwrap()] = solid_block(1.0);
+        let radius = 0.25;
+
+        // straight at the x = 2 face
+        let stopped = chunk.resolve_collision([0.5, 1.5, 1.5, 1.5], [2.5, 1.5, 1.5, 1.5], radius);
+        assert!(!chunk.sphere_overlaps_solid(stopped, radius));
+        assert!((2.0 - stopped[0] - radius).abs() < 1e-3);
+        assert_eq!(stopped[1..], [1.5, 1.5, 1.5]);
+
+        // diagonally at the edge shared by the x = 2 and y = 1 faces
+        let stopped = chunk.resolve_collision([1.0, 0.0, 1.5, 1.5], [2.5, 1.5, 1.5, 1.5], radius);
+        assert!(!chunk.sphere_overlaps_solid(stopped, radius));
+        let distance_to_edge =
+            ((2.0 - stopped[0]).max(0.0).powi(2) + (1.0 - stopped[1]).max(0.0).powi(2)).sqrt();
+        assert!((distance_to_edge - radius).abs() < 1e-3);
+    }
+
+    #[test]
+    fn grid_movement_snaps_to_the_nearest_world_axis() {
+        // turned a little towards z and pitched a little up
+        let view = Transform::rotation_xz(0.3) * Transform::rotation_xy(0.2);
+
+        let forward = world_displacement([2.0, 0.0, 0.0, 0.0], view, false, true);
+        assert_eq!(forward, [2.0, 0.0, 0.0, 0.0]);
+        let tilted = world_displacement([2.0, 0.0, 0.0, 0.0], view, false, false);
+        assert!(tilted[1] != 0.0 && tilted[2] != 0.0);
+
+        let sideways = world_displacement([0.0, 0.0, 1.0, 0.0], view, false, true);
+        assert_eq!(sideways.map(f32::abs), [0.0, 0.0, 1.0, 0.0]);
+        let ana = world_displacement([0.0, 0.0, 0.0, 1.0], view, false, true);
+        assert_eq!(ana, [0.0, 0.0, 0.0, 1.0]);
+
+        assert_eq!(snap_to_axis([0.0; 4]), [0.0; 4]);
+        assert_eq!(snap_to_axis([0.1, -0.7, 0.5, 0.2]), [0.0, -1.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn tour_dwells_then_moves_on_and_loops() {
+        let waypoints = vec![
+            Transform::translation([1.0, 0.0, 0.0, 0.0]),
+            Transform::translation([0.0, 2.0, 0.0, 0.0]) * Transform::rotation_xz(1.0),
+            Transform::translation([0.0, 0.0, 0.0, 3.0]) * Transform::rotation_zw(-0.5),
+        ];
+        let tour = Tour {
+            waypoints: waypoints.clone(),
+            dwell_time: Duration::from_secs(2),
+            transition_time: Duration::from_secs(1),
+        };
+        let at = |seconds: f64| tour.transform(Duration::from_secs_f64(seconds)).unwrap();
+
+        assert_eq!(at(0.0), waypoints[0]);
+        assert_eq!(at(1.9), waypoints[0]);
+        let halfway = at(2.5);
+        assert_ne!(halfway, waypoints[0]);
+        assert_ne!(halfway, waypoints[1]);
+        assert_eq!(at(3.0), waypoints[1]);
+        assert_eq!(at(4.9), waypoints[1]);
+        assert_eq!(at(6.0), waypoints[2]);
+        // after the last waypoint it heads back to the first
+        assert_eq!(at(9.0), waypoints[0]);
+        assert_eq!(at(10.0), waypoints[0]);
+
+        assert_eq!(
+            Tour {
+                waypoints: vec![],
+                ..tour.clone()
+            }
+            .transform(Duration::ZERO),
+            None
+        );
+    }
+
+    #[test]
+    fn tour_segments_end_exactly_on_their_keyframe() {
+        let waypoints = vec![
+            Transform::rotation_xy(0.3),
+            Transform::translation([0.0, 1.0, 2.0, 3.0]) * Transform::rotation_xw(2.0),
+        ];
+        let tour = Tour {
+            waypoints: waypoints.clone(),
+            dwell_time: Duration::from_millis(500),
+            transition_time: Duration::from_millis(750),
+        };
+        let segment = tour.dwell_time + tour.transition_time;
+
+        for (index, &keyframe) in waypoints.iter().enumerate() {
+            let end = segment * (index as u32 + 1);
+            let next = waypoints[(index + 1) % waypoints.len()];
+            assert_eq!(tour.transform(end).unwrap(), next);
+            let just_before = tour.transform(end - Duration::from_nanos(1)).unwrap();
+            assert!(just_before.represents_same_rotation(next, 1e-4));
+            assert_eq!(
+                tour.transform(end - tour.transition_time).unwrap(),
+                keyframe
+            );
+        }
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn swapped_shader_groups_fail_against_the_pipeline_layout() {
+        let (device, _queue) = test_device();
+        let layout = ComputeBindGroupLayouts::new(
+            &device,
+            wgpu::TextureFormat::Rgba8Unorm,
+            wgpu::TextureFormat::R32Float,
+        )
+        .pipeline_layout(&device);
+        let build = |source: &str| {
+            pollster::block_on(async {
+                let module = create_compute_shader(&device, source).await?;
+                create_compute_pipeline(&device, &layout, &module, "main").await
+            })
+        };
+        build(SHADER_SOURCE).unwrap();
+
+        // the swapped shader is still valid on its own, only the layout disagrees with it
+        let swapped = SHADER_SOURCE
+            .replace("@group(1)", "@group(swap)")
+            .replace("@group(2)", "@group(1)")
+            .replace("@group(swap)", "@group(2)");
+        pollster::block_on(create_compute_shader(&device, &swapped)).unwrap();
+        let error = build(&swapped).unwrap_err().to_string();
+        assert!(
+            error.starts_with("Could not create the compute pipeline with entry point `main`"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn memory_report_sums_its_allocations() {
+        let size = wgpu::Extent3d {
+            width: 64,
+            height: 48,
+            depth_or_array_layers: 1,
+        };
+        let report = MemoryReport {
+            allocations: vec![
+                (
+                    "Main Texture",
+                    texture_byte_size(size, wgpu::TextureFormat::Rgba8Unorm),
+                ),
+                (
+                    "Depth Texture",
+                    texture_byte_size(size, wgpu::TextureFormat::R32Float),
+                ),
+                ("Chunk Storage Buffer", 1000),
+                ("Empty", 0),
+            ],
+        };
+        assert_eq!(report.allocations[0].1, 64 * 48 * 4);
+        assert_eq!(report.total(), 2 * 64 * 48 * 4 + 1000);
+        assert_eq!(MemoryReport::default().total(), 0);
+
+        assert!(!report.is_near_budget(report.total() * 2));
+        assert!(report.is_near_budget(report.total()));
+        assert!(report.is_near_budget(report.total() / 2));
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn view_plane_gizmo_draws_the_projected_grid_lines() {
+        let mut chunk = empty_chunk();
+        chunk.data[Chunk::block_index([1, 1, 1, 1]).unwrap()] = Block::new(Color {
+            r: 1.0,
+            g: 0.0,
+            b: 0.0,
+        });
+        // turned out of the world axes, so the grid doesn't line up with the block
+        let camera = Transform::translation([-0.3, 1.5, 1.5, 1.5]) * Transform::rotation_xy(0.2);
+        let size = [64, 48];
+        // one headless device at a time, so read each frame out before rendering the next
+        let pixels = |view_plane_gizmo| {
+            let settings = RenderSettings {
+                view_plane_gizmo,
+                ..frame_render_settings(size)
+            };
+            let frame = render_headless(size, camera, &chunk, &settings);
+            (0..size[1])
+                .flat_map(|y| (0..size[0]).map(move |x| (x, y)))
+                .map(|(x, y)| frame.pixel(x, y))
+                .collect::<Vec<_>>()
+        };
+        let plain = pixels(0);
+        let gizmo = pixels(1);
+
+        let lines = view_plane_grid_lines(camera)
+            .into_iter()
+            .filter_map(|line| project_line(camera, 90.0f32.to_radians(), size, line))
+            .collect::<Vec<_>>();
+        let line_distance = |x: u32, y: u32| {
+            let position = [x as f32 + 0.5, y as f32 + 0.5];
+            lines
+                .iter()
+                .map(|&[start, end]| {
+                    let along = [end[0] - start[0], end[1] - start[1]];
+                    let offset = [position[0] - start[0], position[1] - start[1]];
+                    let length_squared = (along[0] * along[0] + along[1] * along[1]).max(1e-6);
+                    let t = ((offset[0] * along[0] + offset[1] * along[1]) / length_squared)
+                        .clamp(0.0, 1.0);
+                    let [dx, dy] = [offset[0] - along[0] * t, offset[1] - along[1] * t];
+                    (dx * dx + dy * dy).sqrt()
+                })
+                .fold(f32::INFINITY, f32::min)
+        };
+
+        let mut grid_pixels = 0;
+        for (index, (&before, &after)) in plain.iter().zip(&gizmo).enumerate() {
+            let (x, y) = (index as u32 % size[0], index as u32 / size[0]);
+            let distance = line_distance(x, y);
+            if distance <= 0.5 {
+                grid_pixels += 1;
+                assert!(after[2] > before[2], "({x}, {y}): {before:?} -> {after:?}");
+            } else if distance > 1.0 {
+                assert_eq!(after, before, "({x}, {y})");
+            }
+        }
+        assert!(grid_pixels > 0);
+    }
+
+    #[test]
+    fn view_plane_grid_lies_on_the_transformed_hyperplane() {
+        let dot = |a: [f32; 4], b: [f32; 4]| a.iter().zip(b).map(|(a, b)| a * b).sum::<f32>();
+        let camera = Transform::translation([3.0, 1.0, -2.0, 0.5])
+            * Transform::rotation_xw(0.6)
+            * Transform::rotation_zw(-0.4);
+        let (normal, distance) = camera.transform_plane([0.0, 0.0, 0.0, 1.0], 0.0);
+
+        let lines = view_plane_grid_lines(camera);
+        assert_eq!(lines.len(), MAX_VIEW_PLANE_LINES);
+        let axes = [
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+        ]
+        .map(|axis| camera.transform_direction(axis));
+        for [start, end] in lines {
+            for point in [start, end] {
+                assert!((dot(normal, point) - distance).abs() <= 1e-4, "{point:?}");
+            }
+            // each line runs the full grid along one of the camera's axes
+            let along: [f32; 4] = std::array::from_fn(|axis| end[axis] - start[axis]);
+            let length = 2.0 * VIEW_PLANE_GRID_EXTENT as f32;
+            assert!(
+                axes.iter()
+                    .any(|&axis| (dot(along, axis) - length).abs() <= 1e-4),
+                "{along:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn view_plane_lines_are_clipped_to_the_near_plane() {
+        let camera = Transform::translation([1.0, 2.0, 3.0, 4.0]);
+        let v_fov = 90.0f32.to_radians();
+        let size = [64, 48];
+        let point = |forward: f32, up: f32, right: f32| camera.transform([forward, up, right, 0.0]);
+
+        // a line straight up in front of the camera projects to a vertical line through the center
+        let [start, end] = project_line(
+            camera,
+            v_fov,
+            size,
+            [point(2.0, -1.0, 0.0), point(2.0, 1.0, 0.0)],
+        )
+        .unwrap();
+        assert_eq!(start[0], 32.0);
+        assert_eq!(end[0], 32.0);
+        assert!(start[1] > 24.0 && end[1] < 24.0);
+
+        // a line reaching behind the camera is cut off at the near plane
+        let line = [point(-2.0, 1.0, 0.0), point(2.0, 1.0, 0.0)];
+        let [near, far] = project_line(camera, v_fov, size, line).unwrap();
+        let (projected_near, _) =
+            project_point(camera, v_fov, size, point(VIEW_PLANE_GRID_NEAR, 1.0, 0.0)).unwrap();
+        assert!((near[1] - projected_near[1]).abs() <= 1e-2, "{near:?}");
+        assert_eq!(
+            Some(far),
+            project_point(camera, v_fov, size, line[1]).map(|(far, _)| far)
+        );
+
+        assert_eq!(
+            project_line(
+                camera,
+                v_fov,
+                size,
+                [point(-2.0, 1.0, 0.0), point(-1.0, 1.0, 0.0)]
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn empty_selections_have_no_shader_bounds() {
+        let selection = Selection {
+            min: [1, 0, 1, 1],
+            max: [2, 4, 2, 3],
+        };
+        assert_eq!(
+            Selection::shader_bounds(Some(selection)),
+            ([1, 0, 1, 1].into(), [2, 4, 2, 3].into())
+        );
+        let empty = Selection {
+            min: [1, 1, 1, 1],
+            max: [2, 1, 2, 2],
+        };
+        assert!(empty.is_empty());
+        let nothing = ([0; 4].into(), [0; 4].into());
+        assert_eq!(Selection::shader_bounds(Some(empty)), nothing);
+        assert_eq!(Selection::shader_bounds(None), nothing);
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn selections_reach_the_shader_and_empty_ones_tint_nothing() {
+        let selection = Selection {
+            min: [1, 0, 1, 1],
+            max: [2, 4, 2, 3],
+        };
+        let empty = Selection {
+            min: [1, 1, 1, 1],
+            max: [2, 1, 2, 2],
+        };
+        let mut chunk = empty_chunk();
+        chunk.data[Chunk::block_index([1, 1, 1, 1]).unwrap()] = Block::new(Color {
+            r: 1.0,
+            g: 0.0,
+            b: 0.0,
+        });
+        let camera = Transform::translation([-2.0, 1.5, 1.5, 1.5]);
+        let size = [64, 48];
+        let center = |selection| {
+            let (selection_min, selection_max) = Selection::shader_bounds(selection);
+            let settings = RenderSettings {
+                selection_min,
+                selection_max,
+                ..frame_render_settings(size)
+            };
+            render_headless(size, camera, &chunk, &settings).pixel(32, 24)
+        };
+        let unselected = center(None);
+        assert_eq!(center(Some(empty)), unselected);
+        assert_ne!(center(Some(selection)), unselected);
+    }
+
+    #[test]
+    fn fifo_is_chosen_over_no_vsync_without_mailbox() {
+        use wgpu::PresentMode::*;
+
+        let without_mailbox = [AutoNoVsync, Immediate, Fifo];
+        assert_eq!(choose_present_mode(&without_mailbox, false), Fifo);
+        assert_eq!(choose_present_mode(&[Fifo, Mailbox], false), Mailbox);
+        assert_eq!(choose_present_mode(&[], false), Fifo);
+
+        // opting into low latency still prefers no vsync
+        assert_eq!(choose_present_mode(&without_mailbox, true), Immediate);
+        assert_eq!(choose_present_mode(&[Fifo], true), AutoNoVsync);
+    }
+
+    #[test]
+    fn copy_then_paste_reproduces_the_region_at_an_offset() {
+        let mut chunk = empty_chunk();
+        for (position, r) in [
+            ([0, 0, 0, 0], 0.1),
+            ([1, 0, 1, 0], 0.2),
+            ([0, 1, 1, 1], 0.3),
+        ] {
+            chunk.data[Chunk::block_index(position).unwrap()] = solid_block(r);
+        }
+        let clip = chunk.copy_region([0, 0, 0, 0], [2, 2, 2, 2]);
+        assert_eq!(clip.size(), [2; 4]);
+
+        let original = chunk.clone();
+        let at = [2, 1, 2, 0];
+        let previous = chunk.paste_region(&clip, at);
+        assert_eq!(previous.len(), 16);
+        for offset in region_offsets([2; 4]) {
+            let source = Chunk::block_index(offset).unwrap();
+            let target = Chunk::block_index(std::array::from_fn(|axis| at[axis] + offset[axis]));
+            assert_eq!(chunk.data[target.unwrap()], original.data[source]);
+        }
+
+        chunk.apply_diff(&previous).unwrap();
+        assert!(chunk.diff(&original).is_empty());
+
+        // pasting over the far edge keeps only the part that still fits
+        let edge = [CHUNK_SIZE - 1; 4];
+        let previous = chunk.paste_region(&clip, edge);
+        assert_eq!(previous.len(), 1);
+        assert_eq!(
+            chunk.data[Chunk::block_index(edge).unwrap()],
+            original.data[0]
+        );
+        assert!(chunk.paste_region(&clip, [usize::MAX; 4]).is_empty());
+
+        // copying past the edge clips the region
+        assert_eq!(
+            chunk.copy_region([3, 0, 0, 0], [6, 1, 1, 1]).size(),
+            [1, 1, 1, 1]
+        );
+    }
+
+    #[test]
+    fn points_project_onto_the_screen_unless_behind_the_camera() {
+        let camera = Transform::translation([1.0, 2.0, 3.0, 4.0]);
+        let v_fov = FRAC_PI_2;
+        let size = [64, 48];
+        let project = |offset: [f32; 4]| {
+            let position = std::array::from_fn(|axis| camera.position()[axis] + offset[axis]);
+            project_point(camera, v_fov, size, position)
+        };
+
+        // straight ahead lands in the middle, at its distance
+        assert_eq!(project([2.0, 0.0, 0.0, 0.0]), Some(([32.0, 24.0], 2.0)));
+        // 45 degrees up reaches the top edge with a 90 degree field of view
+        let ([x, y], depth) = project([2.0, 2.0, 0.0, 0.0]).unwrap();
+        assert!((x - 32.0).abs() < 1e-4 && y.abs() < 1e-4 && depth == 2.0);
+        let ([x, y], _) = project([2.0, 0.0, 1.0, 0.0]).unwrap();
+        assert!(x > 32.0 && (y - 24.0).abs() < 1e-4);
+        // w is perpendicular to the view, so only the ray tracer sees it
+        assert_eq!(project([2.0, 0.0, 0.0, 5.0]), Some(([32.0, 24.0], 2.0)));
+
+        assert_eq!(project([-2.0, 0.0, 0.0, 0.0]), None);
+        assert_eq!(project([0.0, 1.0, 0.0, 0.0]), None);
+        assert_eq!(project([0.0; 4]), None);
+    }
+
+    #[test]
+    fn an_adapter_is_found_when_falling_back_is_allowed() {
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+        let hardware = pollster::block_on(request_adapter(&instance, None, false));
+        let Some(adapter) = pollster::block_on(request_adapter(&instance, None, true)) else {
+            eprintln!("Neither a hardware nor a software adapter is available, skipping");
+            return;
+        };
+        // the fallback is only used without a hardware adapter
+        if let Some(hardware) = hardware {
+            assert_eq!(adapter.get_info(), hardware.get_info());
+        }
+    }
+
+    #[test]
+    fn pinned_w_survives_movement_along_w() {
+        // turned halfway into w, so flying forward moves along x and w
+        let camera = Transform::translation([1.0, 1.0, 1.0, 2.0]) * Transform::rotation_xw(0.5);
+        let moved = camera * fly_movement([3.0, 0.0, 0.5, 0.0], Transform::IDENTITY, false);
+        assert_ne!(moved.position()[3], 2.0);
+
+        let position = moved.position();
+        let pinned = clamp_camera_position(position, None, None, false, Some(2.0));
+        assert_eq!(pinned[3], 2.0);
+        assert_eq!(pinned[..3], position[..3]);
+
+        // the pin wins over the other limits
+        let pinned = clamp_camera_position(
+            [10.0, 0.0, 0.0, 10.0],
+            Some(4.0),
+            Some(WorldBounds {
+                min: [-1.0; 4],
+                max: [1.0; 4],
+            }),
+            true,
+            Some(2.0),
+        );
+        assert_eq!(pinned, [1.0, 0.0, 0.0, 2.0]);
+        assert_eq!(
+            clamp_camera_position([10.0, 0.0, 0.0, 10.0], None, None, false, None),
+            [10.0, 0.0, 0.0, 10.0]
+        );
+    }
+
+    #[test]
+    fn walking_forward_stays_level_at_any_pitch() {
+        for pitch in [-1.5, -0.7, 0.0, 0.3, 1.2] {
+            let view = Transform::rotation_xz(0.4) * Transform::rotation_xy(pitch);
+            let walked = world_displacement([2.0, 0.0, 0.0, 0.0], view, true, false);
+            assert_eq!(walked[1], 0.0, "pitch {pitch}");
+            let length = walked.iter().map(|x| x * x).sum::<f32>().sqrt();
+            assert!((length - 2.0).abs() < 1e-4, "pitch {pitch}: {walked:?}");
+
+            // up and down stay separate from the pitch
+            let climbed = world_displacement([0.0, 1.0, 0.0, 0.0], view, true, false);
+            assert_eq!(climbed, [0.0, 1.0, 0.0, 0.0]);
+        }
+
+        assert_eq!(flatten_to_horizontal([0.0, -1.0, 0.0, 0.0]), [0.0; 4]);
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn frame_target_pool_reuses_textures_and_evicts_the_oldest() {
+        let (device, _queue) = test_device();
+        let (layout, luminance) = frame_target_resources(&device);
+        let create = |size| {
+            FrameTargets::new(
+                &device,
+                &layout,
+                wgpu::TextureFormat::Rgba8Unorm,
+                &luminance,
+                size,
+            )
+        };
+
+        let sizes: Vec<[u32; 2]> = (1..=MAX_POOLED_FRAME_TARGETS as u32 + 1)
+            .map(|i| [16 * i, 8 * i])
+            .collect();
+        let mut pool = VecDeque::new();
+        let mut textures = vec![];
+        for &size in &sizes {
+            let frame_targets = create(size);
+            textures.push(frame_targets.main_texture.global_id());
+            pool_frame_targets(&mut pool, frame_targets);
+        }
+        assert_eq!(pool.len(), MAX_POOLED_FRAME_TARGETS);
+
+        // the first size was pooled longest ago, so it made room for the last
+        assert!(take_pooled_frame_targets(&mut pool, sizes[0]).is_none());
+        for (&size, &texture) in sizes.iter().zip(&textures).skip(1) {
+            let frame_targets = take_pooled_frame_targets(&mut pool, size).unwrap();
+            assert_eq!(frame_targets.main_texture.global_id(), texture);
+            assert_eq!(frame_targets.size(), size);
+        }
+        assert!(pool.is_empty());
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn shadow_rays_start_bias_along_the_normal() {
+        let bytes = encode_render_settings(&RenderSettings {
+            shadow_bias: 0.125,
+            ..render_settings()
+        });
+        assert_eq!(
+            read_f32s(
+                &bytes,
+                shader_struct_offset("RenderSettings", "shadow_bias")
+            ),
+            [0.125]
+        );
+
+        // a floor block with a floating block one cell above it, casting a shadow on its top face
+        let mut chunk = empty_chunk();
+        chunk.data[Chunk::block_index([1, 0, 1, 1]).unwrap()] = solid_block(1.0);
+        chunk.data[Chunk::block_index([1, 2, 1, 1]).unwrap()] = solid_block(1.0);
+        let camera = Transform::translation([0.2, 1.6, 1.5, 1.5]).look_at([1.5, 1.0, 1.5, 1.5]);
+        let size = [64, 48];
+        let center = |shadows, shadow_bias| {
+            let settings = RenderSettings {
+                shadows,
+                shadow_bias,
+                ..frame_render_settings(size)
+            };
+            render_headless(size, camera, &chunk, &settings).pixel(32, 24)
+        };
+        let unshadowed = center(0, 0.0);
+        let shadowed = center(1, 0.01);
+        assert!(shadowed[0] < unshadowed[0], "{shadowed:?} {unshadowed:?}");
+        // offset along the normal past the top of the floating block, nothing is in the way
+        assert_eq!(center(1, 2.5), unshadowed);
+    }
+
+    #[test]
+    fn autofocus_converges_on_the_block_in_the_center() {
+        let mut chunk = empty_chunk();
+        chunk.data[Chunk::block_index([3, 1, 1, 1]).unwrap()] = solid_block(1.0);
+        let camera = Transform::translation([0.5, 1.5, 1.5, 1.5]);
+        let forward = camera.transform_direction([1.0, 0.0, 0.0, 0.0]);
+        let focus_target = chunk
+            .raycast(camera.position(), forward, AUTOFOCUS_MAX_DISTANCE)
+            .unwrap();
+        assert!((focus_target - 2.5).abs() < 1e-5);
+
+        let mut depth_of_field = DepthOfField {
+            aperture: 0.1,
+            focal_distance: 10.0,
+        };
+        let mut previous_error = f32::INFINITY;
+        for _ in 0..120 {
+            depth_of_field.autofocus(focus_target, 1.0 / 60.0);
+            let error = (depth_of_field.focal_distance - focus_target).abs();
+            assert!(error <= previous_error);
+            previous_error = error;
+        }
+        assert!(previous_error < 1e-3, "{previous_error}");
+    }
+
+    #[test]
+    fn raycasts_without_a_direction_hit_nothing() {
+        let mut chunk = empty_chunk();
+        chunk.data[Chunk::block_index([2, 1, 1, 1]).unwrap()] = solid_block(1.0);
+        let origin = [0.5, 1.5, 1.5, 1.5];
+
+        assert_eq!(chunk.raycast(origin, [1.0, 0.0, 0.0, 0.0], 10.0), Some(1.5));
+        assert_eq!(chunk.raycast(origin, [0.0; 4], 10.0), None);
+        assert_eq!(chunk.raycast(origin, [-0.0, 0.0, -0.0, 0.0], 10.0), None);
+        assert_eq!(chunk.raycast(origin, [f32::NAN, 0.0, 0.0, 0.0], 10.0), None);
+        assert_eq!(
+            chunk.raycast(origin, [f32::INFINITY, 0.0, 0.0, 0.0], 10.0),
+            None
+        );
+        assert_eq!(
+            chunk.raycast(origin, [1.0, f32::NEG_INFINITY, 0.0, 0.0], 10.0),
+            None
+        );
+    }
+
+    #[test]
+    fn raycasts_with_unbounded_inputs_finish() {
+        let mut chunk = empty_chunk();
+        chunk.data[Chunk::block_index([2, 1, 1, 1]).unwrap()] = solid_block(1.0);
+        let origin = [0.5, 1.5, 1.5, 1.5];
+        let forward = [1.0, 0.0, 0.0, 0.0];
+
+        assert_eq!(chunk.raycast(origin, forward, f32::INFINITY), Some(1.5));
+        // misses leave the chunk instead of stepping on forever
+        assert_eq!(
+            chunk.raycast(origin, [-1.0, 0.0, 0.0, 0.0], f32::INFINITY),
+            None
+        );
+        assert_eq!(
+            chunk.raycast(origin, [0.0, 1.0, 0.0, 0.0], f32::INFINITY),
+            None
+        );
+        assert_eq!(
+            chunk.raycast([-1e30, 1.5, 1.5, 1.5], [-1.0, 0.0, 0.0, 0.0], f32::INFINITY),
+            None
+        );
+        // rays from outside still find the chunk
+        assert_eq!(
+            chunk.raycast([-3.5, 1.5, 1.5, 1.5], forward, f32::INFINITY),
+            Some(5.5)
+        );
+
+        assert_eq!(chunk.raycast(origin, forward, f32::NAN), None);
+        assert_eq!(
+            chunk.raycast([f32::NAN, 1.5, 1.5, 1.5], forward, 10.0),
+            None
+        );
+        assert_eq!(
+            chunk.raycast([0.5, f32::INFINITY, 1.5, 1.5], forward, 10.0),
+            None
+        );
+    }
+
+    #[test]
+    fn heatmap_gradients_bake_into_the_lookup_buffer() {
+        let [start, middle, end] =
+            [[0.0, 0.0, 1.0], [0.0, 1.0, 0.0], [1.0, 0.5, 0.0]].map(|[r, g, b]| Color { r, g, b });
+        let bytes =
+            HeatmapGradient::encode(&Gradient::new([(0.0, start), (0.5, middle), (1.0, end)]))
+                .unwrap();
+        assert_eq!(bytes.len(), HEATMAP_GRADIENT_SIZE * 16);
+
+        // each `vec3<f32>` element is padded out to 16 bytes
+        let color_at = |index: usize| read_f32s::<3>(&bytes, index * 16);
+        assert_eq!(color_at(0), [0.0, 0.0, 1.0]);
+        assert_eq!(color_at(HEATMAP_GRADIENT_SIZE - 1), [1.0, 0.5, 0.0]);
+        let [r, g, b] = color_at(HEATMAP_GRADIENT_SIZE / 2);
+        assert!(r > 0.0 && g > 0.9 && b == 0.0, "{:?}", [r, g, b]);
+    }
+
+    #[test]
+    fn chunks_over_the_binding_limit_are_rejected() {
+        let size = Chunk::SHADER_SIZE.get();
+        check_chunk_buffer_size(size, size as u32).unwrap();
+        check_chunk_buffer_size(
+            size,
+            wgpu::Limits::default().max_storage_buffer_binding_size,
+        )
+        .unwrap();
+
+        let error = check_chunk_buffer_size(size, size as u32 - 1)
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.contains(&format!("needs {size} bytes"))
+                && error.contains(&format!("allows {} bytes", size - 1))
+                && error.contains("smaller CHUNK_SIZE"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn cycling_from_any_render_mode_visits_every_mode_once() {
+        let mut start = RenderMode::Normal;
+        loop {
+            let mut render_mode = start;
+            let mut visited = vec![start];
+            loop {
+                render_mode = render_mode.next();
+                if render_mode == start {
+                    break;
+                }
+                assert!(!visited.contains(&render_mode), "{visited:?}");
+                visited.push(render_mode);
+            }
+            assert_eq!(visited.len(), 5);
+            // every mode the shader branches on is part of the cycle
+            let mut values = visited.iter().map(|&mode| mode as u32).collect::<Vec<_>>();
+            values.sort();
+            assert_eq!(values, [0, 1, 2, 3, 4]);
+
+            start = start.next();
+            if start == RenderMode::Normal {
+                break;
+            }
+        }
+    }
+
+    #[test]
+    fn debug_settings_round_trip_through_a_file() {
+        let path = temp_path("debug-settings.txt");
+        for debug_settings in [
+            DebugSettings::default(),
+            DebugSettings {
+                render_mode: RenderMode::Depth,
+                wireframe: true,
+                view_plane_gizmo: true,
+                ..DebugSettings::default()
+            },
+            DebugSettings {
+                render_mode: RenderMode::ChunkTint,
+                wireframe: false,
+                split_view: true,
+                back_faces: true,
+                view_plane_gizmo: false,
+                compass: true,
+            },
+        ] {
+            std::fs::write(&path, debug_settings.to_text()).unwrap();
+            let loaded = DebugSettings::parse(&std::fs::read_to_string(&path).unwrap()).unwrap();
+            assert_eq!(loaded, debug_settings);
+        }
+        std::fs::remove_file(&path).unwrap();
+
+        // anything left out is off
+        assert_eq!(
+            DebugSettings::parse("\n  wireframe = true\n").unwrap(),
+            DebugSettings {
+                wireframe: true,
+                ..DebugSettings::default()
+            }
+        );
+        assert!(DebugSettings::parse("render_mode = sepia").is_err());
+        assert!(DebugSettings::parse("wireframe = yes").is_err());
+        assert!(DebugSettings::parse("shaders = true").is_err());
+    }
+
+    #[test]
+    fn negative_positions_are_never_in_the_origin_block() {
+        let mut chunk = empty_chunk();
+        chunk.data[Chunk::block_index([0; 4]).unwrap()] = solid_block(1.0);
+
+        assert!(chunk.is_solid([0.0; 4]));
+        assert!(chunk.is_solid([-0.0, 0.5, 0.5, 0.5]));
+        assert!(chunk.is_solid([0.999; 4]));
+        // truncating towards zero would put these in the block at the origin
+        for axis in 0..4 {
+            for negative in [-1e-6, -0.5, -0.999, -1.0, -1000.0, f32::NEG_INFINITY] {
+                let mut position = [0.5; 4];
+                position[axis] = negative;
+                assert!(!chunk.is_solid(position), "{position:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn a_single_voxel_exports_as_one_obj_cube() {
+        let mut chunk = empty_chunk();
+        chunk.data[Chunk::block_index([1, 2, 3, 1]).unwrap()] = solid_block(0.25);
+        // a block in another slice is left out
+        chunk.data[Chunk::block_index([0, 0, 0, 2]).unwrap()] = solid_block(1.0);
+        let path = temp_path("slice.obj");
+        chunk.export_slice_obj(1, &path).unwrap();
+        let obj = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let mut vertices = vec![];
+        let mut faces = vec![];
+        for line in obj.lines() {
+            let mut fields = line.split_whitespace();
+            match fields.next() {
+                Some("v") => {
+                    let values = fields
+                        .map(|x| x.parse::<f32>().unwrap())
+                        .collect::<Vec<_>>();
+                    assert_eq!(values.len(), 6, "{line}");
+                    assert_eq!(values[3..], [0.25, 0.5, 0.5]);
+                    vertices.push([values[0], values[1], values[2]]);
+                }
+                Some("f") => {
+                    let face = fields
+                        .map(|x| x.parse::<usize>().unwrap())
+                        .collect::<Vec<_>>();
+                    assert_eq!(face.len(), 4, "{line}");
+                    faces.push(face);
+                }
+                _ => panic!("Unexpected OBJ line `{line}`"),
+            }
+        }
+        assert_eq!(vertices.len(), 8);
+        assert_eq!(faces.len(), 6);
+
+        let mut corners = vertices.clone();
+        corners.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        corners.dedup();
+        assert_eq!(corners.len(), 8);
+        assert_eq!(corners[0], [1.0, 2.0, 3.0]);
+        assert_eq!(corners[7], [2.0, 3.0, 4.0]);
+
+        let center = [1.5, 2.5, 3.5];
+        for face in faces {
+            assert!(face.iter().all(|&index| (1..=8).contains(&index)));
+            let [a, b, c, d] = [0, 1, 2, 3].map(|i| vertices[face[i] - 1]);
+            let sub = |p: [f32; 3], q: [f32; 3]| [p[0] - q[0], p[1] - q[1], p[2] - q[2]];
+            let (u, v) = (sub(b, a), sub(c, a));
+            let normal = [
+                u[1] * v[2] - u[2] * v[1],
+                u[2] * v[0] - u[0] * v[2],
+                u[0] * v[1] - u[1] * v[0],
+            ];
+            let face_center = std::array::from_fn(|axis| (a[axis] + c[axis]) * 0.5);
+            let outward = sub(face_center, center);
+            // counter-clockwise seen from outside, and a proper quad rather than a bowtie
+            assert!((0..3).map(|axis| normal[axis] * outward[axis]).sum::<f32>() > 0.0);
+            assert_eq!(
+                std::array::from_fn::<f32, 3, _>(|axis| (b[axis] + d[axis]) * 0.5),
+                face_center
+            );
+        }
+    }
+
+    #[test]
+    fn screenshots_supersample_and_downsample() {
+        assert_eq!(supersampled_size([64, 48], 1, 8192).unwrap(), [64, 48]);
+        assert_eq!(supersampled_size([64, 48], 3, 8192).unwrap(), [192, 144]);
+        assert!(supersampled_size([64, 48], 200, 8192).is_err());
+        assert!(supersampled_size([64, 48], u32::MAX, u32::MAX).is_err());
+
+        // a 2x2 checker of 3x3 blocks, with a partial row and column left over
+        let size = [7, 7];
+        let pixels = (0..size[1])
+            .flat_map(|y| (0..size[0]).map(move |x| (x, y)))
+            .flat_map(|(x, y)| {
+                let value = if (x / 3 + y / 3) % 2 == 0 { 30 } else { 90 };
+                [value, value, value, 255]
+            })
+            .collect::<Vec<u8>>();
+        let downsampled = downsample(&pixels, size, 3);
+        assert_eq!(downsampled.len(), 2 * 2 * 4);
+        assert_eq!(
+            downsampled
+                .chunks_exact(4)
+                .map(|pixel| pixel[0])
+                .collect::<Vec<_>>(),
+            [30, 90, 90, 30]
+        );
+        assert_eq!(
+            encode_ppm([2, 2], &downsampled).len(),
+            "P6\n2 2\n255\n".len() + 2 * 2 * 3
+        );
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn supersampled_screenshots_restore_the_frame_targets() {
+        let (device, _queue) = test_device();
+        let (layout, luminance) = frame_target_resources(&device);
+        let mut pool = VecDeque::new();
+        // the same swap `Game::screenshot` makes through `Game::resize_frame_targets`
+        let resize = |current: &mut FrameTargets, pool: &mut VecDeque<_>, size| {
+            let frame_targets = take_pooled_frame_targets(pool, size).unwrap_or_else(|| {
+                FrameTargets::new(
+                    &device,
+                    &layout,
+                    wgpu::TextureFormat::Rgba8Unorm,
+                    &luminance,
+                    size,
+                )
+            });
+            pool_frame_targets(pool, std::mem::replace(current, frame_targets));
+        };
+        let mut current = FrameTargets::new(
+            &device,
+            &layout,
+            wgpu::TextureFormat::Rgba8Unorm,
+            &luminance,
+            [64, 48],
+        );
+        let original = current.main_texture.global_id();
+
+        let supersampled = supersampled_size([64, 48], 2, 8192).unwrap();
+        resize(&mut current, &mut pool, supersampled);
+        assert_eq!(current.size(), [128, 96]);
+        resize(&mut current, &mut pool, [64, 48]);
+        remove_pooled_frame_targets(&mut pool, supersampled);
+
+        assert_eq!(current.size(), [64, 48]);
+        assert_eq!(current.main_texture.global_id(), original);
+        assert!(pool.is_empty());
+    }
+
+    /// Two scenes, each with a different block set
+    fn two_scenes() -> [Chunk; 2] {
+        [0.25, 0.75].map(|r| {
+            let mut chunk = empty_chunk();
+            chunk.data[(r * 100.0) as usize] = solid_block(r);
+            chunk
+        })
+    }
+
+    #[test]
+    fn switching_scenes_swaps_the_chunk() {
+        let [first, second] = two_scenes();
+        let original = empty_chunk();
+        let mut scenes = Scenes {
+            chunks: vec![first.clone(), second.clone()],
+            active: None,
+        };
+        let mut chunk = original.clone();
+
+        assert!(scenes.activate(&mut chunk, 1).unwrap());
+        assert!(chunk == second);
+        assert!(!scenes.activate(&mut chunk, 1).unwrap());
+        // edits stay with the scene they were made in
+        chunk.data[7] = solid_block(1.0);
+        let edited = chunk.clone();
+        assert!(scenes.activate(&mut chunk, 0).unwrap());
+        assert!(chunk == first);
+        assert!(scenes.activate(&mut chunk, 1).unwrap());
+        assert!(chunk == edited);
+        assert!(scenes.activate(&mut chunk, 2).is_err());
+        assert_eq!(scenes.active, Some(1));
+    }
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn switching_scenes_reuploads_the_chunk() {
+        let mut scenes = Scenes {
+            chunks: two_scenes().to_vec(),
+            active: None,
+        };
+        let mut chunk = empty_chunk();
+
+        // a switch marks the whole chunk dirty, which uploads every block of the new scene
+        let (device, queue) = test_device();
+        let chunk_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: Chunk::SHADER_SIZE.get(),
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::COPY_DST
+                | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let mut staging_belt = wgpu::util::StagingBelt::new(Chunk::SHADER_SIZE.get());
+        for index in [0, 1] {
+            scenes.activate(&mut chunk, index).unwrap();
+            let mut encoder = device.create_command_encoder(&Default::default());
+            stage_chunk_upload(
+                &mut staging_belt,
+                &mut encoder,
+                &device,
+                &chunk_buffer,
+                &chunk,
+                0..chunk.data.len(),
+            )
+            .unwrap();
+            staging_belt.finish();
+            queue.submit([encoder.finish()]);
+            staging_belt.recall();
+            let uploaded = read_chunk_buffer(&device, &queue, &chunk_buffer).unwrap();
+            assert!(uploaded.diff(&chunk).is_empty());
+        }
+    }
+
+    #[test]
+    fn xw_turn_ease_follows_the_curve_to_the_target() {
+        let smoothing = XwTurnSmoothing {
+            duration: 0.4,
+            curve: EasingCurve::SmoothStep,
+        };
+        let mut ease = XwTurnEase::default();
+        ease.retarget(1.0);
+        let mut turned = 0.0;
+        for step in 1..=4 {
+            turned += ease.advance(smoothing, 0.1);
+            let expected = smoothing.curve.apply(step as f32 / 4.0);
+            assert!((turned - expected).abs() < 1e-5, "{turned} != {expected}");
+        }
+        assert!((turned - 1.0).abs() < 1e-5);
+        assert_eq!(ease.remaining(), 0.0);
+        assert_eq!(ease.advance(smoothing, 0.1), 0.0);
+    }
+
+    #[test]
+    fn failed_auto_save_logs_an_error() {
+        struct RecordingLogger(std::sync::Mutex<Vec<(log::Level, String)>>);
+
+        impl log::Log for RecordingLogger {
+            fn enabled(&self, _metadata: &log::Metadata) -> bool {
+                true
+            }
+
+            fn log(&self, record: &log::Record) {
+                let message = record.args().to_string();
+                self.0.lock().unwrap().push((record.level(), message));
+            }
+
+            fn flush(&self) {}
+        }
+
+        static LOGGER: RecordingLogger = RecordingLogger(std::sync::Mutex::new(Vec::new()));
+        log::set_logger(&LOGGER).unwrap();
+        log::set_max_level(log::LevelFilter::Trace);
+
+        // the parent directory doesn't exist, so the write fails
+        let path = temp_path("missing-directory").join("auto-save.chunk");
+        let mut auto_save = AutoSave {
+            settings: AutoSaveSettings {
+                path: path.clone(),
+                idle_time: Duration::ZERO,
+            },
+            time_since_edit: None,
+            save_thread: None,
+        };
+        auto_save.mark_edited();
+        auto_save.update(&empty_chunk(), Duration::ZERO).unwrap();
+        auto_save.save_thread.take().unwrap().join().unwrap();
+
+        let records = LOGGER.0.lock().unwrap();
+        assert!(records.iter().any(|(level, message)| {
+            *level == log::Level::Error && message.contains(&path.display().to_string())
+        }));
     }
 }
@@ -1,9 +1,13 @@
+mod clock;
 pub mod color;
-mod game;
+mod console;
+pub mod game;
 pub mod math;
 
-use game::Game;
-use std::sync::Arc;
+use anyhow::bail;
+use clock::{Clock, FixedTimestep, SystemClock};
+use game::{scaled_dt, Game};
+use std::{path::PathBuf, sync::Arc};
 use winit::{
     dpi::PhysicalSize,
     event::{DeviceEvent, Event, MouseScrollDelta, StartCause, WindowEvent},
@@ -11,142 +15,256 @@ use winit::{
     window::{CursorGrabMode, WindowBuilder},
 };
 
+struct StartupSettings {
+    visible: bool,
+    grab_cursor: bool,
+    hide_cursor: bool,
+    fallback_adapter: bool,
+    debug_settings: Option<PathBuf>,
+}
+
+impl StartupSettings {
+    fn from_args(args: impl IntoIterator<Item = String>) -> anyhow::Result<Self> {
+        let mut settings = Self {
+            visible: true,
+            grab_cursor: true,
+            hide_cursor: true,
+            fallback_adapter: false,
+            debug_settings: None,
+        };
+        for arg in args {
+            match arg.as_str() {
+                "--hidden" => settings.visible = false,
+                "--no-cursor-grab" => settings.grab_cursor = false,
+                "--show-cursor" => settings.hide_cursor = false,
+                "--fallback-adapter" => settings.fallback_adapter = true,
+                _ if arg.starts_with("--debug-settings=") => {
+                    settings.debug_settings = Some(arg["--debug-settings=".len()..].into());
+                }
+                _ => bail!("Unknown argument `{arg}`"),
+            }
+        }
+        Ok(settings)
+    }
+}
+
 fn main() -> anyhow::Result<()> {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn")).init();
+
+    let startup_settings = StartupSettings::from_args(std::env::args().skip(1))?;
+
     let event_loop = EventLoop::new()?;
     let window = Arc::new(
         WindowBuilder::new()
             .with_title("Game")
-            .with_visible(false)
+            .with_visible(startup_settings.visible)
             .build(&event_loop)?,
     );
 
-    let mut game = pollster::block_on(Game::new(window.clone()))?;
+    let mut game = Some(pollster::block_on(Game::new(
+        window.clone(),
+        startup_settings.fallback_adapter,
+    ))?);
+    if let (Some(game), Some(path)) = (&mut game, &startup_settings.debug_settings) {
+        game.load_debug_settings(path)?;
+    }
 
-    let mut last_time = std::time::Instant::now();
+    let mut clock = SystemClock::new();
     let mut dt = std::time::Duration::ZERO;
-    let ts = std::time::Duration::from_secs(1) / 100;
-    let mut fixed_time = std::time::Duration::ZERO;
-    event_loop.run(move |event, elwt| match event {
-        Event::NewEvents(cause) => {
-            match cause {
-                StartCause::Init => {
-                    elwt.set_control_flow(ControlFlow::Poll);
-                    window.set_visible(true);
-                    window.set_cursor_grab(CursorGrabMode::Confined).unwrap();
-                    window.set_cursor_visible(false);
-                    last_time = std::time::Instant::now();
+    let mut fixed_timestep = FixedTimestep::new(std::time::Duration::from_secs(1) / 100);
+    event_loop.run(move |event, elwt| {
+        if let Event::LoopExiting = event {
+            window.set_visible(false);
+            if let Some(game) = game.take() {
+                if let Some(path) = &startup_settings.debug_settings {
+                    if let Err(error) = game.save_debug_settings(path) {
+                        log::error!("{error:?}");
+                    }
                 }
-                StartCause::Poll => {}
-                _ => {}
-            };
-
-            let time = std::time::Instant::now();
-            dt = time - last_time;
-            last_time = time;
+                game.shutdown();
+            }
+            return;
         }
 
-        Event::WindowEvent { window_id, event } if window_id == window.id() && !elwt.exiting() => {
-            match event {
-                WindowEvent::CloseRequested | WindowEvent::Destroyed => {
-                    elwt.exit();
-                }
+        let Some(game) = &mut game else {
+            return;
+        };
 
-                WindowEvent::Resized(PhysicalSize { width, height }) => {
-                    match game.resize(width, height) {
+        match event {
+            Event::NewEvents(cause) => {
+                match cause {
+                    StartCause::Init => {
+                        elwt.set_control_flow(ControlFlow::Poll);
+                        if startup_settings.grab_cursor {
+                            window.set_cursor_grab(CursorGrabMode::Confined).unwrap();
+                        }
+                        window.set_cursor_visible(!startup_settings.hide_cursor);
+                        clock.reset();
+                    }
+                    StartCause::Poll => {}
+                    _ => {}
+                };
+
+                dt = clock.tick();
+            }
+
+            Event::WindowEvent { window_id, event }
+                if window_id == window.id() && !elwt.exiting() =>
+            {
+                match event {
+                    WindowEvent::CloseRequested | WindowEvent::Destroyed => {
+                        elwt.exit();
+                    }
+
+                    WindowEvent::Resized(PhysicalSize { width, height }) => {
+                        match game.resize(width, height) {
+                            Ok(()) => {}
+                            Err(error) => {
+                                log::error!("{error}\n{}", error.backtrace());
+                                elwt.exit();
+                            }
+                        }
+                    }
+
+                    WindowEvent::RedrawRequested => match draw(game) {
                         Ok(()) => {}
                         Err(error) => {
-                            eprintln!("{error}");
-                            eprintln!("{}", error.backtrace());
+                            log::error!("{error}\n{}", error.backtrace());
                             elwt.exit();
                         }
-                    }
+                    },
+
+                    WindowEvent::KeyboardInput {
+                        device_id: _,
+                        event,
+                        is_synthetic: _,
+                    } => match game.keyboard(event) {
+                        Ok(()) => {}
+                        Err(error) => {
+                            log::error!("{error}\n{}", error.backtrace());
+                            elwt.exit();
+                        }
+                    },
+
+                    _ => {}
                 }
+            }
 
-                WindowEvent::RedrawRequested => match game.draw() {
+            Event::DeviceEvent {
+                device_id: _,
+                event,
+            } => match event {
+                DeviceEvent::MouseMotion { delta: (x, y) } => match game.cursor(x as _, y as _) {
                     Ok(()) => {}
                     Err(error) => {
-                        eprintln!("{error}");
-                        eprintln!("{}", error.backtrace());
+                        log::error!("{error}\n{}", error.backtrace());
                         elwt.exit();
                     }
                 },
-
-                WindowEvent::KeyboardInput {
-                    device_id: _,
-                    event,
-                    is_synthetic: _,
-                } => match game.keyboard(event) {
+                DeviceEvent::MouseWheel {
+                    delta: MouseScrollDelta::LineDelta(x, y),
+                } => match game.scroll(x, y) {
                     Ok(()) => {}
                     Err(error) => {
-                        eprintln!("{error}");
-                        eprintln!("{}", error.backtrace());
+                        log::error!("{error}\n{}", error.backtrace());
                         elwt.exit();
                     }
                 },
-
                 _ => {}
-            }
-        }
-
-        Event::DeviceEvent {
-            device_id: _,
-            event,
-        } => match event {
-            DeviceEvent::MouseMotion { delta: (x, y) } => match game.cursor(x as _, y as _) {
-                Ok(()) => {}
-                Err(error) => {
-                    eprintln!("{error}");
-                    eprintln!("{}", error.backtrace());
-                    elwt.exit();
-                }
-            },
-            DeviceEvent::MouseWheel {
-                delta: MouseScrollDelta::LineDelta(x, y),
-            } => match game.scroll(x, y) {
-                Ok(()) => {}
-                Err(error) => {
-                    eprintln!("{error}");
-                    eprintln!("{}", error.backtrace());
-                    elwt.exit();
-                }
             },
-            _ => {}
-        },
-
-        Event::AboutToWait if !elwt.exiting() => {
-            match game.update(dt) {
-                Ok(()) => {}
-                Err(error) => {
-                    eprintln!("{error}");
-                    eprintln!("{}", error.backtrace());
-                    elwt.exit();
-                    return;
-                }
-            }
 
-            fixed_time += dt;
-            while fixed_time >= ts {
-                match game.fixed_update(ts) {
+            Event::AboutToWait if !elwt.exiting() => {
+                match game.update(dt) {
                     Ok(()) => {}
                     Err(error) => {
-                        eprintln!("{error}");
-                        eprintln!("{}", error.backtrace());
+                        log::error!("{error}\n{}", error.backtrace());
                         elwt.exit();
                         return;
                     }
                 }
-                fixed_time -= ts;
-            }
 
-            window.request_redraw();
-        }
+                if game.fixed_update_enabled() {
+                    for _ in 0..fixed_timestep.advance(scaled_dt(dt, game.time_scale())) {
+                        match game.fixed_update(fixed_timestep.step()) {
+                            Ok(()) => {}
+                            Err(error) => {
+                                log::error!("{error}\n{}", error.backtrace());
+                                elwt.exit();
+                                return;
+                            }
+                        }
+                    }
+                } else {
+                    fixed_timestep.reset();
+                    while game.take_fixed_step() {
+                        match game.fixed_update(fixed_timestep.step()) {
+                            Ok(()) => {}
+                            Err(error) => {
+                                log::error!("{error}\n{}", error.backtrace());
+                                elwt.exit();
+                                return;
+                            }
+                        }
+                    }
+                }
 
-        Event::LoopExiting => {
-            window.set_visible(false);
-        }
+                game.set_fixed_update_alpha(fixed_timestep.alpha());
+                window.request_redraw();
+            }
 
-        _ => {}
+            _ => {}
+        }
     })?;
 
     Ok(())
 }
+
+fn draw(game: &mut Game) -> anyhow::Result<()> {
+    let result = game.acquire_frame().and_then(|frame| {
+        let Some(frame) = frame else {
+            return Ok(());
+        };
+        let view = frame.texture.create_view(&Default::default());
+        game.render_to_view(&view, (frame.texture.width(), frame.texture.height()))?;
+        game.present(frame);
+        Ok(())
+    });
+    // also runs for frames that were skipped, so every update is paired with a post draw
+    game.finish_draw();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn startup_settings(args: &[&str]) -> anyhow::Result<StartupSettings> {
+        StartupSettings::from_args(args.iter().map(|&arg| arg.to_owned()))
+    }
+
+    #[test]
+    fn startup_settings_follow_the_flags() {
+        let defaults = startup_settings(&[]).unwrap();
+        assert!(defaults.visible && defaults.grab_cursor && defaults.hide_cursor);
+        assert!(!defaults.fallback_adapter);
+        assert_eq!(defaults.debug_settings, None);
+
+        let settings = startup_settings(&[
+            "--hidden",
+            "--no-cursor-grab",
+            "--show-cursor",
+            "--fallback-adapter",
+            "--debug-settings=debug.txt",
+        ])
+        .unwrap();
+        assert!(!settings.visible && !settings.grab_cursor && !settings.hide_cursor);
+        assert!(settings.fallback_adapter);
+        assert_eq!(settings.debug_settings, Some("debug.txt".into()));
+
+        // each flag only changes its own setting
+        let settings = startup_settings(&["--show-cursor"]).unwrap();
+        assert!(settings.visible && settings.grab_cursor && !settings.hide_cursor);
+
+        assert!(startup_settings(&["--fullscreen"]).is_err());
+    }
+}
@@ -1 +1,3 @@
+pub mod bivector;
+pub mod point;
 pub mod transform;
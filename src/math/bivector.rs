@@ -0,0 +1,89 @@
+const SIMPLE_BIVECTOR_EPSILON: f32 = 1e-3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bivector {
+    pub xy: f32,
+    pub xz: f32,
+    pub xw: f32,
+    pub yz: f32,
+    pub yw: f32,
+    pub zw: f32,
+}
+
+impl Bivector {
+    pub const ZERO: Self = Self {
+        xy: 0.0,
+        xz: 0.0,
+        xw: 0.0,
+        yz: 0.0,
+        yw: 0.0,
+        zw: 0.0,
+    };
+
+    /// The plane spanned by `a` and `b`, oriented from `a` towards `b`
+    pub fn wedge(a: [f32; 4], b: [f32; 4]) -> Self {
+        let [ax, ay, az, aw] = a;
+        let [bx, by, bz, bw] = b;
+        Self {
+            xy: ax * by - ay * bx,
+            xz: ax * bz - az * bx,
+            xw: ax * bw - aw * bx,
+            yz: ay * bz - az * by,
+            yw: ay * bw - aw * by,
+            zw: az * bw - aw * bz,
+        }
+    }
+
+    /// Whether this is a single plane, rather than a sum of two that can't be written as one
+    pub fn is_simple(self) -> bool {
+        let Self {
+            xy,
+            xz,
+            xw,
+            yz,
+            yw,
+            zw,
+        } = self.normalized();
+        (xy * zw - xz * yw + xw * yz).abs() <= SIMPLE_BIVECTOR_EPSILON
+    }
+
+    pub fn magnitude_squared(self) -> f32 {
+        let Self {
+            xy,
+            xz,
+            xw,
+            yz,
+            yw,
+            zw,
+        } = self;
+        xy * xy + xz * xz + xw * xw + yz * yz + yw * yw + zw * zw
+    }
+
+    pub fn magnitude(self) -> f32 {
+        self.magnitude_squared().sqrt()
+    }
+
+    pub fn normalized(self) -> Self {
+        let magnitude = self.magnitude();
+        if magnitude <= f32::EPSILON {
+            return Self::ZERO;
+        }
+        let inverse_magnitude = magnitude.recip();
+        let Self {
+            xy,
+            xz,
+            xw,
+            yz,
+            yw,
+            zw,
+        } = self;
+        Self {
+            xy: xy * inverse_magnitude,
+            xz: xz * inverse_magnitude,
+            xw: xw * inverse_magnitude,
+            yz: yz * inverse_magnitude,
+            yw: yw * inverse_magnitude,
+            zw: zw * inverse_magnitude,
+        }
+    }
+}
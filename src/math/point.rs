@@ -0,0 +1,64 @@
+use super::transform::Transform;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Point {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub w: f32,
+}
+
+impl Point {
+    pub const ORIGIN: Self = Self {
+        x: 0.0,
+        y: 0.0,
+        z: 0.0,
+        w: 0.0,
+    };
+
+    pub fn from_cartesian(position: [f32; 4]) -> Self {
+        debug_assert!(
+            position.iter().all(|x| x.is_finite()),
+            "Point position is not finite: {position:?}"
+        );
+        let [x, y, z, w] = position;
+        Self { x, y, z, w }
+    }
+
+    pub fn from_transform(transform: Transform) -> Self {
+        Self::from_cartesian(transform.position())
+    }
+
+    pub fn to_cartesian(self) -> [f32; 4] {
+        let Self { x, y, z, w } = self;
+        [x, y, z, w]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_transform_is_the_translated_origin() {
+        let position = Point::from_transform(Transform::translation([1.0, 2.0, 3.0, 4.0]));
+        for (coordinate, expected) in std::iter::zip(position.to_cartesian(), [1.0, 2.0, 3.0, 4.0])
+        {
+            assert!((coordinate - expected).abs() <= 1e-6);
+        }
+    }
+
+    #[test]
+    fn from_transform_ignores_rotation_about_the_origin() {
+        let position =
+            Point::from_transform(Transform::rotation_xw(1.0) * Transform::rotation_yz(0.5));
+        assert!(position.to_cartesian().iter().all(|x| x.abs() <= 1e-6));
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "Point position is not finite")]
+    fn non_finite_points_trip_the_debug_assertion() {
+        Point::from_cartesian([0.0, f32::INFINITY, 0.0, 0.0]);
+    }
+}
@@ -2,7 +2,12 @@ use std::ops::{Mul, Not};
 
 use encase::ShaderType;
 
-#[derive(Debug, Clone, Copy, ShaderType)]
+use super::bivector::Bivector;
+
+const SQRT_DEGENERATE_EPSILON: f32 = 1e-12;
+const MOTOR_MAGNITUDE_EPSILON: f32 = 1e-2;
+
+#[derive(Debug, Clone, Copy, PartialEq, ShaderType)]
 pub struct Transform {
     pub s: f32,
     pub e01: f32,
@@ -53,6 +58,24 @@ impl Transform {
         }
     }
 
+    /// Returns `transforms[0] * transforms[1] * ...`, so the last transform is applied first.
+    pub fn compose(transforms: &[Self]) -> Self {
+        transforms
+            .iter()
+            .fold(Self::IDENTITY, |composed, &transform| composed * transform)
+    }
+
+    /// Returns the running products of `compose`, so the `i`th entry is `compose(&transforms[..=i])`.
+    pub fn compose_chain(transforms: &[Self]) -> Vec<Self> {
+        transforms
+            .iter()
+            .scan(Self::IDENTITY, |composed, &transform| {
+                *composed = *composed * transform;
+                Some(*composed)
+            })
+            .collect()
+    }
+
     pub fn rotation_xy(angle: f32) -> Self {
         let (sin, cos) = (angle * 0.5).sin_cos();
         Self {
@@ -107,6 +130,338 @@ impl Transform {
         }
     }
 
+    /// Rotates by `angle` in `plane`, which has to be simple, a double rotation can be built by
+    /// multiplying the rotations in two orthogonal planes
+    pub fn rotation(plane: Bivector, angle: f32) -> Self {
+        debug_assert!(plane.is_simple(), "Rotation plane is not simple: {plane:?}");
+        let plane = plane.normalized();
+        let (sin, cos) = (angle * 0.5).sin_cos();
+        Self {
+            s: cos,
+            e12: sin * plane.xy,
+            e13: -sin * plane.xz,
+            e14: sin * plane.xw,
+            e23: sin * plane.yz,
+            e24: -sin * plane.yw,
+            e34: sin * plane.zw,
+            ..Self::IDENTITY
+        }
+    }
+
+    /// Returns the rotation in the plane spanned by the unit directions `from` and `to` that turns
+    /// `from` onto `to`
+    pub fn rotation_between(from: [f32; 4], to: [f32; 4]) -> Self {
+        let plane = Bivector::wedge(from, to);
+        let cos = std::iter::zip(from, to).map(|(a, b)| a * b).sum::<f32>();
+        let sin = plane.magnitude();
+        if sin > f32::EPSILON {
+            return Self::rotation(plane, sin.atan2(cos));
+        }
+        if cos >= 0.0 {
+            return Self::IDENTITY;
+        }
+        // `from` and `to` are opposite, so half a turn in any plane containing `from` works
+        let axis = (0..4)
+            .min_by(|&a, &b| from[a].abs().total_cmp(&from[b].abs()))
+            .unwrap_or(0);
+        let mut other = [0.0; 4];
+        other[axis] = 1.0;
+        Self::rotation(Bivector::wedge(from, other), std::f32::consts::PI)
+    }
+
+    /// Turns the transform in place so that its forward axis, local x, points at `target`
+    pub fn look_at(self, target: [f32; 4]) -> Self {
+        let position = self.position();
+        let offset: [f32; 4] = std::array::from_fn(|axis| target[axis] - position[axis]);
+        let distance = offset.iter().map(|x| x * x).sum::<f32>().sqrt();
+        if distance <= f32::EPSILON {
+            return self;
+        }
+        let forward = self.transform_direction([1.0, 0.0, 0.0, 0.0]);
+        let rotation = Self::rotation_between(forward, offset.map(|x| x / distance));
+        Self::translation(position) * rotation * Self::translation(position.map(|x| -x)) * self
+    }
+
+    /// Builds a uniformly distributed rotation from a source of uniform samples in `[0, 1)`.
+    pub fn random(mut sample: impl FnMut() -> f32) -> Self {
+        let mut random_unit_quaternion = || loop {
+            let q: [f32; 4] = std::array::from_fn(|_| sample() * 2.0 - 1.0);
+            let magnitude_squared = q.iter().map(|x| x * x).sum::<f32>();
+            if magnitude_squared > f32::EPSILON && magnitude_squared <= 1.0 {
+                let inverse_magnitude = magnitude_squared.sqrt().recip();
+                break q.map(|x| x * inverse_magnitude);
+            }
+        };
+        let [a, b, c, d] = random_unit_quaternion();
+        let [e, f, g, h] = random_unit_quaternion();
+        Self {
+            s: (a + e) * 0.5,
+            e12: (d + h) * 0.5,
+            e13: (c + g) * 0.5,
+            e14: (b - f) * 0.5,
+            e23: (b + f) * 0.5,
+            e24: (g - c) * 0.5,
+            e34: (d - h) * 0.5,
+            e1234: (e - a) * 0.5,
+            ..Self::IDENTITY
+        }
+    }
+
+    /// Returns the plane and angle of a simple rotation, the inverse of `rotation`. The e1234 part
+    /// of a double rotation is ignored, so this only describes rotations in a single plane
+    pub fn rotation_plane(self) -> (Bivector, f32) {
+        let plane = Bivector {
+            xy: self.e12,
+            xz: -self.e13,
+            xw: self.e14,
+            yz: self.e23,
+            yw: -self.e24,
+            zw: self.e34,
+        };
+        let sin = plane.magnitude();
+        if sin <= f32::EPSILON {
+            return (Bivector::ZERO, 0.0);
+        }
+        (plane.normalized(), 2.0 * sin.atan2(self.s))
+    }
+
+    /// The sandwich `frame * self * !frame`, which carries this motion's planes over by `frame`
+    pub fn conjugate_by(self, frame: Self) -> Self {
+        frame * self * !frame
+    }
+
+    /// Scales the rotation down so no direction turns by more than `max_radians`, keeping its
+    /// planes and position. Double rotations keep the ratio between their two angles
+    pub fn clamp_rotation_angle(self, max_radians: f32) -> Self {
+        let position = self.position();
+        let rotation = (Self::translation(position.map(|x| -x)) * self).with_positive_scalar();
+        let [(first_angle, _), (second_angle, _)] = rotation.isoclinic_halves();
+        // the largest angle is in the plane where both halves turn the same way
+        let angle = first_angle + second_angle;
+        if angle <= max_radians {
+            return self;
+        }
+        Self::translation(position) * rotation.rotation_powf(max_radians.max(0.0) / angle)
+    }
+
+    /// `self` and `-self` are the same rotation, the one with a positive scalar part turns by at
+    /// most half a turn
+    fn with_positive_scalar(self) -> Self {
+        if self.s < 0.0 {
+            Self::from_components(self.components().map(|x| -x))
+        } else {
+            self
+        }
+    }
+
+    /// The idempotent `(1 + sign * e1234) / 2`, which squares to itself and picks out one isoclinic
+    /// half of a rotation
+    fn isoclinic_idempotent(sign: f32) -> Self {
+        Self {
+            s: 0.5,
+            e1234: 0.5 * sign,
+            ..Self::IDENTITY
+        }
+    }
+
+    /// Splits a rotation about the origin into its isoclinic halves along the idempotents
+    /// `(1 ± e1234) / 2`, returning the angle of each half and its bivector part scaled to unit
+    /// magnitude. Each half behaves like a unit quaternion, and the angles of the rotation's two
+    /// planes are the sum and difference of the halves' angles
+    fn isoclinic_halves(self) -> [(f32, Self); 2] {
+        let bivector_part = |transform: Self| Self {
+            s: 0.0,
+            e12: transform.e12,
+            e13: transform.e13,
+            e14: transform.e14,
+            e23: transform.e23,
+            e24: transform.e24,
+            e34: transform.e34,
+            ..Self::IDENTITY
+        };
+        let bivector_magnitude = |transform: Self| {
+            transform.components()[5..11]
+                .iter()
+                .map(|x| x * x)
+                .sum::<f32>()
+                .sqrt()
+        };
+        [1.0, -1.0].map(|sign| {
+            let idempotent = Self::isoclinic_idempotent(sign);
+            let half = self * idempotent;
+            let magnitude = bivector_magnitude(half);
+            let plane = if magnitude > f32::EPSILON {
+                bivector_part(half)
+            } else {
+                // The plane of a half that doesn't turn, or turns by a full turn, is arbitrary
+                bivector_part(Self::rotation_xy(std::f32::consts::PI) * idempotent)
+            };
+            let inverse_magnitude = bivector_magnitude(plane).recip();
+            (
+                (std::f32::consts::SQRT_2 * magnitude).atan2(2.0 * half.s),
+                Self::from_components(plane.components().map(|x| x * inverse_magnitude)),
+            )
+        })
+    }
+
+    /// Raises a rotation about the origin to the power `t`, scaling the angles in both of its
+    /// planes by `t`
+    fn rotation_powf(self, t: f32) -> Self {
+        let mut components = [0.0; 16];
+        for (sign, (angle, plane)) in [1.0, -1.0].into_iter().zip(self.isoclinic_halves()) {
+            let (sin, cos) = (angle * t).sin_cos();
+            for (component, plane_component) in components.iter_mut().zip(plane.components()) {
+                *component += plane_component * sin * std::f32::consts::FRAC_1_SQRT_2;
+            }
+            components[0] += cos * 0.5;
+            components[15] += cos * 0.5 * sign;
+        }
+        Self::from_components(components)
+    }
+
+    /// Rotates along the arc of at most half a turn; the position is interpolated linearly.
+    /// Double rotations turn in both of their planes at once.
+    pub fn slerp_shortest(self, other: Self, t: f32) -> Self {
+        self.slerp_arc(other, t, false)
+    }
+
+    /// Rotates along the complementary arc of more than half a turn, in the plane that turns the
+    /// most; the position is interpolated linearly.
+    pub fn slerp_long(self, other: Self, t: f32) -> Self {
+        self.slerp_arc(other, t, true)
+    }
+
+    fn slerp_arc(self, other: Self, t: f32, long: bool) -> Self {
+        let start_position = self.position();
+        let end_position = other.position();
+        let start_rotation = Self::translation(start_position.map(|x| -x)) * self;
+        let end_rotation = Self::translation(end_position.map(|x| -x)) * other;
+
+        let relative = (!start_rotation * end_rotation).with_positive_scalar();
+        // `-relative` is the same rotation reached the other way around, except for no rotation
+        // at all, which has no other way around
+        let relative = if long && relative.s < 1.0 {
+            Self::from_components(relative.components().map(|x| -x))
+        } else {
+            relative
+        };
+
+        let position = std::array::from_fn(|axis| {
+            start_position[axis] + (end_position[axis] - start_position[axis]) * t
+        });
+        Self::translation(position) * start_rotation * relative.rotation_powf(t)
+    }
+
+    /// Returns the transform halfway from the identity to `self`, so `self.sqrt() * self.sqrt()`
+    /// represents the same transform as `self`, possibly with the opposite sign.
+    pub fn sqrt(self) -> Self {
+        let position = self.position();
+        let rotation_root = (Self::translation(position.map(|x| -x)) * self).rotation_sqrt();
+
+        // `translation(offset) * rotation_root` squares to
+        // `translation(offset + rotation_root(offset)) * rotation`, so solve for `offset`
+        let columns: [[f32; 4]; 4] = std::array::from_fn(|axis| {
+            let mut direction = [0.0; 4];
+            direction[axis] = 1.0;
+            rotation_root.transform_direction(direction)
+        });
+        let offset = solve_linear_system(std::array::from_fn(|row| {
+            std::array::from_fn(|column| match column {
+                4 => position[row],
+                _ if column == row => columns[column][row] + 1.0,
+                _ => columns[column][row],
+            })
+        }));
+
+        Self::translation(offset) * rotation_root
+    }
+
+    /// The square root of a rotation about the origin
+    fn rotation_sqrt(self) -> Self {
+        // `self` and `-self` are the same rotation, and the one with a positive scalar part has a
+        // root of at most a quarter turn, which keeps `1 + self` well away from zero
+        let rotation = self.with_positive_scalar();
+        // A rotation splits into two isoclinic halves along the idempotents `(1 ± e1234) / 2`, and
+        // each half has the quaternion square root `normalize(1 + q)`
+        let shifted = Self {
+            s: rotation.s + 1.0,
+            ..rotation
+        };
+        let norm = !shifted * shifted;
+        let half_root = |sign: f32| {
+            let half_norm = norm.s + sign * norm.e1234;
+            if half_norm <= SQRT_DEGENERATE_EPSILON {
+                // This half is a full turn, so any half turn squares to it
+                return Self::rotation_xy(std::f32::consts::PI) * Self::isoclinic_idempotent(sign);
+            }
+            let scale = half_norm.sqrt().recip();
+            let half = shifted * Self::isoclinic_idempotent(sign);
+            Self::from_components(half.components().map(|x| x * scale))
+        };
+        let [a, b] = [half_root(1.0), half_root(-1.0)].map(Self::components);
+        Self::from_components(std::array::from_fn(|i| a[i] + b[i]))
+    }
+
+    /// Whether the components match to within `epsilon` up to a global sign, since a motor and its
+    /// negation move everything the same way
+    pub fn represents_same_rotation(self, other: Self, epsilon: f32) -> bool {
+        let components = self.components();
+        let other_components = other.components();
+        let matches = |sign: f32| {
+            std::iter::zip(components, other_components).all(|(component, other_component)| {
+                (component - sign * other_component).abs() <= epsilon
+            })
+        };
+        matches(1.0) || matches(-1.0)
+    }
+
+    fn from_components(components: [f32; 16]) -> Self {
+        let [s, e01, e02, e03, e04, e12, e13, e14, e23, e24, e34, e0123, e0124, e0134, e0234, e1234] =
+            components;
+        Self {
+            s,
+            e01,
+            e02,
+            e03,
+            e04,
+            e12,
+            e13,
+            e14,
+            e23,
+            e24,
+            e34,
+            e0123,
+            e0124,
+            e0134,
+            e0234,
+            e1234,
+        }
+    }
+
+    fn components(self) -> [f32; 16] {
+        let Self {
+            s,
+            e01,
+            e02,
+            e03,
+            e04,
+            e12,
+            e13,
+            e14,
+            e23,
+            e24,
+            e34,
+            e0123,
+            e0124,
+            e0134,
+            e0234,
+            e1234,
+        } = self;
+        [
+            s, e01, e02, e03, e04, e12, e13, e14, e23, e24, e34, e0123, e0124, e0134, e0234, e1234,
+        ]
+    }
+
     pub fn magnitude_squared(self) -> f32 {
         (!self * self).s
     }
@@ -116,7 +471,11 @@ impl Transform {
     }
 
     pub fn normalized(self) -> Self {
-        let inverse_magnitude = self.magnitude().recip();
+        let magnitude = self.magnitude();
+        if magnitude <= f32::EPSILON {
+            return Self::IDENTITY;
+        }
+        let inverse_magnitude = magnitude.recip();
         let Self {
             s,
             e01,
@@ -135,7 +494,7 @@ impl Transform {
             e0234,
             e1234,
         } = self;
-        Self {
+        let normalized = Self {
             s: s * inverse_magnitude,
             e01: e01 * inverse_magnitude,
             e02: e02 * inverse_magnitude,
@@ -152,10 +511,33 @@ impl Transform {
             e0134: e0134 * inverse_magnitude,
             e0234: e0234 * inverse_magnitude,
             e1234: e1234 * inverse_magnitude,
-        }
+        };
+        normalized.debug_assert_motor("The normalized transform");
+        normalized
+    }
+
+    /// Panics in debug builds if any component is NaN or infinite
+    #[track_caller]
+    fn debug_assert_finite(self, description: &str) {
+        debug_assert!(
+            self.components().into_iter().all(f32::is_finite),
+            "{description} is not finite: {self:?}"
+        );
+    }
+
+    /// Panics in debug builds unless this is a finite transform with unit magnitude
+    #[track_caller]
+    fn debug_assert_motor(self, description: &str) {
+        self.debug_assert_finite(description);
+        debug_assert!(
+            (self.magnitude_squared() - 1.0).abs() <= MOTOR_MAGNITUDE_EPSILON,
+            "{description} is not a unit motor, its magnitude is {}: {self:?}",
+            self.magnitude()
+        );
     }
 
     pub fn transform(self, point: [f32; 4]) -> [f32; 4] {
+        self.debug_assert_motor("The transform applied to a point");
         let Self {
             s: a,
             e01: b,
@@ -224,11 +606,20 @@ impl Transform {
                     + h * s2
                     + g * s0),
         ];
+        debug_assert!(
+            result.iter().all(|x| x.is_finite()),
+            "Transforming {point:?} by {self:?} gave the non-finite {result:?}"
+        );
         result.reverse();
         result
     }
 
+    pub fn position(self) -> [f32; 4] {
+        self.transform([0.0; 4])
+    }
+
     pub fn transform_direction(self, normal: [f32; 4]) -> [f32; 4] {
+        self.debug_assert_motor("The transform applied to a direction");
         let Self {
             s: a,
             e01: _,
@@ -274,9 +665,25 @@ impl Transform {
             p2 + 2.0 * (p * (k * p3 - p * p2 - g * p0 - h * p1) + f * s1 - j * s3 - i * s2),
             p3 + 2.0 * (h * s3 + g * s2 + f * s0 - p * (k * p2 + p * p3 + i * p0 + j * p1)),
         ];
+        debug_assert!(
+            result.iter().all(|x| x.is_finite()),
+            "Transforming {normal:?} by {self:?} gave the non-finite {result:?}"
+        );
         result.reverse();
         result
     }
+
+    /// Carries the hyperplane of points `p` with `normal · p = distance` along with this
+    /// transform, returning the moved `normal` and `distance`
+    pub fn transform_plane(self, normal: [f32; 4], distance: f32) -> ([f32; 4], f32) {
+        let normal = self.transform_direction(normal);
+        let offset = normal
+            .iter()
+            .zip(self.position())
+            .map(|(a, b)| a * b)
+            .sum::<f32>();
+        (normal, distance + offset)
+    }
 }
 
 impl Not for Transform {
@@ -362,7 +769,7 @@ impl Mul<Self> for Transform {
             e0234: p2,
             e1234: q2,
         } = rhs;
-        Self {
+        let product = Self {
             s: -g1 * g2 + -h1 * h2 + -i1 * i2 + -j1 * j2 + -k1 * k2 + -l1 * l2 + a1 * a2 + q1 * q2,
             e01: -c1 * g2
                 + -d1 * h2
@@ -499,6 +906,448 @@ impl Mul<Self> for Transform {
                 + h1 * n2
                 + i2 * m1,
             e1234: -h1 * k2 + -h2 * k1 + a1 * q2 + a2 * q1 + g1 * l2 + g2 * l1 + i1 * j2 + i2 * j1,
+        };
+        product.debug_assert_finite("The product of two transforms");
+        product
+    }
+}
+
+/// Solves the augmented system `rows` by Gaussian elimination, leaving unknowns without a usable
+/// pivot at zero
+fn solve_linear_system(mut rows: [[f32; 5]; 4]) -> [f32; 4] {
+    for pivot in 0..4 {
+        let best = (pivot..4)
+            .max_by(|&a, &b| rows[a][pivot].abs().total_cmp(&rows[b][pivot].abs()))
+            .unwrap_or(pivot);
+        rows.swap(pivot, best);
+        if rows[pivot][pivot].abs() <= f32::EPSILON {
+            continue;
+        }
+        for row in 0..4 {
+            if row != pivot {
+                let factor = rows[row][pivot] / rows[pivot][pivot];
+                let pivot_row = rows[pivot];
+                for (value, pivot_value) in rows[row].iter_mut().zip(pivot_row).skip(pivot) {
+                    *value -= factor * pivot_value;
+                }
+            }
+        }
+    }
+    std::array::from_fn(|row| {
+        if rows[row][row].abs() <= f32::EPSILON {
+            0.0
+        } else {
+            rows[row][4] / rows[row][row]
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f32 = 1e-5;
+
+    fn assert_bivector_eq(a: Bivector, b: Bivector) {
+        let difference = Bivector {
+            xy: a.xy - b.xy,
+            xz: a.xz - b.xz,
+            xw: a.xw - b.xw,
+            yz: a.yz - b.yz,
+            yw: a.yw - b.yw,
+            zw: a.zw - b.zw,
+        };
+        assert!(difference.magnitude() <= EPSILON, "{a:?} != {b:?}");
+    }
+
+    #[test]
+    fn rotation_plane_round_trips_rotation() {
+        let planes = [
+            Bivector {
+                xw: 1.0,
+                ..Bivector::ZERO
+            },
+            Bivector {
+                xy: 2.0,
+                yz: -1.0,
+                ..Bivector::ZERO
+            },
+            Bivector::wedge([1.0, 2.0, 0.0, 1.0], [0.0, 1.0, 3.0, -1.0]),
+        ];
+        for plane in planes {
+            for angle in [0.1, 1.0, 3.0] {
+                let (extracted_plane, extracted_angle) =
+                    Transform::rotation(plane, angle).rotation_plane();
+                assert_bivector_eq(extracted_plane, plane.normalized());
+                assert!((extracted_angle - angle).abs() <= EPSILON);
+            }
         }
     }
+
+    #[test]
+    fn rotation_plane_of_identity_is_zero() {
+        assert_eq!(Transform::IDENTITY.rotation_plane(), (Bivector::ZERO, 0.0));
+        assert_eq!(
+            Transform::translation([1.0, 2.0, 3.0, 4.0]).rotation_plane(),
+            (Bivector::ZERO, 0.0)
+        );
+    }
+
+    #[test]
+    fn transform_plane_keeps_transformed_points_on_it() {
+        let dot = |a: [f32; 4], b: [f32; 4]| a.iter().zip(b).map(|(a, b)| a * b).sum::<f32>();
+        let transform = Transform::translation([1.0, -2.0, 0.5, 3.0])
+            * Transform::rotation_xw(0.7)
+            * Transform::rotation_yz(-1.2);
+        let (normal, distance) = ([0.0, 0.6, 0.0, 0.8], 2.0);
+        let (moved_normal, moved_distance) = transform.transform_plane(normal, distance);
+        assert!((dot(moved_normal, moved_normal) - 1.0).abs() <= EPSILON);
+        for point in [
+            [0.0, 2.0, 0.0, 1.0],
+            [5.0, -2.0, 1.0, 4.0],
+            [-1.0, 0.0, 3.0, 2.5],
+        ] {
+            assert!((dot(normal, point) - distance).abs() <= EPSILON);
+            let moved = transform.transform(point);
+            assert!(
+                (dot(moved_normal, moved) - moved_distance).abs() <= 1e-4,
+                "{moved:?} is off the plane {moved_normal:?} {moved_distance}"
+            );
+        }
+    }
+
+    #[test]
+    fn isoclinic_idempotents_square_to_themselves_and_split_one() {
+        let [plus, minus] = [1.0, -1.0].map(Transform::isoclinic_idempotent);
+        assert_eq!(plus * plus, plus);
+        assert_eq!(minus * minus, minus);
+        assert_eq!(plus * minus, Transform::from_components([0.0; 16]));
+        let [a, b] = [plus, minus].map(Transform::components);
+        let sum = Transform::from_components(std::array::from_fn(|i| a[i] + b[i]));
+        assert_eq!(sum, Transform::IDENTITY);
+    }
+
+    #[test]
+    fn rotation_between_turns_from_onto_to() {
+        let pairs = [
+            ([1.0, 0.0, 0.0, 0.0], [0.0, 0.0, 0.0, 1.0]),
+            ([0.0, 1.0, 0.0, 0.0], [0.6, 0.0, 0.8, 0.0]),
+            ([0.5, 0.5, 0.5, 0.5], [0.5, -0.5, 0.5, -0.5]),
+            ([0.0, 0.0, 1.0, 0.0], [0.0, 0.0, 1.0, 0.0]),
+            ([0.0, 0.6, 0.0, 0.8], [0.0, -0.6, 0.0, -0.8]),
+        ];
+        for (from, to) in pairs {
+            let turned = Transform::rotation_between(from, to).transform_direction(from);
+            for (turned, to) in std::iter::zip(turned, to) {
+                assert!((turned - to).abs() <= EPSILON, "{from:?} -> {to:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn look_at_keeps_position_and_faces_target() {
+        let transform = Transform::translation([1.0, 2.0, 3.0, 4.0]) * Transform::rotation_yz(0.3);
+        let looking = transform.look_at([1.0, 2.0, 3.0, 10.0]);
+        let position = looking.position();
+        let forward = looking.transform_direction([1.0, 0.0, 0.0, 0.0]);
+        for (axis, expected) in [1.0, 2.0, 3.0, 4.0].into_iter().enumerate() {
+            assert!((position[axis] - expected).abs() <= EPSILON);
+        }
+        for (axis, expected) in [0.0, 0.0, 0.0, 1.0].into_iter().enumerate() {
+            assert!((forward[axis] - expected).abs() <= EPSILON);
+        }
+    }
+
+    fn assert_transform_eq(a: Transform, b: Transform) {
+        assert!(a.represents_same_rotation(b, 1e-4), "{a:?} != {b:?}");
+    }
+
+    #[test]
+    fn large_rotations_are_clamped() {
+        let rotation = Transform::translation([1.0, 2.0, 3.0, 4.0]) * Transform::rotation_xz(2.5);
+        let clamped = rotation.clamp_rotation_angle(0.5);
+        assert_transform_eq(
+            clamped,
+            Transform::translation([1.0, 2.0, 3.0, 4.0]) * Transform::rotation_xz(0.5),
+        );
+
+        let rotation = Transform::rotation_yw(-3.0);
+        assert_transform_eq(
+            rotation.clamp_rotation_angle(1.0),
+            Transform::rotation_yw(-1.0),
+        );
+    }
+
+    #[test]
+    fn small_rotations_are_not_clamped() {
+        let rotation = Transform::translation([1.0, 0.0, 0.0, 0.0]) * Transform::rotation_xy(0.2);
+        assert_eq!(rotation.clamp_rotation_angle(0.5), rotation);
+        let rotation = Transform::rotation_xy(0.2) * Transform::rotation_zw(-0.1);
+        assert_eq!(rotation.clamp_rotation_angle(0.5), rotation);
+    }
+
+    #[test]
+    fn double_rotations_are_clamped_in_both_planes() {
+        let rotation = Transform::rotation_xy(2.0) * Transform::rotation_zw(1.0);
+        let clamped = rotation.clamp_rotation_angle(0.5);
+        assert_transform_eq(
+            clamped,
+            Transform::rotation_xy(0.5) * Transform::rotation_zw(0.25),
+        );
+    }
+
+    #[test]
+    fn conjugating_by_identity_does_nothing() {
+        let rotation = Transform::translation([1.0, -2.0, 0.5, 3.0])
+            * Transform::rotation_xy(0.7)
+            * Transform::rotation_zw(-0.3);
+        assert_transform_eq(rotation.conjugate_by(Transform::IDENTITY), rotation);
+    }
+
+    #[test]
+    fn conjugating_by_a_quarter_turn_moves_the_plane() {
+        // turning y onto z carries a rotation in xy over to xz
+        let frame = Transform::rotation_yz(std::f32::consts::FRAC_PI_2);
+        assert_transform_eq(
+            Transform::rotation_xy(0.7).conjugate_by(frame),
+            Transform::rotation_xz(0.7),
+        );
+
+        // turning x onto w carries a rotation in xy over to wy
+        let frame = Transform::rotation_xw(std::f32::consts::FRAC_PI_2);
+        assert_transform_eq(
+            Transform::rotation_xy(0.4).conjugate_by(frame),
+            Transform::rotation_yw(-0.4),
+        );
+    }
+
+    #[test]
+    fn normalizing_a_zero_motor_gives_identity() {
+        let zero = Transform {
+            s: 0.0,
+            ..Transform::IDENTITY
+        };
+        assert_eq!(zero.normalized(), Transform::IDENTITY);
+        let tiny = Transform {
+            s: 1e-9,
+            e12: -1e-9,
+            ..Transform::IDENTITY
+        };
+        assert_eq!(tiny.normalized(), Transform::IDENTITY);
+    }
+
+    #[test]
+    fn normalizing_keeps_the_motion() {
+        let motor = Transform::translation([1.0, 2.0, -3.0, 0.5]) * Transform::rotation_xw(0.8);
+        assert_transform_eq(motor.normalized(), motor);
+
+        let scaled = Transform {
+            s: motor.s * 3.0,
+            e12: motor.e12 * 3.0,
+            e13: motor.e13 * 3.0,
+            e14: motor.e14 * 3.0,
+            e23: motor.e23 * 3.0,
+            e24: motor.e24 * 3.0,
+            e34: motor.e34 * 3.0,
+            e1234: motor.e1234 * 3.0,
+            e01: motor.e01 * 3.0,
+            e02: motor.e02 * 3.0,
+            e03: motor.e03 * 3.0,
+            e04: motor.e04 * 3.0,
+            e0123: motor.e0123 * 3.0,
+            e0124: motor.e0124 * 3.0,
+            e0134: motor.e0134 * 3.0,
+            e0234: motor.e0234 * 3.0,
+        };
+        let normalized = scaled.normalized();
+        assert!((normalized.magnitude() - 1.0).abs() <= EPSILON);
+        assert_transform_eq(normalized, motor);
+    }
+
+    #[test]
+    fn slerp_variants_take_complementary_arcs() {
+        use std::f32::consts::{FRAC_PI_4, PI};
+
+        let end = Transform::rotation_xy(1.5 * PI);
+        // 270° one way is 90° the other way
+        let shortest = Transform::IDENTITY.slerp_shortest(end, 0.5);
+        assert_transform_eq(shortest, Transform::rotation_xy(-FRAC_PI_4));
+        assert!((shortest.rotation_plane().1 - FRAC_PI_4).abs() <= 1e-4);
+        let long = Transform::IDENTITY.slerp_long(end, 0.5);
+        assert_transform_eq(long, Transform::rotation_xy(0.75 * PI));
+        assert!((long.rotation_plane().1 - 0.75 * PI).abs() <= 1e-4);
+
+        for t in [0.0, 1.0] {
+            assert_transform_eq(
+                Transform::IDENTITY.slerp_shortest(end, t),
+                Transform::IDENTITY.slerp_long(end, t),
+            );
+        }
+    }
+
+    #[test]
+    fn slerp_reaches_double_rotation_endpoints() {
+        let start = Transform::translation([1.0, 0.0, -2.0, 0.5]) * Transform::rotation_xw(0.3);
+        let end = Transform::translation([3.0, 1.0, 0.0, -0.5])
+            * Transform::rotation_xy(0.7)
+            * Transform::rotation_zw(0.4);
+        assert_transform_eq(
+            Transform::IDENTITY.slerp_shortest(
+                Transform::rotation_xy(0.7) * Transform::rotation_zw(0.4),
+                1.0,
+            ),
+            Transform::rotation_xy(0.7) * Transform::rotation_zw(0.4),
+        );
+        for slerp in [Transform::slerp_shortest, Transform::slerp_long] {
+            assert_transform_eq(slerp(start, end, 0.0), start);
+            assert_transform_eq(slerp(start, end, 1.0), end);
+        }
+
+        // halfway turns halfway in both planes
+        assert_transform_eq(
+            Transform::IDENTITY.slerp_shortest(end, 0.5),
+            Transform::translation([1.5, 0.5, 0.0, -0.25])
+                * Transform::rotation_xy(0.35)
+                * Transform::rotation_zw(0.2),
+        );
+    }
+
+    #[test]
+    fn compose_applies_the_last_transform_first() {
+        let a = Transform::rotation_xy(0.4);
+        let b = Transform::translation([1.0, 2.0, 0.0, -1.0]);
+        let c = Transform::rotation_zw(1.1);
+        assert_transform_eq(Transform::compose(&[a, b, c]), a * b * c);
+
+        // so composing [b, a] rotates a point before moving it
+        let point = [0.5, -1.0, 2.0, 0.0];
+        let composed = Transform::compose(&[b, a]).transform(point);
+        let expected = b.transform(a.transform(point));
+        for (composed, expected) in std::iter::zip(composed, expected) {
+            assert!(
+                (composed - expected).abs() <= EPSILON,
+                "{composed} != {expected}"
+            );
+        }
+
+        assert_eq!(Transform::compose(&[]), Transform::IDENTITY);
+        assert_eq!(Transform::compose(&[c]), c);
+    }
+
+    #[test]
+    fn negated_motors_are_the_same_rotation() {
+        let motor = Transform::translation([1.0, 0.0, -2.0, 0.5]) * Transform::rotation_xw(0.7);
+        let negated = Transform::from_components(motor.components().map(|x| -x));
+        assert_ne!(motor, negated);
+        assert!(motor.represents_same_rotation(negated, EPSILON));
+        assert!(negated.represents_same_rotation(motor, EPSILON));
+        // a full turn is the negated identity
+        assert!(Transform::rotation_yz(2.0 * std::f32::consts::PI)
+            .represents_same_rotation(Transform::IDENTITY, EPSILON));
+
+        assert!(!motor.represents_same_rotation(Transform::rotation_xw(0.7), EPSILON));
+        assert!(!Transform::rotation_xy(0.5)
+            .represents_same_rotation(Transform::rotation_xy(-0.5), EPSILON));
+    }
+
+    #[test]
+    fn random_rotations_are_unit_rotors() {
+        // a small xorshift generator keeps the test deterministic without a rand dependency
+        let mut state = 0x2545_f491_u32;
+        let mut sample = || {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            (state >> 8) as f32 / (1 << 24) as f32
+        };
+
+        let mut mean_forward = [0.0; 4];
+        let count = 1000;
+        for _ in 0..count {
+            let rotation = Transform::random(&mut sample);
+            assert!((rotation.magnitude() - 1.0).abs() <= 1e-4, "{rotation:?}");
+            assert_eq!(rotation.position(), [0.0; 4]);
+            assert!(
+                (rotation * !rotation).represents_same_rotation(Transform::IDENTITY, 1e-4),
+                "{rotation:?}"
+            );
+            let forward = rotation.transform_direction([1.0, 0.0, 0.0, 0.0]);
+            for axis in 0..4 {
+                mean_forward[axis] += forward[axis] / count as f32;
+            }
+        }
+        // rotations are spread out rather than bunched around one direction
+        for mean in mean_forward {
+            assert!(mean.abs() <= 0.1, "{mean_forward:?}");
+        }
+    }
+
+    #[test]
+    fn compose_chain_matches_folding_by_hand() {
+        let transforms = [
+            Transform::rotation_xy(0.3),
+            Transform::translation([0.0, 1.0, 0.0, 2.0]),
+            Transform::rotation_zw(-1.2),
+            Transform::rotation_xw(0.8) * Transform::translation([1.0, 0.0, 0.0, 0.0]),
+        ];
+        let chain = Transform::compose_chain(&transforms);
+        assert_eq!(chain.len(), transforms.len());
+
+        let mut folded = Transform::IDENTITY;
+        for (i, (&transform, &chained)) in std::iter::zip(&transforms, &chain).enumerate() {
+            folded = folded * transform;
+            assert_transform_eq(chained, folded);
+            assert_transform_eq(chained, Transform::compose(&transforms[..=i]));
+        }
+        assert!(Transform::compose_chain(&[]).is_empty());
+    }
+
+    #[test]
+    fn square_roots_square_back_to_the_rotation() {
+        use std::f32::consts::PI;
+
+        let rotations = [
+            Transform::IDENTITY,
+            Transform::rotation_xy(0.4),
+            Transform::rotation_zw(-2.0),
+            Transform::rotation_xz(PI - 1e-3),
+            Transform::rotation_yw(PI),
+            Transform::rotation_xy(PI) * Transform::rotation_zw(PI),
+            Transform::rotation_xy(1.0) * Transform::rotation_zw(PI - 1e-3),
+            Transform::rotation_xw(0.7) * Transform::rotation_yz(2.5),
+            Transform::translation([1.0, -2.0, 0.5, 3.0]) * Transform::rotation_xz(PI),
+        ];
+        for rotation in rotations {
+            let root = rotation.sqrt();
+            let squared = root * root;
+            assert!(
+                squared.represents_same_rotation(rotation, 1e-3),
+                "{rotation:?}: {squared:?}"
+            );
+        }
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "is not a unit motor")]
+    fn denormalized_motors_trip_the_debug_assertion() {
+        let rotation = Transform::rotation_xy(0.5);
+        let denormalized = Transform {
+            s: rotation.s * 2.0,
+            e12: rotation.e12 * 2.0,
+            ..rotation
+        };
+        denormalized.transform([1.0, 0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "is not finite")]
+    fn non_finite_products_trip_the_debug_assertion() {
+        let nan = Transform {
+            e01: f32::NAN,
+            ..Transform::IDENTITY
+        };
+        let _ = Transform::rotation_xy(0.5) * nan;
+    }
 }
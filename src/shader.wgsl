@@ -2,6 +2,18 @@
 @binding(0)
 var output_texture: texture_storage_2d<rgba8unorm, write>;
 
+@group(0)
+@binding(1)
+var<storage, read_write> accumulation: array<vec4<f32>>;
+
+@group(0)
+@binding(2)
+var depth_texture: texture_storage_2d<r32float, write>;
+
+@group(0)
+@binding(3)
+var<storage, read_write> luminance_sum: atomic<u32>;
+
 struct Camera {
     transform: Transform,
     v_fov: f32,
@@ -11,6 +23,10 @@ struct Camera {
 @binding(0)
 var<uniform> camera: Camera;
 
+@group(1)
+@binding(1)
+var<uniform> second_camera: Camera;
+
 struct Ray {
     origin: vec4<f32>,
     direction: vec4<f32>,
@@ -21,11 +37,13 @@ struct Hit {
     position: vec4<f32>,
     normal: vec4<f32>,
     color: vec3<f32>,
+    steps: u32,
 }
 
 struct Block {
     color: vec3<f32>,
     exists: u32,
+    health: u32,
 }
 
 struct Chunk {
@@ -36,9 +54,192 @@ struct Chunk {
 @binding(0)
 var<storage, read> chunk: Chunk;
 
+struct RenderSettings {
+    ambient_color: vec3<f32>,
+    ambient_strength: f32,
+    directional_light: u32,
+    sample_index: u32,
+    render_mode: u32,
+    split_view: u32,
+    split_screen: u32,
+    wireframe: u32,
+    voxel_margin: f32,
+    viewport_x: u32,
+    viewport_y: u32,
+    viewport_width: u32,
+    viewport_height: u32,
+    letterbox_color: vec3<f32>,
+    back_faces: u32,
+    outline_thickness: f32,
+    outline_darkness: f32,
+    world_border_radius: f32,
+    srgb_encode: u32,
+    sky_zenith_color: vec3<f32>,
+    sky_horizon_color: vec3<f32>,
+    sky_nadir_color: vec3<f32>,
+    tonemap_white_point: f32,
+    tonemap_contrast: f32,
+    exposure: f32,
+    measure_luminance: u32,
+    view_plane_gizmo: u32,
+    shadows: u32,
+    shadow_bias: f32,
+    aperture: f32,
+    focal_distance: f32,
+    custom_heatmap_gradient: u32,
+    selection_min: vec4<i32>,
+    selection_max: vec4<i32>,
+}
+
+@group(3)
+@binding(0)
+var<uniform> render_settings: RenderSettings;
+
+struct DebugPoint {
+    x: f32,
+    y: f32,
+    radius: f32,
+    color: vec3<f32>,
+}
+
+struct DebugPoints {
+    count: u32,
+    points: array<DebugPoint, MAX_DEBUG_POINTS>,
+}
+
+@group(3)
+@binding(1)
+var<storage, read> debug_points: DebugPoints;
+
+struct HeatmapGradient {
+    colors: array<vec3<f32>, HEATMAP_GRADIENT_SIZE>,
+}
+
+@group(3)
+@binding(2)
+var<storage, read> heatmap_gradient: HeatmapGradient;
+
+struct ViewPlaneLine {
+    start_x: f32,
+    start_y: f32,
+    end_x: f32,
+    end_y: f32,
+}
+
+struct ViewPlaneLines {
+    count: u32,
+    lines: array<ViewPlaneLine, MAX_VIEW_PLANE_LINES>,
+}
+
+@group(3)
+@binding(3)
+var<storage, read> view_plane_lines: ViewPlaneLines;
+
+const MAX_STEPS: u32 = 100u;
+const MAX_BLOCK_HEALTH: u32 = 4u;
+const RENDER_MODE_NORMALS: u32 = 1u;
+const RENDER_MODE_STEP_HEATMAP: u32 = 2u;
+const RENDER_MODE_CHUNK_TINT: u32 = 3u;
+const RENDER_MODE_DEPTH: u32 = 4u;
+const DEPTH_VIEW_DISTANCE: f32 = 32.0;
+const WIREFRAME_WIDTH: f32 = 0.05;
+const WORLD_BORDER_COLOR: vec3<f32> = vec3<f32>(0.3, 0.6, 1.0);
+const WORLD_BORDER_OPACITY: f32 = 0.3;
+const SELECTION_COLOR: vec3<f32> = vec3<f32>(0.2, 0.8, 1.0);
+const SELECTION_OPACITY: f32 = 0.4;
+const LUMINANCE_SCALE: f32 = 16.0;
+const MAX_DEBUG_POINTS: u32 = 64u;
+const HEATMAP_GRADIENT_SIZE: u32 = 32u;
+const VIEW_PLANE_GRID_COLOR: vec3<f32> = vec3<f32>(1.0, 0.3, 0.8);
+const VIEW_PLANE_GRID_OPACITY: f32 = 0.25;
+const VIEW_PLANE_LINE_WIDTH: f32 = 0.75;
+const MAX_VIEW_PLANE_LINES: u32 = 75u;
+const MAX_MEASURED_LUMINANCE: f32 = 16.0;
+
+fn near_cell_edge(position: vec4<f32>, voxel_pos: vec4<i32>, width: f32) -> bool {
+    let local = position - vec4<f32>(voxel_pos);
+    let near = vec4<u32>(min(local, 1.0 - local) < vec4<f32>(width));
+    return near.x + near.y + near.z + near.w >= 3u;
+}
+
+fn segment_distance(position: vec2<f32>, start: vec2<f32>, end: vec2<f32>) -> f32 {
+    let along = end - start;
+    let t = clamp(dot(position - start, along) / max(dot(along, along), 1e-6), 0.0, 1.0);
+    return distance(position, start + along * t);
+}
+
+fn sky_color(direction: vec4<f32>) -> vec3<f32> {
+    if direction.y >= 0.0 {
+        return mix(render_settings.sky_horizon_color, render_settings.sky_zenith_color, direction.y);
+    }
+    return mix(render_settings.sky_horizon_color, render_settings.sky_nadir_color, -direction.y);
+}
+
+fn heatmap_color(t: f32) -> vec3<f32> {
+    let position = clamp(t, 0.0, 1.0) * f32(HEATMAP_GRADIENT_SIZE - 1u);
+    let index = min(u32(position), HEATMAP_GRADIENT_SIZE - 2u);
+    return mix(heatmap_gradient.colors[index], heatmap_gradient.colors[index + 1u], position - f32(index));
+}
+
+fn tonemap(color: vec3<f32>) -> vec3<f32> {
+    if render_settings.tonemap_white_point <= 0.0 {
+        return color;
+    }
+    let curved = pow(max(color, vec3<f32>(0.0)), vec3<f32>(render_settings.tonemap_contrast));
+    let white = pow(render_settings.tonemap_white_point, render_settings.tonemap_contrast);
+    return curved * (1.0 + curved / (white * white)) / (1.0 + curved);
+}
+
+fn output_color(color: vec3<f32>) -> vec4<f32> {
+    var output = clamp(color, vec3<f32>(0.0), vec3<f32>(1.0));
+    if render_settings.srgb_encode != 0u {
+        let low = output * 12.92;
+        let high = 1.055 * pow(output, vec3<f32>(1.0 / 2.4)) - 0.055;
+        output = select(high, low, output <= vec3<f32>(0.0031308));
+    }
+    return vec4<f32>(output, 1.0);
+}
+
+fn outline_factor(position: vec4<f32>, normal: vec4<f32>) -> f32 {
+    let local = fract(position);
+    let edge_distances = select(min(local, 1.0 - local), vec4<f32>(1.0), normal != vec4<f32>(0.0));
+    let edge_distance = min(min(min(edge_distances.x, edge_distances.y), edge_distances.z), edge_distances.w);
+    if edge_distance < render_settings.outline_thickness {
+        return 1.0 - render_settings.outline_darkness;
+    }
+    return 1.0;
+}
+
+fn world_border_distance(ray: Ray, radius: f32) -> f32 {
+    let b = dot(ray.origin, ray.direction);
+    let c = dot(ray.origin, ray.origin) - radius * radius;
+    let discriminant = b * b - c;
+    if discriminant < 0.0 {
+        return -1.0;
+    }
+    let root = sqrt(discriminant);
+    if -b - root > 0.0 {
+        return -b - root;
+    }
+    return -b + root;
+}
+
+fn chunk_index(voxel_pos: vec4<i32>) -> i32 {
+    if all(voxel_pos >= vec4<i32>(0)) && all(voxel_pos < vec4<i32>(4)) {
+        return voxel_pos.x + voxel_pos.y * 4 + voxel_pos.z * 4 * 4 + voxel_pos.w * 4 * 4 * 4;
+    }
+    return -1;
+}
+
+fn block_color(index: i32) -> vec3<f32> {
+    let health = f32(chunk.data[index].health) / f32(MAX_BLOCK_HEALTH);
+    return chunk.data[index].color * mix(0.4, 1.0, clamp(health, 0.0, 1.0));
+}
+
 fn trace_ray(ray: Ray) -> Hit {
     var hit: Hit;
     hit.hit = false;
+    hit.steps = MAX_STEPS;
 
     let step_sizes = 1.0 / abs(ray.direction);
     let step_dir = vec4<i32>(sign(ray.direction));
@@ -46,7 +247,8 @@ fn trace_ray(ray: Ray) -> Hit {
 
     var curr_pos = ray.origin;
     var voxel_pos = vec4<i32>(floor(curr_pos));
-    for (var i = 0u; i < 100u; i += 1u) {
+    var previous_index = chunk_index(voxel_pos);
+    for (var i = 0u; i < MAX_STEPS; i += 1u) {
         let closest_dist = min(min(min(next_dist.x, next_dist.y), next_dist.z), next_dist.w);
         curr_pos += ray.direction * closest_dist;
         let step_axis = vec4<i32>(next_dist == vec4<f32>(closest_dist));
@@ -54,13 +256,45 @@ fn trace_ray(ray: Ray) -> Hit {
         next_dist -= closest_dist;
         next_dist += step_sizes * vec4<f32>(step_axis);
 
-        if all(voxel_pos >= vec4<i32>(0)) && all(voxel_pos < vec4<i32>(4)) {
-            let index = u32(voxel_pos.x) + u32(voxel_pos.y) * 4 + u32(voxel_pos.z) * 4 * 4 + u32(voxel_pos.w) * 4 * 4 * 4;
-            if chunk.data[index].exists != 0 {
+        let index = chunk_index(voxel_pos);
+        let exists = index >= 0 && chunk.data[index].exists != 0;
+        let previous_exists = previous_index >= 0 && chunk.data[previous_index].exists != 0;
+        if render_settings.back_faces != 0u && previous_exists && !exists {
+            hit.hit = true;
+            hit.position = curr_pos;
+            hit.normal = vec4<f32>(-step_axis * step_dir);
+            hit.color = block_color(previous_index);
+            hit.steps = i + 1u;
+            return hit;
+        }
+        previous_index = index;
+
+        if exists {
+            let visible = render_settings.wireframe == 0u || near_cell_edge(curr_pos, voxel_pos, WIREFRAME_WIDTH);
+            if visible {
+                var position = curr_pos;
+                var normal = vec4<f32>(-step_axis * step_dir);
+                if render_settings.voxel_margin > 0.0 {
+                    let block_min = vec4<f32>(voxel_pos) + render_settings.voxel_margin;
+                    let block_max = vec4<f32>(voxel_pos) + 1.0 - render_settings.voxel_margin;
+                    let t0 = (block_min - curr_pos) / ray.direction;
+                    let t1 = (block_max - curr_pos) / ray.direction;
+                    let t_min = min(t0, t1);
+                    let t_max = max(t0, t1);
+                    let t_near = max(max(max(t_min.x, t_min.y), t_min.z), t_min.w);
+                    let t_far = min(min(min(t_max.x, t_max.y), t_max.z), t_max.w);
+                    if t_near > t_far || t_far < 0.0 {
+                        continue;
+                    }
+                    position = curr_pos + ray.direction * max(t_near, 0.0);
+                    normal = vec4<f32>(vec4<i32>(t_min == vec4<f32>(t_near)) * -step_dir);
+                }
+
                 hit.hit = true;
-                hit.position = curr_pos;
-                hit.normal = vec4<f32>(-step_axis * step_dir);
-                hit.color = chunk.data[index].color;
+                hit.position = position;
+                hit.normal = normal;
+                hit.color = block_color(index);
+                hit.steps = i + 1u;
                 return hit;
             }
         }
@@ -69,6 +303,13 @@ fn trace_ray(ray: Ray) -> Hit {
     return hit;
 }
 
+fn in_shadow(hit: Hit, sun_direction: vec4<f32>) -> bool {
+    var shadow_ray: Ray;
+    shadow_ray.origin = hit.position + hit.normal * render_settings.shadow_bias;
+    shadow_ray.direction = -sun_direction;
+    return trace_ray(shadow_ray).hit;
+}
+
 @compute
 @workgroup_size(16, 16)
 fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
@@ -79,21 +320,148 @@ fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
         return;
     }
 
-    let theta = tan(camera.v_fov / 2.0);
-    let aspect = f32(size.x) / f32(size.y);
-    let normalized_uv = vec2<f32>(f32(coords.x) / f32(size.x), 1.0 - (f32(coords.y) / f32(size.y))) * 2.0 - 1.0;
+    let viewport_min = vec2<u32>(render_settings.viewport_x, render_settings.viewport_y);
+    let viewport_size = vec2<u32>(render_settings.viewport_width, render_settings.viewport_height);
+    if any(coords < viewport_min) || any(coords >= viewport_min + viewport_size) {
+        textureStore(output_texture, coords, output_color(render_settings.letterbox_color));
+        textureStore(depth_texture, coords, vec4<f32>(-1.0));
+        return;
+    }
+
+    var view_size = viewport_size;
+    var view_coords = coords - viewport_min;
+    var slice = false;
+    var view_camera = camera;
+    if render_settings.split_view != 0u || render_settings.split_screen != 0u {
+        view_size.x = viewport_size.x / 2u;
+        if view_coords.x >= view_size.x {
+            view_coords.x -= view_size.x;
+            slice = render_settings.split_view != 0u;
+            if render_settings.split_screen != 0u {
+                view_camera = second_camera;
+            }
+        }
+    }
+
+    let theta = tan(view_camera.v_fov / 2.0);
+    let aspect = f32(view_size.x) / f32(view_size.y);
+    let jitter = fract(f32(render_settings.sample_index) * vec2<f32>(0.7548776662, 0.5698402910));
+    let pixel = vec2<f32>(view_coords) + jitter;
+    let normalized_uv = vec2<f32>(pixel.x / f32(view_size.x), 1.0 - (pixel.y / f32(view_size.y))) * 2.0 - 1.0;
 
     var ray: Ray;
-    ray.origin = transform(camera.transform, vec4<f32>(0.0, 0.0, 0.0, 0.0));
-    ray.direction = normalize(transform_direction(camera.transform, vec4<f32>(1.0 + 0.0001, normalized_uv.y * theta + 0.0001, normalized_uv.x * aspect * theta + 0.0001, 0.0001)));
+    ray.origin = transform(view_camera.transform, vec4<f32>(0.0, 0.0, 0.0, 0.0));
+    ray.direction = normalize(transform_direction(view_camera.transform, vec4<f32>(1.0 + 0.0001, normalized_uv.y * theta + 0.0001, normalized_uv.x * aspect * theta + 0.0001, 0.0001)));
+    if slice {
+        ray.direction = normalize(vec4<f32>(ray.direction.xyz, 0.0001));
+    }
+    if render_settings.aperture > 0.0 {
+        let focus_point = ray.origin + ray.direction * render_settings.focal_distance;
+        let lens_sample = fract(f32(render_settings.sample_index) * vec2<f32>(0.3819660113, 0.7548776662));
+        let lens_angle = lens_sample.x * 6.2831853;
+        let lens_radius = sqrt(lens_sample.y) * render_settings.aperture;
+        ray.origin += transform_direction(view_camera.transform, vec4<f32>(0.0, cos(lens_angle), sin(lens_angle), 0.0) * lens_radius);
+        ray.direction = normalize(focus_point - ray.origin);
+    }
 
-    var color = vec3<f32>(0.0);
+    var color = sky_color(ray.direction);
     let hit = trace_ray(ray);
+    var depth = -1.0;
+    if hit.hit {
+        depth = distance(ray.origin, hit.position);
+    }
+    textureStore(depth_texture, coords, vec4<f32>(depth));
+    if hit.hit {
+        var diffuse = 0.0;
+        if render_settings.directional_light != 0u {
+            let sun_direction = normalize(vec4<f32>(0.3, -1.0, 0.2, 0.1));
+            let sun_dot = dot(hit.normal, -sun_direction);
+            diffuse = max(sun_dot * 0.5 + 0.5, 0.2);
+            if render_settings.shadows != 0u && sun_dot > 0.0 && in_shadow(hit, sun_direction) {
+                diffuse = 0.5;
+            }
+        }
+        let ambient = render_settings.ambient_color * render_settings.ambient_strength * (hit.normal.w * 0.25 + 0.75);
+        color = hit.color * outline_factor(hit.position, hit.normal) * (diffuse + ambient);
+    }
+
     if hit.hit {
-        let sun_direction = normalize(vec4<f32>(0.3, -1.0, 0.2, 0.1));
-        color = hit.color * max((dot(hit.normal, -sun_direction) * 0.5 + 0.5), 0.2);
+        let block = vec4<i32>(floor(hit.position - hit.normal * 0.5));
+        if all(block >= render_settings.selection_min) && all(block < render_settings.selection_max) {
+            color = mix(color, SELECTION_COLOR, SELECTION_OPACITY);
+        }
+    }
+
+    if render_settings.world_border_radius > 0.0 {
+        let border_distance = world_border_distance(ray, render_settings.world_border_radius);
+        if border_distance > 0.0 && (depth < 0.0 || border_distance < depth) {
+            color = mix(color, WORLD_BORDER_COLOR, WORLD_BORDER_OPACITY);
+        }
+    }
+
+    switch render_settings.render_mode {
+        case RENDER_MODE_NORMALS: {
+            if hit.hit {
+                color = hit.normal.xyz * 0.5 + 0.5 + hit.normal.w * vec3<f32>(0.5, -0.5, 0.5);
+            }
+        }
+        case RENDER_MODE_STEP_HEATMAP: {
+            let t = f32(hit.steps) / f32(MAX_STEPS);
+            if render_settings.custom_heatmap_gradient != 0u {
+                color = heatmap_color(t);
+            } else {
+                color = mix(vec3<f32>(0.0, 1.0, 0.0), vec3<f32>(1.0, 0.0, 0.0), t);
+            }
+        }
+        case RENDER_MODE_CHUNK_TINT: {
+            if hit.hit {
+                let chunk_pos = vec4<i32>(floor(hit.position / 4.0));
+                let tint = fract(vec3<f32>(chunk_pos.xyz + chunk_pos.w) * vec3<f32>(0.618034, 0.381966, 0.723607));
+                color *= mix(vec3<f32>(0.5), vec3<f32>(1.0), tint);
+            }
+        }
+        case RENDER_MODE_DEPTH: {
+            var t = 1.0;
+            if hit.hit {
+                t = clamp(depth / DEPTH_VIEW_DISTANCE, 0.0, 1.0);
+            }
+            if render_settings.custom_heatmap_gradient != 0u {
+                color = heatmap_color(t);
+            } else {
+                color = vec3<f32>(1.0 - t);
+            }
+        }
+        default: {}
+    }
+
+    let accumulation_index = coords.y * size.x + coords.x;
+    let previous = accumulation[accumulation_index].rgb;
+    color = mix(previous, color, 1.0 / f32(render_settings.sample_index + 1u));
+    accumulation[accumulation_index] = vec4<f32>(color, 1.0);
+    if render_settings.measure_luminance != 0u {
+        let luminance = clamp(dot(color, vec3<f32>(0.2126, 0.7152, 0.0722)), 0.0, MAX_MEASURED_LUMINANCE);
+        atomicAdd(&luminance_sum, u32(luminance * LUMINANCE_SCALE));
+    }
+    var output = tonemap(color * render_settings.exposure);
+    for (var i = 0u; i < min(debug_points.count, MAX_DEBUG_POINTS); i += 1u) {
+        let point = debug_points.points[i];
+        let offset = abs(vec2<f32>(coords) + 0.5 - vec2<f32>(point.x, point.y));
+        if max(offset.x, offset.y) <= point.radius && min(offset.x, offset.y) <= 1.0 {
+            output = point.color;
+        }
+    }
+    if render_settings.view_plane_gizmo != 0u {
+        for (var i = 0u; i < min(view_plane_lines.count, MAX_VIEW_PLANE_LINES); i += 1u) {
+            let line = view_plane_lines.lines[i];
+            let start = vec2<f32>(line.start_x, line.start_y);
+            let end = vec2<f32>(line.end_x, line.end_y);
+            if segment_distance(vec2<f32>(coords) + 0.5, start, end) <= VIEW_PLANE_LINE_WIDTH {
+                output = mix(output, VIEW_PLANE_GRID_COLOR, VIEW_PLANE_GRID_OPACITY);
+                break;
+            }
+        }
     }
-    textureStore(output_texture, coords, vec4<f32>(clamp(color, vec3<f32>(0.0), vec3<f32>(1.0)), 1.0));
+    textureStore(output_texture, coords, output_color(output));
 }
 
 struct Transform {